@@ -0,0 +1,67 @@
+// Draws each ant as a triangle over its cell, pointing the way it is heading.
+
+struct Camera {
+    scale: f32,
+    x: f32,
+    y: f32
+}
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+struct Ant {
+    x: u32,
+    y: u32,
+    heading: u32,
+    state: u32,
+}
+
+@group(2) @binding(0)
+var<storage, read> ants: array<Ant>;
+
+// Corners of a triangle pointing north, in cells from the centre of the cell
+// with y going down the board.
+const CORNERS = array<vec2<f32>, 3>(
+    vec2<f32>(0.0, -0.5),
+    vec2<f32>(-0.4, 0.4),
+    vec2<f32>(0.4, 0.4),
+);
+
+@vertex
+fn vs_agent(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> @builtin(position) vec4<f32> {
+    let ant = ants[instance_index];
+    let corner = CORNERS[vertex_index];
+
+    // Quarter turns clockwise, which is the same on the board since y points
+    // down.
+    var rotated = corner;
+    switch ant.heading {
+        case 1u: {
+            rotated = vec2<f32>(-corner.y, corner.x);
+        }
+        case 2u: {
+            rotated = -corner;
+        }
+        case 3u: {
+            rotated = vec2<f32>(corner.y, -corner.x);
+        }
+        default: {}
+    }
+
+    // Same mapping as `vs_main`, the board is a quad from -0.5 to 0.5.
+    let size = vec2<f32>(textureDimensions(t_diffuse));
+    let cell = (vec2<f32>(f32(ant.x), f32(ant.y)) + 0.5 + rotated) / size;
+    let position = vec2<f32>(cell.x - 0.5, 0.5 - cell.y);
+    return vec4<f32>((position + vec2<f32>(camera.x, camera.y)) * camera.scale, 0.0, 1.0);
+}
+
+@fragment
+fn fs_agent() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.2, 0.2, 1.0);
+}
@@ -0,0 +1,380 @@
+use std::fmt;
+
+use anyhow::*;
+
+/// Largest kernel radius `continuous.wgsl` can fit in its shared memory tile.
+pub const MAX_RADIUS: u32 = 24;
+
+/// One cell of a convolution kernel, laid out as `Tap` in `continuous.wgsl`.
+/// SmoothLife uses both weights, for its inner disk and outer ring, Lenia
+/// only the outer one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tap {
+    pub dx: i32,
+    pub dy: i32,
+    pub inner: f32,
+    pub outer: f32,
+}
+
+/// Lenia, a continuous generalisation of Life. Cells take values between 0
+/// and 1 and grow or shrink by a Gaussian of their kernel weighted
+/// neighbourhood, e.g. `lenia:R=13,mu=0.15,sigma=0.015,dt=0.1,b=1`, which is
+/// the rule Orbium lives in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeniaRule {
+    pub radius: u32,
+    /// Centre of the growth function.
+    pub mu: f32,
+    /// Width of the growth function.
+    pub sigma: f32,
+    /// Time step, how much of the growth is applied each generation.
+    pub dt: f32,
+    /// Heights of the concentric rings of the kernel, from the inside out.
+    pub peaks: Vec<f32>,
+}
+
+/// Rafler's SmoothLife, which compares the filling of a disk around each cell
+/// with the filling of the ring around that, e.g.
+/// `smoothlife:ra=21,b1=0.278,b2=0.365,d1=0.267,d2=0.445,dt=0.05`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmoothLifeRule {
+    /// Outer radius of the ring, the disk is a third of it.
+    pub radius: u32,
+    /// Birth interval of the ring's filling.
+    pub birth: (f32, f32),
+    /// Death interval of the ring's filling, cells outside it die.
+    pub death: (f32, f32),
+    pub dt: f32,
+}
+
+impl Default for LeniaRule {
+    fn default() -> Self {
+        return LeniaRule {
+            radius: 13,
+            mu: 0.15,
+            sigma: 0.015,
+            dt: 0.1,
+            peaks: vec![1.0],
+        };
+    }
+}
+
+impl Default for SmoothLifeRule {
+    fn default() -> Self {
+        return SmoothLifeRule {
+            radius: 21,
+            birth: (0.278, 0.365),
+            death: (0.267, 0.445),
+            dt: 0.05,
+        };
+    }
+}
+
+/// Splits `name:key=value,...` into its key value pairs, `name` alone gives
+/// none. Keys without a value, used as flags, get an empty one.
+pub(super) fn parameters<'a>(rule: &'a str, name: &str) -> Result<Vec<(String, &'a str)>> {
+    if rule.len() < name.len() || !rule[..name.len()].eq_ignore_ascii_case(name) {
+        bail!("expected a {name} rule, got {rule}");
+    }
+    let rest = rule[name.len()..].trim();
+    let Some(rest) = rest.strip_prefix(':') else {
+        if !rest.is_empty() {
+            bail!("expected {name}:key=value,..., got {rule}");
+        }
+        return Ok(Vec::new());
+    };
+
+    return Ok(rest
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            return (key.trim().to_ascii_lowercase(), value.trim());
+        })
+        .collect());
+}
+
+fn check_radius(radius: u32) -> Result<u32> {
+    if radius == 0 || radius > MAX_RADIUS {
+        bail!("radius must be between 1 and {MAX_RADIUS}, got {radius}");
+    }
+    return Ok(radius);
+}
+
+impl LeniaRule {
+    pub fn parse(rule: &str) -> Result<Self> {
+        let mut lenia = LeniaRule::default();
+        for (key, value) in parameters(rule, "lenia")? {
+            match key.as_str() {
+                "r" => lenia.radius = check_radius(value.parse()?)?,
+                "mu" | "m" => lenia.mu = value.parse()?,
+                "sigma" | "s" => lenia.sigma = value.parse()?,
+                "dt" | "t" => lenia.dt = value.parse()?,
+                // Ring heights are separated by slashes, e.g. `b=1/0.5`.
+                "b" => {
+                    lenia.peaks = value
+                        .split('/')
+                        .map(|peak| peak.trim().parse::<f32>())
+                        .collect::<Result<_, _>>()?;
+                }
+                _ => bail!("unknown Lenia parameter {key}"),
+            }
+        }
+        if lenia.sigma <= 0.0 {
+            bail!("sigma must be positive");
+        }
+        if lenia.kernel().is_empty() {
+            bail!("{lenia} has an empty kernel, it needs a radius over 1 and a ring above 0");
+        }
+        return Ok(lenia);
+    }
+
+    /// The kernel, a bump on each ring normalised to sum to 1.
+    pub fn kernel(&self) -> Vec<Tap> {
+        let radius = self.radius as i32;
+        let mut taps = Vec::new();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt() / self.radius as f32;
+                if distance >= 1.0 {
+                    continue;
+                }
+                let ring = distance * self.peaks.len() as f32;
+                let peak = self.peaks[ring as usize];
+                let weight = peak * bump(ring.fract());
+                if weight > 0.0 {
+                    taps.push(Tap {
+                        dx,
+                        dy,
+                        inner: 0.0,
+                        outer: weight,
+                    });
+                }
+            }
+        }
+
+        let total: f32 = taps.iter().map(|tap| tap.outer).sum();
+        for tap in &mut taps {
+            tap.outer /= total;
+        }
+        return taps;
+    }
+}
+
+/// Lenia's exponential bump, 1 in the middle of `(0, 1)` and 0 at its ends.
+fn bump(x: f32) -> f32 {
+    if x <= 0.0 || x >= 1.0 {
+        return 0.0;
+    }
+    return (4.0 - 1.0 / (x * (1.0 - x))).exp();
+}
+
+impl SmoothLifeRule {
+    pub fn parse(rule: &str) -> Result<Self> {
+        let mut smooth = SmoothLifeRule::default();
+        for (key, value) in parameters(rule, "smoothlife")? {
+            match key.as_str() {
+                "ra" | "r" => smooth.radius = check_radius(value.parse()?)?,
+                "b1" => smooth.birth.0 = value.parse()?,
+                "b2" => smooth.birth.1 = value.parse()?,
+                "d1" => smooth.death.0 = value.parse()?,
+                "d2" => smooth.death.1 = value.parse()?,
+                "dt" => smooth.dt = value.parse()?,
+                _ => bail!("unknown SmoothLife parameter {key}"),
+            }
+        }
+        if smooth.radius < 3 {
+            bail!("SmoothLife needs a radius of at least 3");
+        }
+        return Ok(smooth);
+    }
+
+    /// The disk and the ring around it, each normalised to sum to 1. Cells
+    /// the edges pass through count partially so the shapes stay round.
+    pub fn kernel(&self) -> Vec<Tap> {
+        let outer_radius = self.radius as f32;
+        let inner_radius = outer_radius / 3.0;
+        let radius = self.radius as i32;
+        let mut taps = Vec::new();
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                let distance = ((dx * dx + dy * dy) as f32).sqrt();
+                let inner = (inner_radius + 0.5 - distance).clamp(0.0, 1.0);
+                let outer = (outer_radius + 0.5 - distance).clamp(0.0, 1.0) - inner;
+                if inner > 0.0 || outer > 0.0 {
+                    taps.push(Tap {
+                        dx,
+                        dy,
+                        inner,
+                        outer,
+                    });
+                }
+            }
+        }
+
+        let inner_total: f32 = taps.iter().map(|tap| tap.inner).sum();
+        let outer_total: f32 = taps.iter().map(|tap| tap.outer).sum();
+        for tap in &mut taps {
+            tap.inner /= inner_total;
+            tap.outer /= outer_total;
+        }
+        return taps;
+    }
+}
+
+/// Layout of the `Rule` struct in `continuous.wgsl`.
+fn params(kind: u32, radius: u32, dt: f32, constants: [f32; 4], kernel: &[Tap]) -> Vec<u32> {
+    let mut params = vec![kind, radius, kernel.len() as u32, dt.to_bits()];
+    params.extend(constants.map(f32::to_bits));
+    for tap in kernel {
+        params.extend([
+            tap.dx as u32,
+            tap.dy as u32,
+            tap.inner.to_bits(),
+            tap.outer.to_bits(),
+        ]);
+    }
+    return params;
+}
+
+impl LeniaRule {
+    pub fn params(&self) -> Vec<u32> {
+        return params(
+            0,
+            self.radius,
+            self.dt,
+            [self.mu, self.sigma, 0.0, 0.0],
+            &self.kernel(),
+        );
+    }
+}
+
+impl SmoothLifeRule {
+    pub fn params(&self) -> Vec<u32> {
+        return params(
+            1,
+            self.radius,
+            self.dt,
+            [self.birth.0, self.birth.1, self.death.0, self.death.1],
+            &self.kernel(),
+        );
+    }
+}
+
+impl fmt::Display for LeniaRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let peaks: Vec<String> = self.peaks.iter().map(f32::to_string).collect();
+        return write!(
+            f,
+            "lenia:R={},mu={},sigma={},dt={},b={}",
+            self.radius,
+            self.mu,
+            self.sigma,
+            self.dt,
+            peaks.join("/")
+        );
+    }
+}
+
+impl fmt::Display for SmoothLifeRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "smoothlife:ra={},b1={},b2={},d1={},d2={},dt={}",
+            self.radius, self.birth.0, self.birth.1, self.death.0, self.death.1, self.dt
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let lenia = LeniaRule::parse("Lenia:R=13,mu=0.15,sigma=0.015,dt=0.1,b=1").unwrap();
+        assert_eq!(lenia, LeniaRule::default());
+        assert_eq!(LeniaRule::parse(&lenia.to_string()).unwrap(), lenia);
+        let rings = LeniaRule::parse("lenia:r=10,m=0.3,s=0.05,t=0.2,b=1/0.5").unwrap();
+        assert_eq!(rings.peaks, [1.0, 0.5]);
+        assert_eq!(LeniaRule::parse(&rings.to_string()).unwrap(), rings);
+
+        let smooth = SmoothLifeRule::parse("smoothlife").unwrap();
+        assert_eq!(smooth, SmoothLifeRule::default());
+        assert_eq!(SmoothLifeRule::parse(&smooth.to_string()).unwrap(), smooth);
+        let smooth = SmoothLifeRule::parse("SmoothLife:r=6,b1=0.2,dt=0.1").unwrap();
+        assert_eq!((smooth.radius, smooth.birth.0, smooth.dt), (6, 0.2, 0.1));
+    }
+
+    #[test]
+    fn rejects_rules_it_cant_run() {
+        for rule in [
+            "lenia:r=0",
+            "lenia:r=25",
+            "lenia:sigma=0",
+            "lenia:mu=x",
+            "lenia:x=1",
+            "lenia:r=1",
+            "lenia:b=0",
+            "lenia:b=0/0",
+            "lenia 13",
+            "smoothlife:ra=2",
+            "smoothlife:ra=25",
+            "smoothlife:x=1",
+        ] {
+            assert!(
+                LeniaRule::parse(rule).is_err() && SmoothLifeRule::parse(rule).is_err(),
+                "{rule}"
+            );
+        }
+    }
+
+    #[test]
+    fn kernels_are_normalised_and_fit_the_radius() {
+        for lenia in ["lenia:r=2", "lenia", "lenia:r=24,b=1/0.5/0.25"] {
+            let lenia = LeniaRule::parse(lenia).unwrap();
+            let kernel = lenia.kernel();
+            let total: f32 = kernel.iter().map(|tap| tap.outer).sum();
+            assert!((total - 1.0).abs() < 1e-4);
+            let radius = lenia.radius as i32;
+            for tap in kernel {
+                assert!(tap.dx.abs() < radius && tap.dy.abs() < radius);
+                assert!(tap.inner == 0.0 && tap.outer > 0.0);
+                // The bump is 0 at the centre, where the innermost ring starts.
+                assert_ne!((tap.dx, tap.dy), (0, 0));
+            }
+        }
+
+        let kernel = SmoothLifeRule::default().kernel();
+        let inner: f32 = kernel.iter().map(|tap| tap.inner).sum();
+        let outer: f32 = kernel.iter().map(|tap| tap.outer).sum();
+        assert!((inner - 1.0).abs() < 1e-4 && (outer - 1.0).abs() < 1e-4);
+        let tap = |offset| {
+            kernel
+                .iter()
+                .find(|tap| (tap.dx, tap.dy) == offset)
+                .unwrap()
+        };
+        assert!(tap((0, 0)).inner > 0.0 && tap((0, 0)).outer == 0.0);
+        assert!(tap((21, 0)).inner == 0.0 && tap((21, 0)).outer > 0.0);
+        assert!(kernel
+            .iter()
+            .all(|tap| tap.dx.abs() <= 21 && tap.dy.abs() <= 21));
+    }
+
+    #[test]
+    fn lists_the_kernel_after_the_constants() {
+        let lenia = LeniaRule::parse("lenia:r=2").unwrap();
+        let params = lenia.params();
+        let taps = lenia.kernel().len();
+        assert_eq!(params[..3], [0, 2, taps as u32]);
+        assert_eq!(params.len(), 8 + 4 * taps);
+        assert_eq!(f32::from_bits(params[4]), lenia.mu);
+
+        let smooth = SmoothLifeRule::default();
+        let params = smooth.params();
+        assert_eq!(params[..2], [1, 21]);
+        assert_eq!(f32::from_bits(params[7]), smooth.death.1);
+    }
+}
@@ -0,0 +1,107 @@
+// Continuous rules, Lenia and SmoothLife. Cells hold a value between 0 and 1
+// in a float texture that wraps around at its edges.
+//
+// Each workgroup copies its 16x16 block of cells plus an apron of R cells
+// into shared memory, then every cell convolves the kernel taps against the
+// tile instead of reading the texture for each of them.
+
+@group(0) @binding(0)
+var t_field: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<r32float, write>;
+
+struct Tap {
+    offset: vec2<i32>,
+    inner: f32,
+    outer: f32,
+}
+
+struct Rule {
+    // 0 for Lenia, 1 for SmoothLife.
+    kind: u32,
+    radius: u32,
+    taps: u32,
+    dt: f32,
+    // Lenia's mu and sigma, or SmoothLife's b1, b2, d1 and d2.
+    constants: vec4<f32>,
+    kernel: array<Tap>,
+}
+
+@group(2) @binding(0)
+var<storage, read> rule: Rule;
+
+const TILE: u32 = 16u;
+const MAX_RADIUS: u32 = 24u;
+const STRIDE: u32 = TILE + 2u * MAX_RADIUS;
+
+var<workgroup> tile: array<f32, STRIDE * STRIDE>;
+
+fn value(position: vec2<i32>) -> f32 {
+    let size = vec2<i32>(textureDimensions(t_field));
+    let wrapped = (position % size + size) % size;
+    return textureLoad(t_field, wrapped, 0).x;
+}
+
+// Smooth step from 0 to 1 around `a`, `alpha` wide.
+fn sigmoid(x: f32, a: f32, alpha: f32) -> f32 {
+    return 1.0 / (1.0 + exp(-(x - a) * 4.0 / alpha));
+}
+
+fn smooth_life(n: f32, m: f32) -> f32 {
+    let alpha_n = 0.028;
+    let alpha_m = 0.147;
+    let aliveness = sigmoid(m, 0.5, alpha_m);
+    let low = mix(rule.constants.x, rule.constants.z, aliveness);
+    let high = mix(rule.constants.y, rule.constants.w, aliveness);
+    return sigmoid(n, low, alpha_n) * (1.0 - sigmoid(n, high, alpha_n));
+}
+
+@compute
+@workgroup_size(16, 16, 1)
+fn cm_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let radius = rule.radius;
+    let span = TILE + 2u * radius;
+    let origin = vec2<i32>(workgroup_id.xy * TILE) - vec2<i32>(i32(radius));
+
+    for (var i = local_index; i < span * span; i += TILE * TILE) {
+        let tx = i % span;
+        let ty = i / span;
+        tile[ty * STRIDE + tx] = value(origin + vec2<i32>(i32(tx), i32(ty)));
+    }
+    workgroupBarrier();
+
+    let size = textureDimensions(t_field);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+
+    // Position of the cell inside the tile, including the apron.
+    let local = vec2<i32>(global_invocation_id.xy - workgroup_id.xy * TILE + vec2<u32>(radius));
+    var inner = 0.0;
+    var outer = 0.0;
+    for (var i = 0u; i < rule.taps; i++) {
+        let tap = rule.kernel[i];
+        let position = local + tap.offset;
+        let neighbour = tile[u32(position.y) * STRIDE + u32(position.x)];
+        inner += tap.inner * neighbour;
+        outer += tap.outer * neighbour;
+    }
+
+    let current = tile[u32(local.y) * STRIDE + u32(local.x)];
+    var growth = 0.0;
+    if rule.kind == 0u {
+        let mu = rule.constants.x;
+        let sigma = rule.constants.y;
+        growth = 2.0 * exp(-(outer - mu) * (outer - mu) / (2.0 * sigma * sigma)) - 1.0;
+    } else {
+        growth = 2.0 * smooth_life(outer, inner) - 1.0;
+    }
+    let next = clamp(current + rule.dt * growth, 0.0, 1.0);
+
+    textureStore(t_output, vec2<i32>(global_invocation_id.xy), vec4<f32>(next, 0.0, 0.0, 1.0));
+}
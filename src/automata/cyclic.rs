@@ -0,0 +1,161 @@
+use std::fmt;
+
+use anyhow::*;
+
+use super::ltl::Neighbourhood;
+
+/// Largest range `cyclic.wgsl` counts over, it reads every neighbour from
+/// the texture.
+pub const MAX_RANGE: u32 = 10;
+
+/// A cyclic automaton in the notation of MCell, e.g. `R1/T3/C3/NM`, with
+/// the range, threshold, number of states and neighbourhood. A cell advances
+/// to the next state, wrapping around to 0, when at least the threshold of
+/// its neighbours are already in it.
+///
+/// Ending in `/GH` makes it a Greenberg-Hastings model of excitable media
+/// instead, e.g. `R1/T3/C5/NM/GH`. State 0 is resting and becomes excited, 1,
+/// when at least the threshold of its neighbours are excited, and every
+/// other state advances on its own through the refractory states back to
+/// resting.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CyclicRule {
+    pub range: u32,
+    pub threshold: u32,
+    pub states: u32,
+    pub neighbourhood: Neighbourhood,
+    pub greenberg_hastings: bool,
+}
+
+impl CyclicRule {
+    pub fn parse(rule: &str) -> Result<Self> {
+        let mut range = 1;
+        let mut threshold = 1;
+        let mut states = 3;
+        let mut neighbourhood = Neighbourhood::Moore;
+        let mut greenberg_hastings = false;
+
+        for token in rule.split('/').map(str::trim) {
+            if token.eq_ignore_ascii_case("GH") {
+                greenberg_hastings = true;
+                continue;
+            }
+            let (key, value) = token.split_at(token.chars().next().map_or(0, char::len_utf8));
+            match key.to_ascii_uppercase().as_str() {
+                "R" => range = parse_number(value)?,
+                "T" => threshold = parse_number(value)?,
+                "C" => states = parse_number(value)?,
+                "N" => {
+                    neighbourhood = match value.to_ascii_uppercase().as_str() {
+                        "M" => Neighbourhood::Moore,
+                        "N" => Neighbourhood::VonNeumann,
+                        "C" => Neighbourhood::Circular,
+                        _ => bail!("unknown neighbourhood: {value}"),
+                    }
+                }
+                _ => bail!("unexpected token in cyclic rule: {token}"),
+            }
+        }
+
+        if range == 0 || range > MAX_RANGE {
+            bail!("range must be between 1 and {MAX_RANGE}, got {range}");
+        }
+        if threshold == 0 {
+            bail!("threshold must be at least 1");
+        }
+        if states < 2 || states > crate::palette::MAX_STATES as u32 {
+            bail!(
+                "cyclic rules need between 2 and {} states, got {states}",
+                crate::palette::MAX_STATES
+            );
+        }
+
+        return Ok(CyclicRule {
+            range,
+            threshold,
+            states,
+            neighbourhood,
+            greenberg_hastings,
+        });
+    }
+
+    /// Layout of the `Rule` struct in `cyclic.wgsl`.
+    pub fn params(&self) -> Vec<u32> {
+        let mut params = vec![
+            self.range,
+            self.threshold,
+            self.states,
+            self.greenberg_hastings as u32,
+        ];
+        params.extend((0..=2 * self.range).map(|row| {
+            self.neighbourhood
+                .half_width(self.range, row.abs_diff(self.range))
+        }));
+        return params;
+    }
+}
+
+impl fmt::Display for CyclicRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let neighbourhood = match self.neighbourhood {
+            Neighbourhood::Moore => 'M',
+            Neighbourhood::VonNeumann => 'N',
+            Neighbourhood::Circular => 'C',
+        };
+        write!(
+            f,
+            "R{}/T{}/C{}/N{}",
+            self.range, self.threshold, self.states, neighbourhood
+        )?;
+        if self.greenberg_hastings {
+            write!(f, "/GH")?;
+        }
+        return fmt::Result::Ok(());
+    }
+}
+
+fn parse_number(value: &str) -> Result<u32> {
+    return value
+        .trim()
+        .parse()
+        .with_context(|| format!("expected a number, got {value:?}"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        for rule in ["R1/T3/C3/NM", "R2/T5/C8/NN/GH", "R10/T1/C256/NC"] {
+            assert_eq!(CyclicRule::parse(rule).unwrap().to_string(), rule);
+        }
+        let rule = CyclicRule::parse("c4/nn/gh").unwrap();
+        assert_eq!(rule.to_string(), "R1/T1/C4/NN/GH");
+        assert!(rule.greenberg_hastings);
+    }
+
+    #[test]
+    fn lists_the_width_of_each_row() {
+        let moore = CyclicRule::parse("R1/T3/C3/NM").unwrap();
+        assert_eq!(moore.params(), [1, 3, 3, 0, 1, 1, 1]);
+        let von_neumann = CyclicRule::parse("R2/T2/C5/NN/GH").unwrap();
+        assert_eq!(von_neumann.params(), [2, 2, 5, 1, 0, 1, 2, 1, 0]);
+    }
+
+    #[test]
+    fn rejects_rules_it_cant_run() {
+        for rule in [
+            "R0/T1/C3/NM",
+            "R11/T1/C3/NM",
+            "R1/T0/C3/NM",
+            "R1/T1/C1/NM",
+            "R1/T1/C257/NM",
+            "R1/T1/C3/NX",
+            "R1/X1/C3/NM",
+            "R1/Tx/C3/NM",
+        ] {
+            assert!(CyclicRule::parse(rule).is_err(), "{rule}");
+        }
+    }
+}
@@ -0,0 +1,62 @@
+// Cyclic automata and Greenberg-Hastings models, counting how many
+// neighbours within the range hold one particular state.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+struct Rule {
+    range: u32,
+    threshold: u32,
+    states: u32,
+    greenberg_hastings: u32,
+    // Cells either side of the centre column on each of the 2R + 1 rows.
+    half_widths: array<u32>,
+}
+
+@group(2) @binding(0)
+var<storage, read> rule: Rule;
+
+// Cells off the board are in no state at all, so they never count.
+const OUTSIDE: u32 = 0xffffffffu;
+
+fn state(position: vec2<i32>) -> u32 {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    if any(position < vec2<i32>(0)) || any(position >= size) {
+        return OUTSIDE;
+    }
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let size = textureDimensions(t_diffuse);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+    let position = vec2<i32>(global_invocation_id.xy);
+    let current = state(position);
+
+    // Greenberg-Hastings cells only wait on their neighbours while resting,
+    // the excited and refractory states advance regardless.
+    let advanced = (current + 1u) % rule.states;
+    var next = advanced;
+    if rule.greenberg_hastings == 0u || current == 0u {
+        let range = i32(rule.range);
+        var count = 0u;
+        for (var dy = -range; dy <= range; dy++) {
+            let width = i32(rule.half_widths[u32(dy + range)]);
+            for (var dx = -width; dx <= width; dx++) {
+                if (dx != 0 || dy != 0) && state(position + vec2<i32>(dx, dy)) == advanced {
+                    count++;
+                }
+            }
+        }
+        next = select(current, advanced, count >= rule.threshold);
+    }
+
+    textureStore(t_output, position, vec4<f32>(f32(next) / 255.0, 0.0, 0.0, 1.0));
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+
+use anyhow::*;
+
+/// Largest radius that still fits a totalistic code in 32 bits.
+pub const MAX_RADIUS: u32 = 15;
+
+/// A two state one dimensional rule, run by `elementary.wgsl` one row at a
+/// time to draw a spacetime diagram down the board.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElementaryRule {
+    pub radius: u32,
+    /// Whether the code is indexed by the number of live cells in the
+    /// neighbourhood rather than by their arrangement.
+    pub totalistic: bool,
+    pub code: u32,
+}
+
+impl ElementaryRule {
+    /// Parses Wolfram codes such as `W110`, and totalistic rules with a
+    /// larger radius such as `T20,R2`.
+    pub fn parse(rule: &str) -> Result<Self> {
+        if let Some(code) = rule.strip_prefix(['W', 'w']) {
+            let code = code.trim().parse::<u32>()?;
+            if code > 255 {
+                bail!("Wolfram codes go from 0 to 255, got {code}");
+            }
+            return Ok(ElementaryRule {
+                radius: 1,
+                totalistic: false,
+                code,
+            });
+        }
+
+        let rest = rule
+            .strip_prefix(['T', 't'])
+            .ok_or_else(|| anyhow!("one dimensional rules start with W or T"))?;
+        let (code, radius) = match rest.split_once(',') {
+            Some((code, radius)) => {
+                let radius = radius
+                    .trim()
+                    .strip_prefix(['R', 'r'])
+                    .ok_or_else(|| anyhow!("expected a radius such as R2, got {radius}"))?;
+                (code, radius.parse::<u32>()?)
+            }
+            None => (rest, 1),
+        };
+        if radius == 0 || radius > MAX_RADIUS {
+            bail!("radius must be between 1 and {MAX_RADIUS}, got {radius}");
+        }
+        let code = code.trim().parse::<u64>()?;
+        if code >= 1 << (2 * radius + 2) {
+            bail!("code {code} is too large for radius {radius}");
+        }
+
+        return Ok(ElementaryRule {
+            radius,
+            totalistic: true,
+            code: code as u32,
+        });
+    }
+
+    /// Layout of the `Rule` struct in `elementary.wgsl`.
+    pub fn params(&self) -> Vec<u32> {
+        return vec![self.radius, self.totalistic as u32, self.code];
+    }
+}
+
+impl fmt::Display for ElementaryRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.totalistic {
+            return write!(f, "T{},R{}", self.code, self.radius);
+        }
+        return write!(f, "W{}", self.code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        for rule in ["W110", "W0", "W255", "T20,R2", "T15,R1", "T4294967295,R15"] {
+            assert_eq!(ElementaryRule::parse(rule).unwrap().to_string(), rule);
+        }
+        let rule = ElementaryRule::parse("t 6, r3").unwrap();
+        assert_eq!((rule.code, rule.radius, rule.totalistic), (6, 3, true));
+        assert_eq!(ElementaryRule::parse("T10").unwrap().radius, 1);
+        assert!(!ElementaryRule::parse("w30").unwrap().totalistic);
+    }
+
+    #[test]
+    fn codes_fit_the_radius() {
+        // A radius `r` neighbourhood holds up to `2r + 1` live cells.
+        for radius in 1..=MAX_RADIUS {
+            let most = (1u64 << (2 * radius + 2)) - 1;
+            assert!(ElementaryRule::parse(&format!("T{most},R{radius}")).is_ok());
+            let over = format!("T{},R{radius}", most + 1);
+            assert!(ElementaryRule::parse(&over).is_err(), "{over}");
+        }
+    }
+
+    #[test]
+    fn rejects_rules_it_cant_run() {
+        for rule in [
+            "W256", "W-1", "W", "T20,R0", "T20,R16", "T20,2", "T20,R", "Tx", "X110",
+        ] {
+            assert!(ElementaryRule::parse(rule).is_err(), "{rule}");
+        }
+    }
+}
@@ -0,0 +1,68 @@
+// One dimensional rules drawn as a spacetime diagram. The top row is the
+// initial configuration and every step computes the row below the last one,
+// once the board is full everything scrolls up a row instead.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+struct Rule {
+    radius: u32,
+    totalistic: u32,
+    code: u32,
+}
+
+@group(2) @binding(0)
+var<storage, read> rule: Rule;
+
+struct Step {
+    generation: u32,
+}
+
+@group(2) @binding(1)
+var<uniform> step: Step;
+
+fn state(position: vec2<i32>) -> u32 {
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    let position = vec2<i32>(global_invocation_id.xy);
+    if position.x >= size.x || position.y >= size.y {
+        return;
+    }
+
+    let filling = step.generation < u32(size.y - 1);
+    // The row being computed and the row it is computed from.
+    let target_row = select(size.y - 1, i32(step.generation) + 1, filling);
+    let previous = select(size.y - 1, i32(step.generation), filling);
+
+    var next: u32;
+    if position.y != target_row {
+        // Copy the rest of the diagram, shifted up once it has filled.
+        next = state(position + vec2<i32>(0, select(1, 0, filling)));
+    } else {
+        let radius = i32(rule.radius);
+        var index = 0u;
+        for (var i = -radius; i <= radius; i++) {
+            // The row wraps around at the edges of the board.
+            let x = ((position.x + i) % size.x + size.x) % size.x;
+            let alive = state(vec2<i32>(x, previous)) == 1u;
+            if rule.totalistic == 1u {
+                index += select(0u, 1u, alive);
+            } else {
+                index = (index << 1u) | select(0u, 1u, alive);
+            }
+        }
+        next = (rule.code >> index) & 1u;
+    }
+
+    textureStore(t_output, position, vec4<f32>(f32(next) / 255.0, 0.0, 0.0, 1.0));
+}
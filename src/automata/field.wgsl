@@ -0,0 +1,44 @@
+// Draws continuous rules, looking the value of each cell up in the palette
+// as a colour map. The board quad and camera are the same as `gol.wgsl`.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+struct VertexInput {
+    @location(0) vert_pos: vec3<f32>,
+    @location(1) colour: vec3<f32>,
+    @location(2) tex_coords: vec2<f32>,
+}
+
+struct Camera {
+    scale: f32,
+    x: f32,
+    y: f32
+}
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@vertex
+fn vs_field(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4((model.vert_pos.xy + vec2(camera.x, camera.y)) * camera.scale, model.vert_pos.z, 1.0);
+    out.tex_coords = model.tex_coords;
+    return out;
+}
+
+@group(0) @binding(0)
+var t_field: texture_2d<f32>;
+
+@group(0) @binding(3)
+var<storage, read> palette: array<vec4<f32>>;
+
+@fragment
+fn fs_field(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(t_field));
+    let cell = vec2<i32>(clamp(in.tex_coords * size, vec2<f32>(0.0), size - 1.0));
+    let value = clamp(textureLoad(t_field, cell, 0).x, 0.0, 1.0);
+    return palette[u32(round(value * 255.0))];
+}
@@ -42,10 +42,108 @@ var s_diffuse: sampler;
 @group(0) @binding(2)
 var t_output: texture_storage_2d<rgba8unorm, write>;
 
+@group(0) @binding(3)
+var<storage, read> palette: array<vec4<f32>>;
+
+// Cell states are stored in the red channel as `state / 255`.
+fn state(position: vec2<i32>) -> u32 {
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    let cell = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    return palette[u32(round(cell.x * 255.0))];
+}
+
+// The hexagon under a point on the board, measured in cells, in odd-r offset
+// coordinates. The third component is how far the point is from the centre
+// of the hexagon, reaching 1 on its edges.
+fn hex_cell(point: vec2<f32>) -> vec3<f32> {
+    // Rows are one unit apart, so the fractional axial coordinates relative
+    // to the first cell are just a shear of the point.
+    let r = point.y - 0.5;
+    let q = point.x - 0.5 - r / 2.0;
+    let cube = vec3<f32>(q, r, -q - r);
+
+    var rounded = round(cube);
+    let delta = abs(rounded - cube);
+    if delta.x > delta.y && delta.x > delta.z {
+        rounded.x = -rounded.y - rounded.z;
+    } else if delta.y > delta.z {
+        rounded.y = -rounded.x - rounded.z;
+    }
+
+    let offset = cube - rounded;
+    let edge = max(max(abs(offset.x - offset.y), abs(offset.y - offset.z)), abs(offset.z - offset.x));
+
+    let row = i32(rounded.y);
+    let column = i32(rounded.x) + (row - (row & 1)) / 2;
+    return vec3<f32>(f32(column), f32(row), edge);
+}
+
+@fragment
+fn fs_hex(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    let point = in.tex_coords * vec2<f32>(size);
+    let hex = hex_cell(point);
+    let cell = vec2<i32>(hex.xy);
+    if any(cell < vec2<i32>(0)) || any(cell >= size) {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    }
+
+    let colour = palette[state(cell)];
+    // Outline the hexagons once they are large enough to make out.
+    let pixels = 1.0 / max(fwidth(point.x), 1e-6);
+    if pixels > 8.0 && hex.z > 1.0 - 2.0 / pixels {
+        return vec4<f32>(colour.rgb * 0.6 + 0.1, colour.a);
+    }
+    return colour;
+}
+
+// The triangle under a point on the board, measured in cells. Triangles where
+// `x + y` is even point up, and each spans two columns, overlapping the
+// triangles either side of it. The last two components are how far the point
+// is from the triangle's flat edge in rows and from its slanted edges in
+// columns.
+fn triangle_cell(point: vec2<f32>) -> vec4<f32> {
+    let t = point.x + 0.5;
+    let column = floor(t);
+    let across = t - column;
+    let row = floor(point.y);
+    let down = point.y - row;
+
+    // Of the two triangles the point could be in, the left one is centred
+    // at `column` and the right one at `column + 1`.
+    let left_up = (i32(column - 1.0 + row) & 1) == 0;
+    let half_width = select(1.0 - down, down, left_up);
+    if across < half_width {
+        let flat = select(down, 1.0 - down, left_up);
+        return vec4<f32>(column - 1.0, row, flat, half_width - across);
+    }
+    let right_half_width = select(down, 1.0 - down, left_up);
+    let flat = select(1.0 - down, down, left_up);
+    return vec4<f32>(column, row, flat, right_half_width - (1.0 - across));
+}
+
+@fragment
+fn fs_triangle(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    let point = in.tex_coords * vec2<f32>(size);
+    let triangle = triangle_cell(point);
+    let cell = vec2<i32>(triangle.xy);
+    if any(cell < vec2<i32>(0)) || any(cell >= size) {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    }
+
+    let colour = palette[state(cell)];
+    // Outline the triangles once they are large enough to make out.
+    let pixels = 1.0 / max(fwidth(point), vec2<f32>(1e-6));
+    let edge = min(triangle.z * pixels.y, triangle.w * min(pixels.x, pixels.y) * 0.7);
+    if min(pixels.x, pixels.y) > 8.0 && edge < 1.0 {
+        return vec4<f32>(colour.rgb * 0.6 + 0.1, colour.a);
+    }
+    return colour;
 }
 
 @compute
@@ -63,25 +161,20 @@ fn cm_main(
             if i == 0 && j == 0 {
                 continue;
             }
-            var color = textureLoad(t_diffuse, vec2<i32>(i32(x) + i, i32(y) + j), 0);
-            if color.x > 0.5f {
+            if state(vec2<i32>(i32(x) + i, i32(y) + j)) == 1u {
                 count += 1;
             }
         }
     }
 
 
-    var color = textureLoad(t_diffuse, vec2<i32>(i32(x), i32(y)), 0);
+    var next = state(vec2<i32>(i32(x), i32(y)));
 
     if count == 3 {
-        color.x = 1.0f;
-        color.y = 1.0f;
-        color.z = 1.0f;
+        next = 1u;
     } else if count < 2 || count > 3 {
-        color.x = 0.0f;
-        color.y = 0.0f;
-        color.z = 0.0f;
+        next = 0u;
     }
 
-    textureStore(t_output, vec2<i32>(i32(x), i32(y)), color);
+    textureStore(t_output, vec2<i32>(i32(x), i32(y)), vec4<f32>(f32(next) / 255.0, 0.0, 0.0, 1.0));
 }
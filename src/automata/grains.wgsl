@@ -0,0 +1,44 @@
+// Draws sandpiles, looking the grain count of each cell up in the palette.
+// Piles of 4 or more, which are about to topple, share the last entry. The
+// board quad and camera are the same as `gol.wgsl`.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+struct VertexInput {
+    @location(0) vert_pos: vec3<f32>,
+    @location(1) colour: vec3<f32>,
+    @location(2) tex_coords: vec2<f32>,
+}
+
+struct Camera {
+    scale: f32,
+    x: f32,
+    y: f32
+}
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@vertex
+fn vs_grains(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4((model.vert_pos.xy + vec2(camera.x, camera.y)) * camera.scale, model.vert_pos.z, 1.0);
+    out.tex_coords = model.tex_coords;
+    return out;
+}
+
+@group(0) @binding(0)
+var t_grains: texture_2d<u32>;
+
+@group(0) @binding(3)
+var<storage, read> palette: array<vec4<f32>>;
+
+@fragment
+fn fs_grains(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(t_grains));
+    let cell = vec2<i32>(clamp(in.tex_coords * size, vec2<f32>(0.0), size - 1.0));
+    return palette[min(textureLoad(t_grains, cell, 0).x, 4u)];
+}
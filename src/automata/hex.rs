@@ -0,0 +1,134 @@
+use std::fmt;
+
+use anyhow::*;
+
+/// A totalistic rule on the hexagonal lattice, e.g. `B2/S34H`. With more than
+/// two states it is a Generations rule such as `B2/S34/3H`, where live cells
+/// that don't survive decay through the extra states before dying.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HexRule {
+    /// Bit `n` is set when a dead cell with `n` live neighbours is born.
+    pub birth: u32,
+    /// Bit `n` is set when a live cell with `n` live neighbours survives.
+    pub survival: u32,
+    pub states: u32,
+}
+
+impl HexRule {
+    /// Parses `B<digits>/S<digits>[/<states>]` followed by `H`, the states
+    /// may also be written `C<states>`.
+    pub fn parse(rule: &str) -> Result<Self> {
+        let body = rule
+            .strip_suffix(['H', 'h'])
+            .ok_or_else(|| anyhow!("hexagonal rules end with H, got {rule}"))?;
+
+        let (birth, survival, states) = parse_generations(body, 6)?;
+        return Ok(HexRule {
+            birth,
+            survival,
+            states,
+        });
+    }
+
+    /// Layout of the `Rule` struct in `hex.wgsl`.
+    pub fn params(&self) -> Vec<u32> {
+        return vec![self.birth, self.survival, self.states];
+    }
+}
+
+/// Parses the `B<counts>/S<counts>[/<states>]` shared by Generations rules on
+/// every lattice, returning the births and survivals as bit masks. Counts
+/// above 9 are written `a` for 10, `b` for 11 and so on.
+pub(super) fn parse_generations(rule: &str, neighbours: u32) -> Result<(u32, u32, u32)> {
+    let mut birth = None;
+    let mut survival = None;
+    let mut states = 2;
+    for part in rule.split('/').map(str::trim) {
+        if let Some(digits) = part.strip_prefix(['B', 'b']) {
+            birth = Some(parse_counts(digits, neighbours)?);
+        } else if let Some(digits) = part.strip_prefix(['S', 's']) {
+            survival = Some(parse_counts(digits, neighbours)?);
+        } else {
+            states = part.trim_start_matches(['C', 'c']).parse::<u32>()?;
+        }
+    }
+    if !(2..=256).contains(&states) {
+        bail!("a rule needs between 2 and 256 states, got {states}");
+    }
+
+    return Ok((
+        birth.ok_or_else(|| anyhow!("missing births in {rule}"))?,
+        survival.ok_or_else(|| anyhow!("missing survivals in {rule}"))?,
+        states,
+    ));
+}
+
+fn parse_counts(digits: &str, neighbours: u32) -> Result<u32> {
+    let mut counts = 0;
+    for digit in digits.chars() {
+        let count = digit
+            .to_digit(36)
+            .filter(|count| *count <= neighbours)
+            .ok_or_else(|| anyhow!("cells have {neighbours} neighbours, got {digit}"))?;
+        counts |= 1 << count;
+    }
+    return Ok(counts);
+}
+
+/// Writes a rule back out in the form `parse_generations` reads.
+pub(super) fn write_generations(
+    f: &mut fmt::Formatter<'_>,
+    birth: u32,
+    survival: u32,
+    states: u32,
+    neighbours: u32,
+) -> fmt::Result {
+    write!(f, "B")?;
+    write_counts(f, birth, neighbours)?;
+    write!(f, "/S")?;
+    write_counts(f, survival, neighbours)?;
+    if states > 2 {
+        write!(f, "/{states}")?;
+    }
+    return fmt::Result::Ok(());
+}
+
+fn write_counts(f: &mut fmt::Formatter<'_>, counts: u32, neighbours: u32) -> fmt::Result {
+    for count in 0..=neighbours {
+        if counts & (1 << count) != 0 {
+            write!(f, "{}", char::from_digit(count, 36).unwrap())?;
+        }
+    }
+    return fmt::Result::Ok(());
+}
+
+impl fmt::Display for HexRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_generations(f, self.birth, self.survival, self.states, 6)?;
+        return write!(f, "H");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        for rule in ["B2/S34H", "B2/S34/3H", "B/S0123456/256H"] {
+            assert_eq!(HexRule::parse(rule).unwrap().to_string(), rule);
+        }
+        let rule = HexRule::parse("b2/s34/C3h").unwrap();
+        assert_eq!(rule.to_string(), "B2/S34/3H");
+        assert_eq!(rule.params(), [1 << 2, 1 << 3 | 1 << 4, 3]);
+    }
+
+    #[test]
+    fn rejects_counts_and_states_hexagons_cant_have() {
+        assert!(HexRule::parse("B2/S34").is_err());
+        assert!(HexRule::parse("B7/S34H").is_err());
+        assert!(HexRule::parse("B2/S34/1H").is_err());
+        assert!(HexRule::parse("B2/S34/257H").is_err());
+        assert!(HexRule::parse("S34H").is_err());
+    }
+}
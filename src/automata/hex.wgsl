@@ -0,0 +1,72 @@
+// Totalistic and Generations rules on a hexagonal lattice stored in odd-r
+// offset coordinates, see `Lattice::Hexagonal`.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+struct Rule {
+    // Bit `n` is set when a cell with `n` live neighbours is born or survives.
+    birth: u32,
+    survival: u32,
+    states: u32,
+}
+
+@group(2) @binding(0)
+var<storage, read> rule: Rule;
+
+fn state(position: vec2<i32>) -> u32 {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    if any(position < vec2<i32>(0)) || any(position >= size) {
+        return 0u;
+    }
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    let position = vec2<i32>(global_invocation_id.xy);
+    if position.x >= size.x || position.y >= size.y {
+        return;
+    }
+
+    // The rows above and below are shifted half a cell left of odd rows.
+    let shift = position.y & 1;
+    let neighbours = array<vec2<i32>, 6>(
+        vec2<i32>(-1, 0),
+        vec2<i32>(1, 0),
+        vec2<i32>(shift - 1, -1),
+        vec2<i32>(shift, -1),
+        vec2<i32>(shift - 1, 1),
+        vec2<i32>(shift, 1),
+    );
+
+    var count = 0u;
+    for (var i = 0; i < 6; i++) {
+        if state(position + neighbours[i]) == 1u {
+            count += 1u;
+        }
+    }
+
+    let current = state(position);
+    var next = 0u;
+    if current == 0u {
+        next = (rule.birth >> count) & 1u;
+    } else if current == 1u {
+        if ((rule.survival >> count) & 1u) == 1u {
+            next = 1u;
+        } else {
+            next = 2u % rule.states;
+        }
+    } else {
+        next = (current + 1u) % rule.states;
+    }
+
+    textureStore(t_output, position, vec4<f32>(f32(next) / 255.0, 0.0, 0.0, 1.0));
+}
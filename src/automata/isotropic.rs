@@ -0,0 +1,263 @@
+use std::fmt;
+
+use anyhow::*;
+
+/// Neighbours in the order of the bits of a configuration, row by row and
+/// skipping the centre: NW, N, NE, W, E, SW, S, SE.
+pub const OFFSETS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Hensel letters for 1 to 4 live neighbours. Counts above 4 use the letter
+/// of the complementary configuration.
+const LETTERS: [&str; 4] = ["ce", "ceaikn", "ceaiknjqry", "ceaiknjqrtwyz"];
+
+/// One configuration for each of the `LETTERS`, taken from Golly. These use a
+/// 3x3 bit layout with the centre cell at bit 4.
+const REPRESENTATIVES: [&[u16]; 4] = [
+    &[1, 2],
+    &[5, 10, 3, 40, 33, 68],
+    &[69, 42, 11, 7, 98, 13, 14, 70, 41, 97],
+    &[325, 170, 15, 45, 99, 71, 106, 102, 43, 101, 105, 78, 108],
+];
+
+/// An isotropic non-totalistic rule such as `B2-a/S12`, stored as the set of
+/// neighbourhood configurations that cause a birth or survival.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IsotropicRule {
+    /// Indexed by configuration, 256 entries each.
+    pub birth: Vec<bool>,
+    pub survival: Vec<bool>,
+}
+
+impl IsotropicRule {
+    /// Parses `B`/`S` rulestrings with optional Hensel letters. Plain
+    /// totalistic rules such as `B3/S23` are a special case of these.
+    pub fn parse(rule: &str) -> Result<Self> {
+        let mut birth = None;
+        let mut survival = None;
+
+        for part in rule.split('/').map(str::trim) {
+            let mut chars = part.chars();
+            match chars.next().map(|c| c.to_ascii_uppercase()) {
+                Some('B') => birth = Some(parse_conditions(chars.as_str())?),
+                Some('S') => survival = Some(parse_conditions(chars.as_str())?),
+                _ => bail!("expected B or S conditions, got {part:?}"),
+            }
+        }
+
+        return Ok(IsotropicRule {
+            birth: birth.ok_or_else(|| anyhow!("missing birth conditions"))?,
+            survival: survival.ok_or_else(|| anyhow!("missing survival conditions"))?,
+        });
+    }
+
+    /// The 256-entry transition table read by `isotropic.wgsl`, bit 0 of an
+    /// entry is set for births and bit 1 for survivals.
+    pub fn params(&self) -> Vec<u32> {
+        return self
+            .birth
+            .iter()
+            .zip(&self.survival)
+            .map(|(&birth, &survival)| birth as u32 | (survival as u32) << 1)
+            .collect();
+    }
+}
+
+impl fmt::Display for IsotropicRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "B")?;
+        write_conditions(f, &self.birth)?;
+        write!(f, "/S")?;
+        return write_conditions(f, &self.survival);
+    }
+}
+
+/// Writes the shortest form of the conditions for each neighbour count,
+/// listing either the letters included or the letters excluded.
+fn write_conditions(f: &mut fmt::Formatter<'_>, selected: &[bool]) -> fmt::Result {
+    for count in 0..=8u32 {
+        let configs = (0..=255u8).filter(|config| config.count_ones() == count);
+        let (included, excluded): (Vec<u8>, Vec<u8>) =
+            configs.partition(|&config| selected[config as usize]);
+        if included.is_empty() {
+            continue;
+        }
+
+        write!(f, "{count}")?;
+        if excluded.is_empty() {
+            continue;
+        }
+
+        let letters = |configs: &[u8]| {
+            let mut letters: Vec<char> = configs.iter().filter_map(|&c| letter(c)).collect();
+            letters.sort();
+            letters.dedup();
+            return letters.into_iter().collect::<String>();
+        };
+        let (included, excluded) = (letters(&included), letters(&excluded));
+        if included.len() <= excluded.len() {
+            write!(f, "{included}")?;
+        } else {
+            write!(f, "-{excluded}")?;
+        }
+    }
+    return fmt::Result::Ok(());
+}
+
+/// The Hensel letter of a configuration, `None` for 0 and 8 neighbours which
+/// only have one configuration each.
+pub fn letter(config: u8) -> Option<char> {
+    let count = config.count_ones() as usize;
+    let (count, config) = match count {
+        0 | 8 => return None,
+        5.. => (8 - count, !config),
+        _ => (count, config),
+    };
+
+    let symmetries = symmetries(config);
+    let index = REPRESENTATIVES[count - 1]
+        .iter()
+        .position(|&representative| symmetries.contains(&without_centre(representative)))
+        .expect("every configuration has a representative");
+    return LETTERS[count - 1].chars().nth(index);
+}
+
+/// `config` under every rotation and reflection of the square.
+fn symmetries(config: u8) -> [u8; 8] {
+    let transform = |map: fn((i32, i32)) -> (i32, i32)| {
+        let mut result = 0;
+        for (bit, &offset) in OFFSETS.iter().enumerate() {
+            if config & (1 << bit) != 0 {
+                let target = OFFSETS.iter().position(|&o| o == map(offset)).unwrap();
+                result |= 1 << target;
+            }
+        }
+        return result;
+    };
+
+    return [
+        transform(|(x, y)| (x, y)),
+        transform(|(x, y)| (-y, x)),
+        transform(|(x, y)| (-x, -y)),
+        transform(|(x, y)| (y, -x)),
+        transform(|(x, y)| (-x, y)),
+        transform(|(x, y)| (x, -y)),
+        transform(|(x, y)| (y, x)),
+        transform(|(x, y)| (-y, -x)),
+    ];
+}
+
+fn without_centre(config: u16) -> u8 {
+    return ((config & 0b1111) | (config >> 5) << 4) as u8;
+}
+
+/// Parses a list of conditions such as `2-a3ij`: a neighbour count followed
+/// by the letters to include, or to exclude when prefixed with `-`.
+fn parse_conditions(conditions: &str) -> Result<Vec<bool>> {
+    let mut selected = vec![false; 256];
+    let mut chars = conditions.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let count = c
+            .to_digit(10)
+            .filter(|&count| count <= 8)
+            .ok_or_else(|| anyhow!("expected a neighbour count, got {c:?}"))?;
+        let negated = chars.next_if_eq(&'-').is_some();
+        let mut letters = String::new();
+        while let Some(letter) = chars.next_if(char::is_ascii_alphabetic) {
+            letters.push(letter.to_ascii_lowercase());
+        }
+
+        let valid = match count {
+            0 | 8 => "",
+            1..=4 => LETTERS[count as usize - 1],
+            _ => LETTERS[7 - count as usize],
+        };
+        if let Some(invalid) = letters.chars().find(|&l| !valid.contains(l)) {
+            bail!("{count}{invalid} is not a valid neighbourhood");
+        }
+
+        for config in 0..=255u8 {
+            if config.count_ones() != count {
+                continue;
+            }
+            let matches = letter(config).is_some_and(|l| letters.contains(l));
+            if letters.is_empty() || matches != negated {
+                selected[config as usize] = true;
+            }
+        }
+    }
+
+    return Ok(selected);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_letter_has_configurations() {
+        for count in 1..=7u32 {
+            let mut letters: Vec<char> = (0..=255u8)
+                .filter(|config| config.count_ones() == count)
+                .filter_map(letter)
+                .collect();
+            letters.sort();
+            letters.dedup();
+            let mut expected: Vec<char> =
+                LETTERS[count.min(8 - count) as usize - 1].chars().collect();
+            expected.sort();
+            assert_eq!(letters, expected, "{count} neighbours");
+        }
+    }
+
+    #[test]
+    fn letters_are_the_same_under_every_symmetry() {
+        for config in 0..=255u8 {
+            for symmetric in symmetries(config) {
+                assert_eq!(letter(symmetric), letter(config));
+            }
+        }
+    }
+
+    #[test]
+    fn writes_the_shortest_conditions() {
+        let rule = IsotropicRule::parse("B2cekin/S12").unwrap();
+        assert_eq!(rule.to_string(), "B2-a/S12");
+        assert_eq!(IsotropicRule::parse(&rule.to_string()).unwrap(), rule);
+        let rule = IsotropicRule::parse("b3/s2-a3").unwrap();
+        assert_eq!(rule.to_string(), "B3/S2-a3");
+        assert_eq!(
+            IsotropicRule::parse("B3/S23").unwrap().to_string(),
+            "B3/S23"
+        );
+    }
+
+    #[test]
+    fn counts_configurations_by_their_letters() {
+        let rule = IsotropicRule::parse("B2e/S").unwrap();
+        // Two live neighbours on opposite edges, north and south, are `2i`
+        // and two that share a corner, north and east, are `2e`.
+        assert!(!rule.birth[1 << 1 | 1 << 6]);
+        assert!(rule.birth[1 << 1 | 1 << 4]);
+        assert_eq!(rule.birth.iter().filter(|&&birth| birth).count(), 4);
+        assert!(rule.survival.iter().all(|&survival| !survival));
+    }
+
+    #[test]
+    fn rejects_letters_the_count_does_not_have() {
+        assert!(IsotropicRule::parse("B1a/S").is_err());
+        assert!(IsotropicRule::parse("B3/S9").is_err());
+        assert!(IsotropicRule::parse("B0c/S").is_err());
+        assert!(IsotropicRule::parse("B3").is_err());
+        assert!(IsotropicRule::parse("B3/S23/C3").is_err());
+    }
+}
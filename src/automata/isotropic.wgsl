@@ -0,0 +1,58 @@
+// Isotropic non-totalistic rules, looked up by the arrangement of the live
+// neighbours rather than just their count.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+// Indexed by the neighbourhood configuration, bit 0 of an entry is set when
+// a dead cell is born and bit 1 when a live cell survives.
+@group(2) @binding(0)
+var<storage, read> table: array<u32, 256>;
+
+fn state(position: vec2<i32>) -> u32 {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    if any(position < vec2<i32>(0)) || any(position >= size) {
+        return 0u;
+    }
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let size = textureDimensions(t_diffuse);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+    let position = vec2<i32>(global_invocation_id.xy);
+
+    // NW, N, NE, W, E, SW, S, SE, matching `OFFSETS` in isotropic.rs.
+    let offsets = array<vec2<i32>, 8>(
+        vec2<i32>(-1, -1),
+        vec2<i32>(0, -1),
+        vec2<i32>(1, -1),
+        vec2<i32>(-1, 0),
+        vec2<i32>(1, 0),
+        vec2<i32>(-1, 1),
+        vec2<i32>(0, 1),
+        vec2<i32>(1, 1),
+    );
+
+    var config = 0u;
+    for (var i = 0u; i < 8u; i++) {
+        if state(position + offsets[i]) == 1u {
+            config |= 1u << i;
+        }
+    }
+
+    let entry = table[config];
+    let alive = state(position) == 1u;
+    let next = select(entry & 1u, (entry >> 1u) & 1u, alive);
+
+    textureStore(t_output, position, vec4<f32>(f32(next) / 255.0, 0.0, 0.0, 1.0));
+}
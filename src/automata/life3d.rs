@@ -0,0 +1,187 @@
+use std::fmt;
+
+use anyhow::*;
+
+/// Which cells around a voxel count as its neighbours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighbourhood3d {
+    /// The 26 voxels sharing a face, edge or corner.
+    Moore,
+    /// The 6 voxels sharing a face.
+    VonNeumann,
+}
+
+impl Neighbourhood3d {
+    pub fn size(&self) -> u32 {
+        match self {
+            Neighbourhood3d::Moore => 26,
+            Neighbourhood3d::VonNeumann => 6,
+        }
+    }
+}
+
+/// A 3D Life-like or Generations rule in the `survival/birth/states/neighbourhood`
+/// notation, e.g. 445 is `4/4/5/M` and Clouds is `13-26/13-14,17-19/2/M`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Life3dRule {
+    /// Bit `n` is set when a live cell with `n` live neighbours survives.
+    pub survival: u32,
+    /// Bit `n` is set when a dead cell with `n` live neighbours is born.
+    pub birth: u32,
+    pub states: u32,
+    pub neighbourhood: Neighbourhood3d,
+}
+
+impl Life3dRule {
+    pub fn parse(rule: &str) -> Result<Self> {
+        let parts: Vec<&str> = rule.split('/').map(str::trim).collect();
+        let [survival, birth, states, neighbourhood] = parts[..] else {
+            bail!("3D rules are written survival/birth/states/neighbourhood, got {rule}");
+        };
+
+        let neighbourhood = match neighbourhood.to_ascii_uppercase().as_str() {
+            "M" => Neighbourhood3d::Moore,
+            "N" | "VN" => Neighbourhood3d::VonNeumann,
+            _ => bail!("unknown neighbourhood {neighbourhood}, expected M or N"),
+        };
+        let states = states.parse::<u32>()?;
+        if !(2..=256).contains(&states) {
+            bail!("a rule needs between 2 and 256 states, got {states}");
+        }
+
+        return Ok(Life3dRule {
+            survival: parse_counts(survival, neighbourhood.size())?,
+            birth: parse_counts(birth, neighbourhood.size())?,
+            states,
+            neighbourhood,
+        });
+    }
+
+    /// Layout of the `Rule` struct in `life3d.wgsl`.
+    pub fn params(&self) -> Vec<u32> {
+        return vec![
+            self.survival,
+            self.birth,
+            self.states,
+            (self.neighbourhood == Neighbourhood3d::Moore) as u32,
+        ];
+    }
+}
+
+/// Parses comma separated counts and ranges such as `5-7,12-13,15`.
+fn parse_counts(counts: &str, neighbours: u32) -> Result<u32> {
+    let mut mask = 0;
+    for part in counts
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let (low, high) = match part.split_once('-') {
+            Some((low, high)) => (low.trim().parse::<u32>()?, high.trim().parse::<u32>()?),
+            None => {
+                let count = part.parse::<u32>()?;
+                (count, count)
+            }
+        };
+        if low > high || high > neighbours {
+            bail!("{part} isn't a range of counts between 0 and {neighbours}");
+        }
+        for count in low..=high {
+            mask |= 1 << count;
+        }
+    }
+    return Ok(mask);
+}
+
+/// Writes a mask back out with runs of counts as ranges.
+fn write_counts(f: &mut fmt::Formatter<'_>, mask: u32) -> fmt::Result {
+    let mut count = 0;
+    let mut first = true;
+    while count < 32 {
+        if mask & (1 << count) == 0 {
+            count += 1;
+            continue;
+        }
+        let low = count;
+        while count < 32 && mask & (1 << count) != 0 {
+            count += 1;
+        }
+        if !first {
+            write!(f, ",")?;
+        }
+        first = false;
+        if count - 1 == low {
+            write!(f, "{low}")?;
+        } else {
+            write!(f, "{low}-{}", count - 1)?;
+        }
+    }
+    return fmt::Result::Ok(());
+}
+
+impl fmt::Display for Life3dRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_counts(f, self.survival)?;
+        write!(f, "/")?;
+        write_counts(f, self.birth)?;
+        write!(f, "/{}/", self.states)?;
+        match self.neighbourhood {
+            Neighbourhood3d::Moore => write!(f, "M"),
+            Neighbourhood3d::VonNeumann => write!(f, "N"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        for rule in [
+            "4/4/5/M",
+            "13-26/13-14,17-19/2/M",
+            "9-26/5-7,12-13,15/5/M",
+            "/1,3/2/N",
+        ] {
+            assert_eq!(Life3dRule::parse(rule).unwrap().to_string(), rule);
+        }
+        let rule = Life3dRule::parse("9-26/5-7,12-13,15/5/M").unwrap();
+        assert_eq!(rule.survival, (1 << 27) - (1 << 9));
+        assert_eq!(
+            rule.birth,
+            1 << 5 | 1 << 6 | 1 << 7 | 1 << 12 | 1 << 13 | 1 << 15
+        );
+        assert_eq!(rule.neighbourhood, Neighbourhood3d::Moore);
+    }
+
+    #[test]
+    fn counts_go_up_to_the_neighbourhood() {
+        assert_eq!(parse_counts("0-2, 26", 26).unwrap(), 0b111 | 1 << 26);
+        assert_eq!(parse_counts("", 26).unwrap(), 0);
+        assert_eq!(parse_counts("6", 6).unwrap(), 1 << 6);
+        for counts in ["27", "20-27", "7-5", "x", "1-"] {
+            assert!(parse_counts(counts, 26).is_err(), "{counts}");
+        }
+        assert!(parse_counts("7", 6).is_err());
+    }
+
+    #[test]
+    fn von_neumann_is_n_or_vn() {
+        for rule in ["1/1/2/N", "1/1/2/VN", "1/1/2/vn"] {
+            let rule = Life3dRule::parse(rule).unwrap();
+            assert_eq!(rule.neighbourhood, Neighbourhood3d::VonNeumann);
+            assert_eq!(rule.to_string(), "1/1/2/N");
+        }
+        for rule in [
+            "4/4/5/X",
+            "7/1/2/N",
+            "4/4/1/M",
+            "4/4/257/M",
+            "4/4/5",
+            "4/4/5/M/M",
+        ] {
+            assert!(Life3dRule::parse(rule).is_err(), "{rule}");
+        }
+    }
+}
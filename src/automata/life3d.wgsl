@@ -0,0 +1,71 @@
+// 3D Life-like and Generations rules on the volume, which wraps around at
+// its faces. Bound by `Volume` in place of the board textures.
+
+@group(0) @binding(0)
+var t_volume: texture_3d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_3d<rgba8unorm, write>;
+
+struct Rule {
+    // Bit `n` is set when a cell with `n` live neighbours survives or is born.
+    survival: u32,
+    birth: u32,
+    states: u32,
+    // Whether all 26 surrounding voxels count, rather than the 6 sharing a
+    // face.
+    moore: u32,
+}
+
+@group(2) @binding(0)
+var<storage, read> rule: Rule;
+
+fn state(position: vec3<i32>) -> u32 {
+    let size = vec3<i32>(textureDimensions(t_volume));
+    let wrapped = (position % size + size) % size;
+    return u32(round(textureLoad(t_volume, wrapped, 0).x * 255.0));
+}
+
+@compute
+@workgroup_size(4, 4, 4)
+fn cm_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let size = textureDimensions(t_volume);
+    if any(global_invocation_id >= size) {
+        return;
+    }
+    let position = vec3<i32>(global_invocation_id);
+
+    var count = 0u;
+    for (var z = -1; z <= 1; z++) {
+        for (var y = -1; y <= 1; y++) {
+            for (var x = -1; x <= 1; x++) {
+                let offset = vec3<i32>(x, y, z);
+                let distance = abs(x) + abs(y) + abs(z);
+                if distance == 0 || (rule.moore == 0u && distance > 1) {
+                    continue;
+                }
+                if state(position + offset) == 1u {
+                    count += 1u;
+                }
+            }
+        }
+    }
+
+    let current = state(position);
+    var next = 0u;
+    if current == 0u {
+        next = (rule.birth >> count) & 1u;
+    } else if current == 1u {
+        if ((rule.survival >> count) & 1u) == 1u {
+            next = 1u;
+        } else {
+            next = 2u % rule.states;
+        }
+    } else {
+        next = (current + 1u) % rule.states;
+    }
+
+    textureStore(t_output, position, vec4<f32>(f32(next) / 255.0, 0.0, 0.0, 1.0));
+}
@@ -0,0 +1,196 @@
+use std::fmt;
+
+use anyhow::*;
+
+/// Largest radius `ltl.wgsl` can fit in its shared memory tile.
+pub const MAX_RADIUS: u32 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Neighbourhood {
+    Moore,
+    VonNeumann,
+    /// Cells within a Euclidean distance of `radius + 0.5`.
+    Circular,
+}
+
+impl Neighbourhood {
+    /// Number of cells either side of the centre column that are part of the
+    /// neighbourhood, on the row `dy` rows away from the centre.
+    pub fn half_width(&self, radius: u32, dy: u32) -> u32 {
+        match self {
+            Neighbourhood::Moore => radius,
+            Neighbourhood::VonNeumann => radius - dy,
+            Neighbourhood::Circular => (radius * radius + radius - dy * dy).isqrt(),
+        }
+    }
+}
+
+/// A Larger than Life rule, e.g. Bosco's Rule `R5,C0,M1,S34..58,B34..45,NM`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LtlRule {
+    pub radius: u32,
+    /// Number of states, anything above 2 adds decaying states like
+    /// Generations rules.
+    pub states: u32,
+    /// Whether the cell itself counts towards its neighbours.
+    pub middle: bool,
+    pub survival: (u32, u32),
+    pub birth: (u32, u32),
+    pub neighbourhood: Neighbourhood,
+}
+
+impl LtlRule {
+    /// Parses both `S34..58,B34..45` ranges and the older `S34,58,B34,45`
+    /// form Golly also accepts.
+    pub fn parse(rule: &str) -> Result<Self> {
+        let mut radius = 1;
+        let mut states = 2;
+        let mut middle = false;
+        let mut survival = None;
+        let mut birth = None;
+        let mut neighbourhood = Neighbourhood::Moore;
+
+        let mut tokens = rule.split(',').map(str::trim).peekable();
+        while let Some(token) = tokens.next() {
+            let (key, value) = token.split_at(token.chars().next().map_or(0, char::len_utf8));
+            match key.to_ascii_uppercase().as_str() {
+                "R" => radius = parse_number(value)?,
+                "C" => states = parse_number(value)?.max(2),
+                "M" => middle = parse_number(value)? != 0,
+                "S" | "B" => {
+                    let range = match value.split_once("..") {
+                        Some((min, max)) => (parse_number(min)?, parse_number(max)?),
+                        None => {
+                            let max = tokens
+                                .next()
+                                .ok_or_else(|| anyhow!("missing upper bound after {token}"))?;
+                            (parse_number(value)?, parse_number(max)?)
+                        }
+                    };
+                    if key.eq_ignore_ascii_case("S") {
+                        survival = Some(range);
+                    } else {
+                        birth = Some(range);
+                    }
+                }
+                "N" => {
+                    neighbourhood = match value.to_ascii_uppercase().as_str() {
+                        "M" => Neighbourhood::Moore,
+                        "N" => Neighbourhood::VonNeumann,
+                        "C" => Neighbourhood::Circular,
+                        _ => bail!("unknown neighbourhood: {value}"),
+                    }
+                }
+                _ => bail!("unexpected token in Larger than Life rule: {token}"),
+            }
+        }
+
+        if radius == 0 || radius > MAX_RADIUS {
+            bail!("radius must be between 1 and {MAX_RADIUS}, got {radius}");
+        }
+        if states > crate::palette::MAX_STATES as u32 {
+            bail!(
+                "at most {} states are supported",
+                crate::palette::MAX_STATES
+            );
+        }
+
+        return Ok(LtlRule {
+            radius,
+            states,
+            middle,
+            survival: survival.ok_or_else(|| anyhow!("missing survival range"))?,
+            birth: birth.ok_or_else(|| anyhow!("missing birth range"))?,
+            neighbourhood,
+        });
+    }
+
+    /// Layout of the `Rule` struct in `ltl.wgsl`.
+    pub fn params(&self) -> Vec<u32> {
+        let mut params = vec![
+            self.radius,
+            self.states,
+            self.middle as u32,
+            self.survival.0,
+            self.survival.1,
+            self.birth.0,
+            self.birth.1,
+        ];
+        params.extend((0..=2 * self.radius).map(|row| {
+            self.neighbourhood
+                .half_width(self.radius, row.abs_diff(self.radius))
+        }));
+        return params;
+    }
+}
+
+impl fmt::Display for LtlRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let neighbourhood = match self.neighbourhood {
+            Neighbourhood::Moore => 'M',
+            Neighbourhood::VonNeumann => 'N',
+            Neighbourhood::Circular => 'C',
+        };
+        // Golly writes two states as C0.
+        let states = if self.states == 2 { 0 } else { self.states };
+        return write!(
+            f,
+            "R{},C{},M{},S{}..{},B{}..{},N{}",
+            self.radius,
+            states,
+            self.middle as u32,
+            self.survival.0,
+            self.survival.1,
+            self.birth.0,
+            self.birth.1,
+            neighbourhood
+        );
+    }
+}
+
+fn parse_number(value: &str) -> Result<u32> {
+    return value
+        .trim()
+        .parse()
+        .with_context(|| format!("expected a number, got {value:?}"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_both_forms_of_ranges() {
+        let bosco = LtlRule::parse("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        assert_eq!(LtlRule::parse("R5,C0,M1,S34,58,B34,45,NM").unwrap(), bosco);
+        assert_eq!((bosco.radius, bosco.states, bosco.middle), (5, 2, true));
+        assert_eq!((bosco.survival, bosco.birth), ((34, 58), (34, 45)));
+        assert_eq!(bosco.to_string(), "R5,C0,M1,S34..58,B34..45,NM");
+        let rule = LtlRule::parse("r2,c3,m0,s1..2,b3..4,nc").unwrap();
+        assert_eq!(LtlRule::parse(&rule.to_string()).unwrap(), rule);
+    }
+
+    #[test]
+    fn neighbourhoods_narrow_away_from_the_centre() {
+        let widths = |neighbourhood: Neighbourhood| {
+            (0..=2)
+                .map(|dy| neighbourhood.half_width(2, dy))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(widths(Neighbourhood::Moore), [2, 2, 2]);
+        assert_eq!(widths(Neighbourhood::VonNeumann), [2, 1, 0]);
+        assert_eq!(widths(Neighbourhood::Circular), [2, 2, 1]);
+        let rule = LtlRule::parse("R2,C0,M0,S1..2,B3..3,NN").unwrap();
+        assert_eq!(rule.params()[7..], [0, 1, 2, 1, 0]);
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        assert!(LtlRule::parse("R0,C0,M0,S1..2,B3..3,NM").is_err());
+        assert!(LtlRule::parse("R17,C0,M0,S1..2,B3..3,NM").is_err());
+        assert!(LtlRule::parse("R2,C0,M0,S1..2,NM").is_err());
+        assert!(LtlRule::parse("R2,C0,M0,S1..2,B3").is_err());
+        assert!(LtlRule::parse("R2,C0,M0,S1..2,B3..3,NX").is_err());
+        assert!(LtlRule::parse("R2,C999,M0,S1..2,B3..3,NM").is_err());
+    }
+}
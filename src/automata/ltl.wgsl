@@ -0,0 +1,110 @@
+// Larger than Life: totalistic rules over a neighbourhood of radius R.
+//
+// Each workgroup copies its 16x16 block of cells plus an apron of R cells
+// into shared memory and turns every row of that tile into a prefix sum. A
+// neighbourhood row then costs a single subtraction, so counting takes
+// O(R) per cell rather than O(R^2).
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+struct Rule {
+    radius: u32,
+    states: u32,
+    middle: u32,
+    survive_min: u32,
+    survive_max: u32,
+    birth_min: u32,
+    birth_max: u32,
+    // Cells either side of the centre column on each of the 2R + 1 rows.
+    half_widths: array<u32>,
+}
+
+@group(2) @binding(0)
+var<storage, read> rule: Rule;
+
+const TILE: u32 = 16u;
+const MAX_RADIUS: u32 = 16u;
+// One extra column so that every row starts with a zero.
+const STRIDE: u32 = TILE + 2u * MAX_RADIUS + 1u;
+
+var<workgroup> rows: array<u32, (TILE + 2u * MAX_RADIUS) * STRIDE>;
+
+fn state(position: vec2<i32>) -> u32 {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    if any(position < vec2<i32>(0)) || any(position >= size) {
+        return 0u;
+    }
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+@compute
+@workgroup_size(16, 16, 1)
+fn cm_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    let radius = rule.radius;
+    let span = TILE + 2u * radius;
+    let origin = vec2<i32>(workgroup_id.xy * TILE) - vec2<i32>(i32(radius));
+
+    for (var i = local_index; i < span * span; i += TILE * TILE) {
+        let tx = i % span;
+        let ty = i / span;
+        let alive = state(origin + vec2<i32>(i32(tx), i32(ty))) == 1u;
+        rows[ty * STRIDE + tx + 1u] = select(0u, 1u, alive);
+    }
+    workgroupBarrier();
+
+    if local_index < span {
+        let row = local_index * STRIDE;
+        rows[row] = 0u;
+        for (var i = 1u; i <= span; i++) {
+            rows[row + i] += rows[row + i - 1u];
+        }
+    }
+    workgroupBarrier();
+
+    let size = textureDimensions(t_diffuse);
+    let x = global_invocation_id.x;
+    let y = global_invocation_id.y;
+    if x >= size.x || y >= size.y {
+        return;
+    }
+
+    // Position of the cell inside the tile, including the apron.
+    let local = global_invocation_id.xy - workgroup_id.xy * TILE + vec2<u32>(radius);
+
+    var count = 0u;
+    for (var dy = 0u; dy <= 2u * radius; dy++) {
+        let width = rule.half_widths[dy];
+        let row = (local.y + dy - radius) * STRIDE;
+        count += rows[row + local.x + width + 1u] - rows[row + local.x - width];
+    }
+
+    let current = state(vec2<i32>(i32(x), i32(y)));
+    if current == 1u && rule.middle == 0u {
+        count -= 1u;
+    }
+
+    var next = 0u;
+    if current == 0u {
+        if count >= rule.birth_min && count <= rule.birth_max {
+            next = 1u;
+        }
+    } else if current == 1u {
+        if count >= rule.survive_min && count <= rule.survive_max {
+            next = 1u;
+        } else if rule.states > 2u {
+            next = 2u;
+        }
+    } else {
+        next = (current + 1u) % rule.states;
+    }
+
+    textureStore(t_output, vec2<i32>(i32(x), i32(y)), vec4<f32>(f32(next) / 255.0, 0.0, 0.0, 1.0));
+}
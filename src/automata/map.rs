@@ -0,0 +1,106 @@
+use std::fmt;
+
+use anyhow::*;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Base64 characters needed to hold the 512 bit table.
+const ENCODED_LENGTH: usize = 86;
+
+/// An arbitrary Moore neighbourhood rule given as a `MAP` string.
+///
+/// The table holds the next state for each of the 512 neighbourhoods, indexed
+/// with the cells read row by row so that the north west cell is the most
+/// significant bit and the south east cell the least.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MapRule {
+    pub table: Vec<bool>,
+}
+
+impl MapRule {
+    pub fn parse(rule: &str) -> Result<Self> {
+        let encoded = rule
+            .strip_prefix("MAP")
+            .ok_or_else(|| anyhow!("MAP rules start with MAP"))?
+            .trim_end_matches('=');
+        if encoded.len() != ENCODED_LENGTH {
+            bail!(
+                "expected {ENCODED_LENGTH} base64 characters for a Moore neighbourhood, got {}",
+                encoded.len()
+            );
+        }
+
+        let mut table = Vec::with_capacity(512);
+        for c in encoded.bytes() {
+            let value = BASE64
+                .iter()
+                .position(|&b| b == c)
+                .ok_or_else(|| anyhow!("invalid base64 character {:?}", c as char))?;
+            table.extend((0..6).rev().map(|bit| value & (1 << bit) != 0));
+        }
+        table.truncate(512);
+
+        return Ok(MapRule { table });
+    }
+
+    /// The table packed 32 entries to a word for `map.wgsl`.
+    pub fn params(&self) -> Vec<u32> {
+        return self
+            .table
+            .chunks(32)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |word, (bit, &alive)| word | (alive as u32) << bit)
+            })
+            .collect();
+    }
+}
+
+impl fmt::Display for MapRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MAP")?;
+        for chunk in self.table.chunks(6) {
+            let value = chunk
+                .iter()
+                .chain(std::iter::repeat(&false))
+                .take(6)
+                .fold(0, |value, &bit| value << 1 | bit as usize);
+            write!(f, "{}", BASE64[value] as char)?;
+        }
+        return fmt::Result::Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let table = (0..512)
+            .map(|index: usize| index.count_ones() % 3 == 1)
+            .collect();
+        let rule = MapRule { table };
+        let written = rule.to_string();
+        assert_eq!(written.len(), 3 + ENCODED_LENGTH);
+        assert_eq!(MapRule::parse(&written).unwrap(), rule);
+        assert_eq!(MapRule::parse(&format!("{written}==")).unwrap(), rule);
+    }
+
+    #[test]
+    fn starts_with_the_empty_neighbourhood() {
+        let rule = MapRule::parse(&format!("MAPg{}", "A".repeat(ENCODED_LENGTH - 1))).unwrap();
+        assert!(rule.table[0]);
+        assert_eq!(rule.table.iter().filter(|&&alive| alive).count(), 1);
+        assert_eq!(rule.params()[0], 1);
+    }
+
+    #[test]
+    fn rejects_malformed_tables() {
+        assert!(MapRule::parse("MAPABC").is_err());
+        assert!(MapRule::parse(&format!("MAP{}", "!".repeat(ENCODED_LENGTH))).is_err());
+        assert!(MapRule::parse(&"A".repeat(ENCODED_LENGTH)).is_err());
+    }
+}
@@ -0,0 +1,45 @@
+// MAP rules: an arbitrary next state for each of the 512 Moore
+// neighbourhoods, including the cell itself.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+// 512 bits, packed 32 to a word starting from the least significant bit.
+@group(2) @binding(0)
+var<storage, read> table: array<u32, 16>;
+
+fn state(position: vec2<i32>) -> u32 {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    if any(position < vec2<i32>(0)) || any(position >= size) {
+        return 0u;
+    }
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let size = textureDimensions(t_diffuse);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+    let position = vec2<i32>(global_invocation_id.xy);
+
+    // Read row by row so the north west cell ends up as the top bit.
+    var index = 0u;
+    for (var j: i32 = -1; j <= 1; j++) {
+        for (var i: i32 = -1; i <= 1; i++) {
+            let alive = state(position + vec2<i32>(i, j)) == 1u;
+            index = (index << 1u) | select(0u, 1u, alive);
+        }
+    }
+
+    let next = (table[index >> 5u] >> (index & 31u)) & 1u;
+
+    textureStore(t_output, position, vec4<f32>(f32(next) / 255.0, 0.0, 0.0, 1.0));
+}
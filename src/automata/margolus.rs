@@ -0,0 +1,144 @@
+use std::fmt;
+
+use anyhow::*;
+
+/// A rule on the Margolus neighbourhood, where the board is split into blocks
+/// of 2x2 cells, shifted by a cell every other generation, and every block is
+/// replaced by the block its pattern maps to. Written in MCell's notation,
+/// e.g. the billiard ball model `MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15`,
+/// where a block's pattern counts 1 for its top left cell, 2 for the top
+/// right, 4 for the bottom left and 8 for the bottom right.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MargolusRule {
+    pub map: [u8; 16],
+}
+
+/// Rules with names of their own.
+const NAMED: [(&str, [u8; 16]); 3] = [
+    (
+        "critters",
+        [15, 14, 13, 3, 11, 5, 6, 1, 7, 9, 10, 2, 12, 4, 8, 0],
+    ),
+    (
+        "tron",
+        [15, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 0],
+    ),
+    (
+        "bbm",
+        [0, 8, 4, 3, 2, 5, 9, 7, 1, 6, 10, 11, 12, 13, 14, 15],
+    ),
+];
+
+impl MargolusRule {
+    /// Whether `rule` names a Margolus rule, in MCell's notation or by name.
+    pub fn matches(rule: &str) -> bool {
+        let lowercase = rule.to_ascii_lowercase();
+        return lowercase.starts_with("ms,d") || NAMED.iter().any(|(name, _)| lowercase == *name);
+    }
+
+    pub fn parse(rule: &str) -> Result<Self> {
+        let lowercase = rule.to_ascii_lowercase();
+        if let Some((_, map)) = NAMED.iter().find(|(name, _)| lowercase == *name) {
+            return Ok(MargolusRule { map: *map });
+        }
+        let Some(entries) = lowercase.strip_prefix("ms,d") else {
+            bail!("expected a Margolus rule, got {rule}");
+        };
+
+        let entries: Vec<u8> = entries
+            .split(';')
+            .map(|entry| {
+                let entry: u8 = entry
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("expected a block, got {entry:?}"))?;
+                if entry > 15 {
+                    bail!("blocks go up to 15, got {entry}");
+                }
+                return Ok(entry);
+            })
+            .collect::<Result<_>>()?;
+        let map = entries
+            .try_into()
+            .map_err(|entries: Vec<u8>| anyhow!("expected 16 blocks, got {}", entries.len()))?;
+        return Ok(MargolusRule { map });
+    }
+
+    /// Whether every block comes from exactly one other, so the rule can be
+    /// run backwards.
+    pub fn is_reversible(&self) -> bool {
+        let mut seen = [false; 16];
+        for &block in &self.map {
+            seen[block as usize] = true;
+        }
+        return seen.iter().all(|&seen| seen);
+    }
+
+    /// The map running the rule backwards, if it is reversible.
+    pub fn inverse(&self) -> Option<[u8; 16]> {
+        if !self.is_reversible() {
+            return None;
+        }
+        let mut inverse = [0; 16];
+        for (from, &to) in self.map.iter().enumerate() {
+            inverse[to as usize] = from as u8;
+        }
+        return Some(inverse);
+    }
+
+    /// Layout of the `Rule` struct in `margolus.wgsl`, the map followed by its
+    /// inverse, which is the map again for rules that aren't reversible.
+    pub fn params(&self) -> Vec<u32> {
+        let inverse = self.inverse().unwrap_or(self.map);
+        return self
+            .map
+            .iter()
+            .chain(&inverse)
+            .map(|&block| block as u32)
+            .collect();
+    }
+}
+
+impl fmt::Display for MargolusRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let blocks: Vec<String> = self.map.iter().map(u8::to_string).collect();
+        return write!(f, "MS,D{}", blocks.join(";"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_rules_run_backwards() {
+        for (name, _) in NAMED {
+            let rule = MargolusRule::parse(name).unwrap();
+            assert!(rule.is_reversible(), "{name}");
+            let inverse = rule.inverse().unwrap();
+            for block in 0..16 {
+                assert_eq!(inverse[rule.map[block] as usize], block as u8, "{name}");
+                assert_eq!(rule.map[inverse[block] as usize], block as u8, "{name}");
+            }
+            assert_eq!(rule.params()[16..], inverse.map(u32::from));
+        }
+    }
+
+    #[test]
+    fn maps_that_merge_blocks_only_run_forwards() {
+        let mut rule = MargolusRule::parse("tron").unwrap();
+        rule.map[1] = 2;
+        assert!(!rule.is_reversible());
+        assert_eq!(rule.inverse(), None);
+        assert_eq!(rule.params()[16..], rule.params()[..16]);
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let bbm = MargolusRule::parse("BBM").unwrap();
+        assert_eq!(bbm.to_string(), "MS,D0;8;4;3;2;5;9;7;1;6;10;11;12;13;14;15");
+        assert_eq!(MargolusRule::parse(&bbm.to_string()).unwrap(), bbm);
+        assert!(MargolusRule::parse("MS,D0;1;2").is_err());
+        assert!(MargolusRule::parse(&bbm.to_string().replace("15", "16")).is_err());
+    }
+}
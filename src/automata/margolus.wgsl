@@ -0,0 +1,86 @@
+// Rules on the Margolus neighbourhood. The board is split into blocks of 2x2
+// cells, shifted by a cell every other generation, and every block is
+// replaced by the block its pattern maps to. The invocation at the top left
+// corner of a block writes all four of its cells, cells at the edges of the
+// board that aren't in a whole block stay as they are.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+struct Rule {
+    // The block each pattern maps to, counting 1 for the top left cell, 2 for
+    // the top right, 4 for the bottom left and 8 for the bottom right.
+    map: array<u32, 16>,
+    // The map run backwards.
+    inverse: array<u32, 16>,
+}
+
+@group(2) @binding(0)
+var<storage, read> rule: Rule;
+
+struct Step {
+    generation: u32,
+    agents: u32,
+    phase: u32,
+    reverse: u32,
+}
+
+@group(2) @binding(1)
+var<uniform> step: Step;
+
+fn state(position: vec2<i32>) -> u32 {
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+fn store(position: vec2<i32>, state: u32) {
+    textureStore(t_output, position, vec4<f32>(f32(state) / 255.0, 0.0, 0.0, 1.0));
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let size = textureDimensions(t_diffuse);
+    let position = global_invocation_id.xy;
+    if position.x >= size.x || position.y >= size.y {
+        return;
+    }
+
+    // Blocks start on odd rows and columns in odd phases, leaving the first
+    // row and column out.
+    let offset = vec2<u32>(step.phase);
+    if any(position < offset) {
+        store(vec2<i32>(position), state(vec2<i32>(position)));
+        return;
+    }
+    let corner = position - (position - offset) % 2u;
+    if any(corner + 1u >= size) {
+        store(vec2<i32>(position), state(vec2<i32>(position)));
+        return;
+    }
+    if any(position != corner) {
+        return;
+    }
+
+    let origin = vec2<i32>(corner);
+    let offsets = array<vec2<i32>, 4>(
+        vec2<i32>(0, 0),
+        vec2<i32>(1, 0),
+        vec2<i32>(0, 1),
+        vec2<i32>(1, 1),
+    );
+    var pattern = 0u;
+    for (var i = 0u; i < 4u; i++) {
+        pattern |= min(state(origin + offsets[i]), 1u) << i;
+    }
+
+    var block = rule.map[pattern];
+    if step.reverse != 0u {
+        block = rule.inverse[pattern];
+    }
+    for (var i = 0u; i < 4u; i++) {
+        store(origin + offsets[i], (block >> i) & 1u);
+    }
+}
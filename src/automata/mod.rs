@@ -0,0 +1,690 @@
+pub mod continuous;
+pub mod cyclic;
+pub mod elementary;
+pub mod hex;
+pub mod isotropic;
+pub mod life3d;
+pub mod ltl;
+pub mod map;
+pub mod margolus;
+pub mod reaction;
+pub mod rulefile;
+pub mod ruletable;
+pub mod ruletree;
+pub mod sand;
+pub mod sandpile;
+pub mod triangular;
+pub mod turmite;
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use anyhow::*;
+
+use crate::{lattice::Lattice, palette::Palette};
+use continuous::{LeniaRule, SmoothLifeRule};
+use cyclic::CyclicRule;
+use elementary::ElementaryRule;
+use hex::HexRule;
+use isotropic::IsotropicRule;
+use life3d::Life3dRule;
+use ltl::LtlRule;
+use map::MapRule;
+use margolus::MargolusRule;
+use reaction::GrayScottRule;
+use rulefile::GollyRule;
+use sand::FallingSandRule;
+use sandpile::SandpileRule;
+use triangular::TriangularRule;
+use turmite::TurmiteRule;
+
+/// The update rule the compute stage runs on the board.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Rule {
+    /// Conway's Game of Life, `cm_main` in `gol.wgsl`.
+    #[default]
+    Life,
+    LargerThanLife(LtlRule),
+    Isotropic(IsotropicRule),
+    Map(MapRule),
+    /// A rule loaded from a Golly `.rule` file.
+    Golly(GollyRule),
+    /// WireWorld, with Golly's numbering of the states: empty, electron head,
+    /// electron tail and conductor.
+    WireWorld,
+    /// A one dimensional rule, drawn as a spacetime diagram.
+    Elementary(ElementaryRule),
+    /// Langton's ant and other turmites, agents that walk over the board
+    /// rather than every cell updating at once.
+    Turmite(TurmiteRule),
+    /// Life-like and Generations rules on a hexagonal lattice.
+    Hex(HexRule),
+    /// Life-like and Generations rules on a triangular lattice.
+    Triangular(TriangularRule),
+    /// Life-like and Generations rules on a 3D volume.
+    Life3d(Life3dRule),
+    /// Cyclic automata and Greenberg-Hastings models.
+    Cyclic(CyclicRule),
+    /// Continuous rules, where cells hold a value between 0 and 1.
+    Lenia(LeniaRule),
+    SmoothLife(SmoothLifeRule),
+    /// Gray-Scott reaction-diffusion, with the concentration of B drawn.
+    GrayScott(GrayScottRule),
+    /// The abelian sandpile, where cells hold grain counts.
+    Sandpile(SandpileRule),
+    /// Falling sand, particles of different materials moving under gravity.
+    FallingSand(FallingSandRule),
+    /// Rules on 2x2 Margolus blocks, such as Critters and the billiard ball
+    /// model.
+    Margolus(MargolusRule),
+}
+
+/// Per-step values for the compute shaders, bound as a uniform at
+/// `@group(2) @binding(1)`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Step {
+    /// Generations run since the board was loaded, wrapping at `u32::MAX`.
+    pub generation: u32,
+    /// Number of ants in the agent buffer.
+    pub agents: u32,
+    /// Which of the two Margolus partitions the blocks are in, 1 shifting
+    /// them a cell right and down.
+    pub phase: u32,
+    /// Whether a reversible rule is being run backwards.
+    pub reverse: u32,
+}
+
+impl Rule {
+    /// Parses a rule given on the command line or typed in, which can also
+    /// be the path of a Golly `.rule` file.
+    pub fn parse_or_load(rule: &str) -> Result<Self> {
+        let rule = rule.trim();
+        if rule.ends_with(".rule") {
+            return Ok(Rule::Golly(GollyRule::load(Path::new(rule))?));
+        }
+        return Self::parse(rule);
+    }
+
+    /// Parses a rule, looking up rules named after Golly `.rule` files in
+    /// the working directory and `rules/`.
+    pub fn parse(rule: &str) -> Result<Self> {
+        return Self::parse_in(rule, &[Path::new("."), Path::new("rules")]);
+    }
+
+    /// Parses a rule, looking up rules named after Golly `.rule` files in
+    /// `directories` before the built-in rules, since names such as
+    /// `Byl-Loop` and `SDSR-Loop` would otherwise be read as built-in
+    /// notations.
+    fn parse_in(rule: &str, directories: &[&Path]) -> Result<Self> {
+        let rule = rule.trim();
+        if let Some(path) = rule_file(rule, directories) {
+            return Ok(Rule::Golly(GollyRule::load(&path)?));
+        }
+        if rule.eq_ignore_ascii_case("life") {
+            return Ok(Rule::Life);
+        }
+        if rule.eq_ignore_ascii_case("wireworld") {
+            return Ok(Rule::WireWorld);
+        }
+        let lowercase = rule.to_ascii_lowercase();
+        if lowercase.starts_with("lenia") {
+            return Ok(Rule::Lenia(LeniaRule::parse(rule)?));
+        }
+        if lowercase.starts_with("smoothlife") {
+            return Ok(Rule::SmoothLife(SmoothLifeRule::parse(rule)?));
+        }
+        if lowercase.starts_with("grayscott") || lowercase.starts_with("gray-scott") {
+            return Ok(Rule::GrayScott(GrayScottRule::parse(rule)?));
+        }
+        if lowercase.starts_with("sandpile") {
+            return Ok(Rule::Sandpile(SandpileRule::parse(rule)?));
+        }
+        if lowercase == "sand" || lowercase == "fallingsand" {
+            return Ok(Rule::FallingSand(FallingSandRule::parse(rule)?));
+        }
+        if MargolusRule::matches(rule) {
+            return Ok(Rule::Margolus(MargolusRule::parse(rule)?));
+        }
+        // 3D rules have four parts and start with the survival counts.
+        if rule.split('/').count() == 4
+            && rule.starts_with(|c: char| c.is_ascii_digit() || c == '/')
+        {
+            return Ok(Rule::Life3d(Life3dRule::parse(rule)?));
+        }
+        if rule.starts_with("MAP") {
+            return Ok(Rule::Map(MapRule::parse(rule)?));
+        }
+        // Ants are spelt with turns alone, which no other rule is.
+        if rule.starts_with('{') || (!rule.is_empty() && rule.chars().all(|c| "LRNU".contains(c))) {
+            return Ok(Rule::Turmite(TurmiteRule::parse(rule)?));
+        }
+        // Wolfram and totalistic codes are a letter followed by the code.
+        if rule.starts_with(['W', 'w', 'T', 't'])
+            && rule[1..]
+                .trim_start()
+                .starts_with(|c: char| c.is_ascii_digit())
+        {
+            return Ok(Rule::Elementary(ElementaryRule::parse(rule)?));
+        }
+        // Cyclic rules also start with their range, but separate their parts
+        // with slashes rather than commas.
+        if rule.starts_with(['R', 'r']) && rule.contains('/') {
+            return Ok(Rule::Cyclic(CyclicRule::parse(rule)?));
+        }
+        if rule.starts_with(['R', 'r']) {
+            return Ok(Rule::LargerThanLife(LtlRule::parse(rule)?));
+        }
+        if rule.starts_with(['B', 'b', 'S', 's']) && rule.ends_with(['H', 'h']) {
+            return Ok(Rule::Hex(HexRule::parse(rule)?));
+        }
+        // Hensel letters include `e` but never `l`, so only `le` is
+        // triangular.
+        if rule.starts_with(['B', 'b', 'S', 's'])
+            && (lowercase.ends_with('l') || lowercase.ends_with("le"))
+        {
+            return Ok(Rule::Triangular(TriangularRule::parse(rule)?));
+        }
+        if rule.starts_with(['B', 'b', 'S', 's']) {
+            // Life is written as `B3/S23`, so it reads back as itself.
+            let isotropic = IsotropicRule::parse(rule)?;
+            if isotropic == IsotropicRule::parse("B3/S23")? {
+                return Ok(Rule::Life);
+            }
+            return Ok(Rule::Isotropic(isotropic));
+        }
+
+        bail!("unrecognised rule: {rule}");
+    }
+
+    /// WGSL source containing the `cm_main` compute entry point for the rule.
+    pub fn shader(&self) -> &'static str {
+        match self {
+            Rule::Life => include_str!("gol.wgsl"),
+            Rule::LargerThanLife(_) => include_str!("ltl.wgsl"),
+            Rule::Isotropic(_) => include_str!("isotropic.wgsl"),
+            Rule::Map(_) => include_str!("map.wgsl"),
+            Rule::Golly(_) => include_str!("ruletree.wgsl"),
+            Rule::WireWorld => include_str!("wireworld.wgsl"),
+            Rule::Elementary(_) => include_str!("elementary.wgsl"),
+            Rule::Turmite(_) => include_str!("turmite.wgsl"),
+            Rule::Hex(_) => include_str!("hex.wgsl"),
+            Rule::Triangular(_) => include_str!("triangular.wgsl"),
+            Rule::Life3d(_) => include_str!("life3d.wgsl"),
+            Rule::Cyclic(_) => include_str!("cyclic.wgsl"),
+            Rule::Lenia(_) | Rule::SmoothLife(_) => include_str!("continuous.wgsl"),
+            Rule::GrayScott(_) => include_str!("reaction.wgsl"),
+            Rule::Sandpile(_) => include_str!("sandpile.wgsl"),
+            Rule::FallingSand(_) => include_str!("sand.wgsl"),
+            Rule::Margolus(_) => include_str!("margolus.wgsl"),
+        }
+    }
+
+    /// Contents of the rule buffer bound at `@group(2) @binding(0)`.
+    pub fn params(&self) -> Vec<u32> {
+        match self {
+            Rule::Life | Rule::WireWorld | Rule::Sandpile(_) => vec![0],
+            Rule::LargerThanLife(rule) => rule.params(),
+            Rule::Isotropic(rule) => rule.params(),
+            Rule::Map(rule) => rule.params(),
+            Rule::Golly(rule) => rule.tree.params(),
+            Rule::Elementary(rule) => rule.params(),
+            Rule::Turmite(rule) => rule.params(),
+            Rule::Hex(rule) => rule.params(),
+            Rule::Triangular(rule) => rule.params(),
+            Rule::Life3d(rule) => rule.params(),
+            Rule::Cyclic(rule) => rule.params(),
+            Rule::Lenia(rule) => rule.params(),
+            Rule::SmoothLife(rule) => rule.params(),
+            Rule::GrayScott(rule) => rule.params(),
+            Rule::FallingSand(rule) => rule.params(),
+            Rule::Margolus(rule) => rule.params(),
+        }
+    }
+
+    /// The `@workgroup_size` of the rule's `cm_main`.
+    pub fn workgroup_size(&self) -> (u32, u32) {
+        match self {
+            Rule::Life => (1, 1),
+            Rule::LargerThanLife(_) | Rule::Lenia(_) | Rule::SmoothLife(_) => (16, 16),
+            Rule::Isotropic(_)
+            | Rule::Map(_)
+            | Rule::Golly(_)
+            | Rule::WireWorld
+            | Rule::Elementary(_)
+            | Rule::Hex(_)
+            | Rule::Triangular(_)
+            | Rule::Cyclic(_)
+            | Rule::GrayScott(_)
+            | Rule::Sandpile(_)
+            | Rule::FallingSand(_)
+            | Rule::Margolus(_) => (8, 8),
+            Rule::Turmite(_) => (256, 1),
+            // With a depth of 4 as well.
+            Rule::Life3d(_) => (4, 4),
+        }
+    }
+
+    /// Whether `cm_main` runs once per ant rather than once per cell.
+    pub fn has_agents(&self) -> bool {
+        return matches!(self, Rule::Turmite(_));
+    }
+
+    /// Whether the board is a spacetime diagram, each row a generation of a
+    /// single row of cells.
+    pub fn is_one_dimensional(&self) -> bool {
+        return matches!(self, Rule::Elementary(_));
+    }
+
+    /// Whether the rule runs on a 3D volume rather than the board.
+    pub fn is_volume(&self) -> bool {
+        return matches!(self, Rule::Life3d(_));
+    }
+
+    /// Whether cells hold continuous values rather than states.
+    pub fn is_continuous(&self) -> bool {
+        return matches!(
+            self,
+            Rule::Lenia(_) | Rule::SmoothLife(_) | Rule::GrayScott(_)
+        );
+    }
+
+    /// Whether every generation can be undone, so the rule can be run
+    /// backwards.
+    pub fn is_reversible(&self) -> bool {
+        match self {
+            Rule::Margolus(rule) => rule.is_reversible(),
+            _ => false,
+        }
+    }
+
+    /// Whether cells hold grain counts rather than states.
+    pub fn is_sandpile(&self) -> bool {
+        return matches!(self, Rule::Sandpile(_));
+    }
+
+    /// Number of times `cm_main` runs for each generation.
+    pub fn substeps(&self) -> u32 {
+        match self {
+            Rule::GrayScott(rule) => rule.substeps,
+            Rule::Sandpile(rule) => rule.substeps,
+            _ => 1,
+        }
+    }
+
+    /// Whether the rule is stepped on the board, rather than a volume, field
+    /// or pile of its own.
+    pub fn runs_on_board(&self) -> bool {
+        return !self.is_volume() && !self.is_continuous() && !self.is_sandpile();
+    }
+
+    /// Whether the rule's square cells only depend on the cells around them
+    /// and nothing else about where they are.
+    fn is_local(&self) -> bool {
+        return self.runs_on_board()
+            && !self.has_agents()
+            && self.lattice() == Lattice::Square
+            && !matches!(self, Rule::Elementary(_) | Rule::FallingSand(_));
+    }
+
+    /// Whether the rule can be stepped a tile at a time in an unbounded
+    /// world, which needs local rules where the tiles left out for being
+    /// empty stay that way.
+    pub fn can_tile(&self) -> bool {
+        return self.is_local() && self.empty_stays_empty();
+    }
+
+    /// Whether a dead cell among dead cells stays dead, so the space around a
+    /// pattern stays empty. Rules with `B0` and the like fill it instead.
+    pub fn empty_stays_empty(&self) -> bool {
+        match self {
+            Rule::LargerThanLife(rule) => rule.birth.0 > 0,
+            Rule::Golly(rule) => rule.tree.next(&vec![0; rule.tree.neighbours as usize + 1]) == 0,
+            Rule::Margolus(rule) => rule.map[0] == 0,
+            _ => self.moore_table().is_none_or(|table| !table[0]),
+        }
+    }
+
+    /// Whether the rule moves whole 2x2 blocks of cells at once, so a cell
+    /// can't take its new state without the rest of its block.
+    pub fn updates_blocks(&self) -> bool {
+        return matches!(self, Rule::Margolus(_) | Rule::FallingSand(_));
+    }
+
+    /// Whether a board too large for one texture can be split into sections
+    /// for the rule. The cells past the board's edges have to act like dead
+    /// cells, where Margolus blocks instead stop at the edges.
+    pub fn can_split(&self) -> bool {
+        return self.is_local() && !matches!(self, Rule::Margolus(_));
+    }
+
+    /// The next state of the centre cell for each 3x3 neighbourhood, indexed
+    /// as in `MapRule`, for two state rules on the Moore neighbourhood.
+    pub fn moore_table(&self) -> Option<Vec<bool>> {
+        // Bits of the `MapRule` index for each neighbour in the order of
+        // `isotropic::OFFSETS`, the centre cell being bit 4.
+        const NEIGHBOURS: [u32; 8] = [8, 7, 6, 5, 3, 2, 1, 0];
+        let table = (0..512u32).map(|index| {
+            let alive = index & 1 << 4 != 0;
+            let config = NEIGHBOURS
+                .iter()
+                .enumerate()
+                .fold(0, |config, (i, &bit)| config | (index >> bit & 1) << i);
+            return (alive, config as usize);
+        });
+        match self {
+            Rule::Life => Some(
+                table
+                    .map(|(alive, config)| {
+                        let count = config.count_ones();
+                        count == 3 || (alive && count == 2)
+                    })
+                    .collect(),
+            ),
+            Rule::Isotropic(rule) => Some(
+                table
+                    .map(|(alive, config)| match alive {
+                        true => rule.survival[config],
+                        false => rule.birth[config],
+                    })
+                    .collect(),
+            ),
+            Rule::Map(rule) => Some(rule.table.clone()),
+            _ => None,
+        }
+    }
+
+    /// The birth and survival conditions of two state rules on the Moore
+    /// neighbourhood that only count their live neighbours, bit `n` of each
+    /// set when `n` live neighbours cause a birth or survival.
+    pub fn life_like(&self) -> Option<(u32, u32)> {
+        let table = self.moore_table()?;
+        let (mut birth, mut survival) = (0u32, 0u32);
+        for count in 0..=8 {
+            for alive in [false, true] {
+                // Every arrangement of `count` neighbours has to agree.
+                let mut next = (0..512usize)
+                    .filter(|index| (index & 1 << 4 != 0) == alive)
+                    .filter(|index| (index & !(1 << 4)).count_ones() == count)
+                    .map(|index| table[index]);
+                let first = next.next()?;
+                if next.any(|next| next != first) {
+                    return None;
+                }
+                match alive {
+                    true => survival |= u32::from(first) << count,
+                    false => birth |= u32::from(first) << count,
+                }
+            }
+        }
+        return Some((birth, survival));
+    }
+
+    /// The lattice the rule's neighbourhood is defined on.
+    pub fn lattice(&self) -> Lattice {
+        match self {
+            Rule::Hex(_) => Lattice::Hexagonal,
+            Rule::Triangular(_) => Lattice::Triangular,
+            _ => Lattice::Square,
+        }
+    }
+
+    /// Number of states a cell can be in.
+    pub fn states(&self) -> u32 {
+        match self {
+            Rule::Life
+            | Rule::Isotropic(_)
+            | Rule::Map(_)
+            | Rule::Elementary(_)
+            | Rule::Margolus(_) => 2,
+            Rule::LargerThanLife(rule) => rule.states,
+            Rule::Golly(rule) => rule.tree.states,
+            Rule::WireWorld => 4,
+            Rule::Turmite(rule) => rule.colours,
+            Rule::Hex(rule) => rule.states,
+            Rule::Triangular(rule) => rule.states,
+            Rule::Life3d(rule) => rule.states,
+            Rule::Cyclic(rule) => rule.states,
+            // Painting sets cells to 0 or 1.
+            Rule::Lenia(_) | Rule::SmoothLife(_) | Rule::GrayScott(_) => 2,
+            // Piles that can be stable, the palette has one more colour for
+            // those about to topple.
+            Rule::Sandpile(_) => 4,
+            Rule::FallingSand(rule) => rule.materials.len() as u32,
+        }
+    }
+
+    /// What painting `state` puts on the board, for rules whose states have
+    /// names.
+    pub fn state_name(&self, state: u8) -> Option<&'static str> {
+        // WireWorld's states are numbered as in Golly.
+        const WIREWORLD: [&str; 4] = ["empty", "electron head", "electron tail", "conductor"];
+        return match self {
+            Rule::WireWorld => WIREWORLD.get(state as usize).copied(),
+            Rule::FallingSand(rule) => rule
+                .materials
+                .get(state as usize)
+                .map(|material| material.name),
+            _ => None,
+        };
+    }
+
+    pub fn palette(&self) -> Palette {
+        match self {
+            Rule::Life
+            | Rule::Isotropic(_)
+            | Rule::Map(_)
+            | Rule::Elementary(_)
+            | Rule::Margolus(_) => Palette::gradient(2),
+            Rule::LargerThanLife(rule) => Palette::gradient(rule.states),
+            Rule::Turmite(rule) => Palette::gradient(rule.colours),
+            Rule::Hex(rule) => Palette::gradient(rule.states),
+            Rule::Triangular(rule) => Palette::gradient(rule.states),
+            Rule::Life3d(rule) => Palette::gradient(rule.states),
+            // Greenberg-Hastings has a resting state to draw as dead.
+            Rule::Cyclic(rule) if rule.greenberg_hastings => Palette::gradient(rule.states),
+            Rule::Cyclic(rule) => Palette::hues(rule.states),
+            Rule::Lenia(_) | Rule::SmoothLife(_) | Rule::GrayScott(_) => Palette::colour_map(),
+            Rule::Golly(rule) => rule.palette(),
+            Rule::FallingSand(rule) => Palette {
+                colours: rule
+                    .materials
+                    .iter()
+                    .map(|material| material.colour)
+                    .collect(),
+            },
+            Rule::Sandpile(_) => Palette {
+                colours: vec![
+                    [0.0, 0.0, 0.0, 1.0],
+                    [0.2, 0.3, 0.9, 1.0],
+                    [0.9, 0.8, 0.1, 1.0],
+                    [0.8, 0.1, 0.1, 1.0],
+                    [1.0, 1.0, 1.0, 1.0],
+                ],
+            },
+            // Golly's WireWorld colours.
+            Rule::WireWorld => Palette {
+                colours: vec![
+                    [0.0, 0.0, 0.0, 1.0],
+                    [0.0, 0.5, 1.0, 1.0],
+                    [1.0, 1.0, 1.0, 1.0],
+                    [1.0, 0.5, 0.0, 1.0],
+                ],
+            },
+        }
+    }
+}
+
+/// The `.rule` file a pattern file refers to by name. Patterns can come from
+/// anywhere, so names that lead to other directories aren't followed.
+fn rule_file(rule: &str, directories: &[&Path]) -> Option<PathBuf> {
+    if rule.is_empty() || rule.contains(['/', '\\', ':']) || rule.starts_with('.') {
+        return None;
+    }
+    return directories
+        .iter()
+        .map(|directory| directory.join(format!("{rule}.rule")))
+        .find(|path| path.is_file());
+}
+
+/// The rule in the notation `Rule::parse` accepts, as written to pattern
+/// file headers.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Life => write!(f, "B3/S23"),
+            Rule::LargerThanLife(rule) => rule.fmt(f),
+            Rule::Isotropic(rule) => rule.fmt(f),
+            Rule::Map(rule) => rule.fmt(f),
+            Rule::Golly(rule) => write!(f, "{}", rule.name),
+            Rule::WireWorld => write!(f, "WireWorld"),
+            Rule::Elementary(rule) => rule.fmt(f),
+            Rule::Turmite(rule) => rule.fmt(f),
+            Rule::Hex(rule) => rule.fmt(f),
+            Rule::Triangular(rule) => rule.fmt(f),
+            Rule::Life3d(rule) => rule.fmt(f),
+            Rule::Cyclic(rule) => rule.fmt(f),
+            Rule::Lenia(rule) => rule.fmt(f),
+            Rule::SmoothLife(rule) => rule.fmt(f),
+            Rule::GrayScott(rule) => rule.fmt(f),
+            Rule::Sandpile(rule) => rule.fmt(f),
+            Rule::FallingSand(rule) => rule.fmt(f),
+            Rule::Margolus(rule) => rule.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn life_reads_back_as_life() {
+        assert_eq!(Rule::parse(&Rule::Life.to_string()).unwrap(), Rule::Life);
+        assert_eq!(Rule::parse("b3/s23").unwrap(), Rule::Life);
+        assert!(matches!(
+            Rule::parse("B36/S23").unwrap(),
+            Rule::Isotropic(_)
+        ));
+    }
+
+    #[test]
+    fn only_codes_are_one_dimensional() {
+        assert!(matches!(Rule::parse("W110").unwrap(), Rule::Elementary(_)));
+        assert!(matches!(
+            Rule::parse("T20,R2").unwrap(),
+            Rule::Elementary(_)
+        ));
+        assert!(Rule::parse("tlife").is_err());
+        assert!(Rule::parse("Wolfram").is_err());
+    }
+
+    #[test]
+    fn only_four_parts_starting_with_counts_are_3d() {
+        for rule in ["4/4/5/M", "9-26/5-7,12-13,15/5/M", "/4/2/N"] {
+            assert!(
+                matches!(Rule::parse(rule).unwrap(), Rule::Life3d(_)),
+                "{rule}"
+            );
+        }
+        assert!(matches!(
+            Rule::parse("R1/T3/C3/NM").unwrap(),
+            Rule::Cyclic(_)
+        ));
+        assert!(matches!(Rule::parse("B2/S34/3H").unwrap(), Rule::Hex(_)));
+        assert!(Rule::parse("4/4/5/Q").is_err());
+        assert!(Rule::parse("4/4/27/M/M").is_err());
+    }
+
+    #[test]
+    fn hashlife_reads_life_the_same_however_it_is_written() {
+        let life = Rule::Life.moore_table().unwrap();
+        // Read row by row from the north west cell, the centre being bit 4.
+        assert!(life[0b111_000_000] && life[0b110_010_000]);
+        assert!(!life[0b110_000_000] && !life[0b111_010_001]);
+        let isotropic = IsotropicRule::parse("B3/S23").unwrap();
+        assert_eq!(Rule::Isotropic(isotropic).moore_table().unwrap(), life);
+        let map = MapRule {
+            table: life.clone(),
+        };
+        assert_eq!(Rule::Map(map).moore_table().unwrap(), life);
+    }
+
+    #[test]
+    fn only_tiles_rules_that_keep_empty_space_empty() {
+        let table = |rule: &str| {
+            let file = format!("@RULE Test\n@TABLE\nn_states:2\nneighborhood:vonNeumann\n{rule}\n");
+            return Rule::Golly(GollyRule::parse(&file).unwrap());
+        };
+        let mut map = Rule::Life.moore_table().unwrap();
+        for rule in [
+            Rule::Life,
+            Rule::parse("B36/S23").unwrap(),
+            Rule::parse("R2,C0,M0,S1..2,B3..3,NM").unwrap(),
+            Rule::parse("bbm").unwrap(),
+            Rule::Map(MapRule { table: map.clone() }),
+            Rule::WireWorld,
+            table("0,1,0,0,0,1"),
+        ] {
+            assert!(rule.empty_stays_empty() && rule.can_tile(), "{rule}");
+        }
+
+        map[0] = true;
+        for rule in [
+            Rule::parse("B03/S23").unwrap(),
+            Rule::parse("R2,C0,M0,S1..2,B0..3,NM").unwrap(),
+            Rule::parse("critters").unwrap(),
+            Rule::parse("tron").unwrap(),
+            Rule::Map(MapRule { table: map }),
+            table("0,0,0,0,0,1"),
+        ] {
+            assert!(!rule.empty_stays_empty() && !rule.can_tile(), "{rule}");
+            // Sections, like the board, have dead cells past their edges.
+            assert_eq!(
+                rule.can_split(),
+                !matches!(rule, Rule::Margolus(_)),
+                "{rule}"
+            );
+        }
+    }
+
+    #[test]
+    fn names_the_wireworld_states() {
+        let names = (0..4).map(|state| Rule::WireWorld.state_name(state));
+        assert!(names.eq([
+            Some("empty"),
+            Some("electron head"),
+            Some("electron tail"),
+            Some("conductor"),
+        ]));
+        assert_eq!(Rule::WireWorld.state_name(4), None);
+        assert_eq!(Rule::Life.state_name(1), None);
+    }
+
+    #[test]
+    fn only_looks_up_rule_files_by_name() {
+        assert!(Rule::parse("../Cargo.toml").is_err());
+        assert!(Rule::parse("/etc/passwd").is_err());
+        assert!(Rule::parse("src/automata/none").is_err());
+        assert!(Rule::parse(".hidden").is_err());
+        assert!(Rule::parse("rules\\..\\secret").is_err());
+        assert!(Rule::parse("missing.rule").is_err());
+    }
+
+    #[test]
+    fn rule_files_come_before_built_in_notations() {
+        let directory = std::env::temp_dir().join(format!("rules-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let file = "@RULE Sexyloop\n@TABLE\nn_states:2\nneighborhood:vonNeumann\n0,1,0,0,0,1\n";
+        std::fs::write(directory.join("Sexyloop.rule"), file).unwrap();
+        let rule = Rule::parse_in("Sexyloop", &[&directory]);
+        let built_in = Rule::parse_in("B3/S23", &[&directory]);
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        assert!(matches!(rule.unwrap(), Rule::Golly(rule) if rule.name == "Sexyloop"));
+        assert_eq!(built_in.unwrap(), Rule::Life);
+        assert!(Rule::parse_in("Sexyloop", &[&directory]).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+// Steps and draws boards of two state rules packed 32 cells to a word, bit
+// `i` of word `w` in a row holding the cell in column `32 * w + i`. Each
+// invocation steps a whole word, counting the neighbours of all 32 cells at
+// once with bitwise adders. The board quad and camera are the same as
+// `gol.wgsl`.
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+struct VertexInput {
+    @location(0) vert_pos: vec3<f32>,
+    @location(1) colour: vec3<f32>,
+    @location(2) tex_coords: vec2<f32>,
+}
+
+struct Camera {
+    scale: f32,
+    x: f32,
+    y: f32
+}
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+struct Board {
+    width: u32,
+    height: u32,
+    // Words in each row, the last one padded with dead cells.
+    words: u32,
+    // Bit `n` set when `n` live neighbours cause a birth or survival.
+    birth: u32,
+    survival: u32,
+}
+
+@group(0) @binding(0)
+var<storage, read> cells: array<u32>;
+
+@group(0) @binding(1)
+var<storage, read_write> next: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read> palette: array<vec4<f32>>;
+
+@group(0) @binding(4)
+var<uniform> board: Board;
+
+// Painting sets or clears single bits, so it goes through its own bind group
+// with the cells as atomics.
+struct Edit {
+    word: u32,
+    bit: u32,
+    alive: u32,
+    _padding: u32,
+}
+
+@group(0) @binding(5)
+var<storage, read_write> painted: array<atomic<u32>>;
+
+@group(0) @binding(6)
+var<storage, read> edits: array<Edit>;
+
+// Cells beyond the edges of the board are dead.
+fn word(column: i32, row: i32) -> u32 {
+    if column < 0 || row < 0 || column >= i32(board.words) || row >= i32(board.height) {
+        return 0u;
+    }
+    return cells[u32(row) * board.words + u32(column)];
+}
+
+@vertex
+fn vs_packed(model: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4((model.vert_pos.xy + vec2(camera.x, camera.y)) * camera.scale, model.vert_pos.z, 1.0);
+    out.tex_coords = model.tex_coords;
+    return out;
+}
+
+@fragment
+fn fs_packed(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(f32(board.width), f32(board.height));
+    let cell = vec2<u32>(clamp(in.tex_coords * size, vec2<f32>(0.0), size - 1.0));
+    let bits = cells[cell.y * board.words + cell.x / 32u];
+    return palette[(bits >> (cell.x % 32u)) & 1u];
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let column = i32(global_invocation_id.x);
+    let row = i32(global_invocation_id.y);
+    if column >= i32(board.words) || row >= i32(board.height) {
+        return;
+    }
+
+    // Masks of the cells whose neighbour in each direction is alive.
+    var neighbours: array<u32, 8>;
+    var found = 0;
+    for (var dy = -1; dy <= 1; dy++) {
+        let left = word(column - 1, row + dy);
+        let centre = word(column, row + dy);
+        let right = word(column + 1, row + dy);
+        // The cell to the west of each cell is one bit lower.
+        neighbours[found] = (centre << 1u) | (left >> 31u);
+        neighbours[found + 1] = (centre >> 1u) | (right << 31u);
+        found += 2;
+        if dy != 0 {
+            neighbours[found] = centre;
+            found += 1;
+        }
+    }
+
+    // Add the masks into the four bits of each cell's count.
+    var sum = array<u32, 4>(0u, 0u, 0u, 0u);
+    for (var i = 0; i < 8; i++) {
+        var carry = neighbours[i];
+        for (var bit = 0; bit < 4; bit++) {
+            let overflow = sum[bit] & carry;
+            sum[bit] ^= carry;
+            carry = overflow;
+        }
+    }
+
+    let alive = word(column, row);
+    var result = 0u;
+    for (var n = 0u; n <= 8u; n++) {
+        var matches = ~0u;
+        for (var bit = 0u; bit < 4u; bit++) {
+            matches &= select(~sum[bit], sum[bit], ((n >> bit) & 1u) != 0u);
+        }
+        let born = select(0u, ~alive, ((board.birth >> n) & 1u) != 0u);
+        let survives = select(0u, alive, ((board.survival >> n) & 1u) != 0u);
+        result |= matches & (born | survives);
+    }
+
+    // Keep the padding past the last column dead.
+    let end = board.width - u32(column) * 32u;
+    if end < 32u {
+        result &= (1u << end) - 1u;
+    }
+    next[u32(row) * board.words + u32(column)] = result;
+}
+
+@compute
+@workgroup_size(64, 1, 1)
+fn cm_paint(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    if global_invocation_id.x >= arrayLength(&edits) {
+        return;
+    }
+    let edit = edits[global_invocation_id.x];
+    if edit.alive != 0u {
+        atomicOr(&painted[edit.word], 1u << edit.bit);
+    } else {
+        atomicAnd(&painted[edit.word], ~(1u << edit.bit));
+    }
+}
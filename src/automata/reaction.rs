@@ -0,0 +1,156 @@
+use std::fmt;
+
+use anyhow::*;
+
+use super::continuous::parameters;
+
+/// Feed rates across the parameter map, from the top row to the bottom.
+const MAP_FEED: (f32, f32) = (0.0, 0.08);
+/// Kill rates across the parameter map, from the left column to the right.
+const MAP_KILL: (f32, f32) = (0.03, 0.07);
+
+/// Gray-Scott reaction-diffusion, two chemicals A and B diffusing over the
+/// board where B turns A into more B. A is fed in and B killed off at the
+/// given rates, e.g. `grayscott:f=0.055,k=0.062`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GrayScottRule {
+    /// Rate A is fed in at.
+    pub feed: f32,
+    /// Rate B is removed at.
+    pub kill: f32,
+    /// Diffusion rates of A and B.
+    pub diffusion: (f32, f32),
+    pub dt: f32,
+    /// Steps run for each generation, the patterns take thousands of steps to
+    /// form.
+    pub substeps: u32,
+    /// Whether feed and kill vary across the board instead, feed growing down
+    /// it and kill to the right, so one board shows every kind of pattern.
+    pub map: bool,
+}
+
+impl Default for GrayScottRule {
+    fn default() -> Self {
+        return GrayScottRule {
+            feed: 0.055,
+            kill: 0.062,
+            diffusion: (1.0, 0.5),
+            dt: 1.0,
+            substeps: 16,
+            map: false,
+        };
+    }
+}
+
+impl GrayScottRule {
+    pub fn parse(rule: &str) -> Result<Self> {
+        let name = if rule.to_ascii_lowercase().starts_with("gray-scott") {
+            "gray-scott"
+        } else {
+            "grayscott"
+        };
+        let mut gray_scott = GrayScottRule::default();
+        for (key, value) in parameters(rule, name)? {
+            match key.as_str() {
+                "f" | "feed" => gray_scott.feed = value.parse()?,
+                "k" | "kill" => gray_scott.kill = value.parse()?,
+                "da" => gray_scott.diffusion.0 = value.parse()?,
+                "db" => gray_scott.diffusion.1 = value.parse()?,
+                "dt" => gray_scott.dt = value.parse()?,
+                "steps" => gray_scott.substeps = value.parse()?,
+                "map" if value.is_empty() => gray_scott.map = true,
+                _ => bail!("unknown Gray-Scott parameter {key}"),
+            }
+        }
+        if gray_scott.substeps == 0 {
+            bail!("Gray-Scott needs at least one step per generation");
+        }
+        return Ok(gray_scott);
+    }
+
+    /// Layout of the `Rule` struct in `reaction.wgsl`.
+    pub fn params(&self) -> Vec<u32> {
+        let (feed, kill) = if self.map {
+            (MAP_FEED, MAP_KILL)
+        } else {
+            ((self.feed, self.feed), (self.kill, self.kill))
+        };
+        return [
+            feed.0,
+            feed.1,
+            kill.0,
+            kill.1,
+            self.diffusion.0,
+            self.diffusion.1,
+            self.dt,
+            // Pads the struct to its alignment.
+            0.0,
+        ]
+        .map(f32::to_bits)
+        .to_vec();
+    }
+}
+
+impl fmt::Display for GrayScottRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "grayscott:f={},k={},da={},db={},dt={},steps={}",
+            self.feed, self.kill, self.diffusion.0, self.diffusion.1, self.dt, self.substeps
+        )?;
+        if self.map {
+            write!(f, ",map")?;
+        }
+        return fmt::Result::Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        for rule in [
+            "grayscott:f=0.055,k=0.062,da=1,db=0.5,dt=1,steps=16",
+            "grayscott:f=0.03,k=0.06,da=0.9,db=0.4,dt=0.5,steps=1,map",
+        ] {
+            assert_eq!(GrayScottRule::parse(rule).unwrap().to_string(), rule);
+        }
+        let rule = GrayScottRule::parse("Gray-Scott: feed=0.04, kill=0.065, steps=4").unwrap();
+        assert_eq!((rule.feed, rule.kill, rule.substeps), (0.04, 0.065, 4));
+        assert_eq!(
+            GrayScottRule::parse("grayscott").unwrap(),
+            GrayScottRule::default()
+        );
+    }
+
+    #[test]
+    fn maps_spread_feed_and_kill_across_the_board() {
+        let params = |rule| {
+            let params = GrayScottRule::parse(rule).unwrap().params();
+            return params.into_iter().map(f32::from_bits).collect::<Vec<_>>();
+        };
+        assert_eq!(
+            params("grayscott:f=0.04,k=0.06"),
+            [0.04, 0.04, 0.06, 0.06, 1.0, 0.5, 1.0, 0.0]
+        );
+        let map = params("grayscott:f=0.04,k=0.06,map");
+        assert_eq!(map[..4], [MAP_FEED.0, MAP_FEED.1, MAP_KILL.0, MAP_KILL.1]);
+    }
+
+    #[test]
+    fn rejects_rules_it_cant_run() {
+        for rule in [
+            "grayscott:steps=0",
+            "grayscott:steps=-1",
+            "grayscott:f=x",
+            "grayscott:map=1",
+            "grayscott:x=1",
+            "grayscott 0.05",
+            "lenia",
+        ] {
+            assert!(GrayScottRule::parse(rule).is_err(), "{rule}");
+        }
+    }
+}
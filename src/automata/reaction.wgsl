@@ -0,0 +1,71 @@
+// Gray-Scott reaction-diffusion. The concentration of B is kept in the first
+// texture, so it is what gets drawn, and A in the second. The field wraps
+// around at its edges.
+
+@group(0) @binding(0)
+var t_field: texture_2d<f32>;
+
+@group(0) @binding(1)
+var t_second: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<r32float, write>;
+
+@group(0) @binding(4)
+var t_second_output: texture_storage_2d<r32float, write>;
+
+struct Rule {
+    // Feed and kill rates at the top left and bottom right of the board, the
+    // same unless the rule is a parameter map.
+    feed: vec2<f32>,
+    kill: vec2<f32>,
+    diffusion_a: f32,
+    diffusion_b: f32,
+    dt: f32,
+}
+
+@group(2) @binding(0)
+var<storage, read> rule: Rule;
+
+fn chemicals(position: vec2<i32>) -> vec2<f32> {
+    let size = vec2<i32>(textureDimensions(t_field));
+    let wrapped = (position % size + size) % size;
+    return vec2<f32>(textureLoad(t_field, wrapped, 0).x, textureLoad(t_second, wrapped, 0).x);
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let size = textureDimensions(t_field);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+    let position = vec2<i32>(global_invocation_id.xy);
+
+    // Laplacian over the Moore neighbourhood, edges weighted 0.2 and corners
+    // 0.05.
+    let current = chemicals(position);
+    var laplacian = -current;
+    for (var dy = -1; dy <= 1; dy++) {
+        for (var dx = -1; dx <= 1; dx++) {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+            let weight = select(0.2, 0.05, dx != 0 && dy != 0);
+            laplacian += weight * chemicals(position + vec2<i32>(dx, dy));
+        }
+    }
+
+    let t = vec2<f32>(global_invocation_id.xy) / vec2<f32>(max(size - 1u, vec2<u32>(1u)));
+    let feed = mix(rule.feed.x, rule.feed.y, t.y);
+    let kill = mix(rule.kill.x, rule.kill.y, t.x);
+
+    let b = current.x;
+    let a = current.y;
+    let reaction = a * b * b;
+    let next_a = a + rule.dt * (rule.diffusion_a * laplacian.y - reaction + feed * (1.0 - a));
+    let next_b = b + rule.dt * (rule.diffusion_b * laplacian.x + reaction - (kill + feed) * b);
+
+    textureStore(t_output, position, vec4<f32>(clamp(next_b, 0.0, 1.0), 0.0, 0.0, 1.0));
+    textureStore(t_second_output, position, vec4<f32>(clamp(next_a, 0.0, 1.0), 0.0, 0.0, 1.0));
+}
@@ -0,0 +1,148 @@
+use std::path::Path;
+
+use anyhow::*;
+
+use super::{ruletable, ruletree::RuleTree};
+use crate::palette::Palette;
+
+/// A rule loaded from a Golly `.rule` file, with its `@TABLE` or `@TREE`
+/// section compiled to a [`RuleTree`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GollyRule {
+    /// The name after `@RULE`, which patterns use to refer to the rule.
+    pub name: String,
+    pub tree: RuleTree,
+    /// Colours from the `@COLORS` section, as `(state, [r, g, b])`.
+    pub colours: Vec<(u8, [u8; 3])>,
+}
+
+impl GollyRule {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        return Self::parse(&text);
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut name = None;
+        let mut tree = None;
+        let mut colours = "";
+
+        for section in sections(text) {
+            let (header, body) = section.split_once('\n').unwrap_or((section, ""));
+            let mut header = header.split_whitespace();
+            match header.next() {
+                Some("@RULE") => name = header.next().map(str::to_string),
+                Some("@TABLE") => tree = Some(ruletable::compile(body).context("in @TABLE")?),
+                Some("@TREE") => tree = Some(RuleTree::parse(body).context("in @TREE")?),
+                Some("@COLORS") => colours = body,
+                _ => {}
+            }
+        }
+
+        let tree = tree.ok_or_else(|| anyhow!("rule file has no @TABLE or @TREE section"))?;
+        let name = name.ok_or_else(|| anyhow!("rule file has no @RULE name"))?;
+        return Ok(GollyRule {
+            name,
+            colours: parse_colours(colours, tree.states)?,
+            tree,
+        });
+    }
+
+    pub fn palette(&self) -> Palette {
+        let mut palette = Palette::gradient(self.tree.states);
+        for &(state, [r, g, b]) in &self.colours {
+            if let Some(colour) = palette.colours.get_mut(state as usize) {
+                *colour = [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0];
+            }
+        }
+        return palette;
+    }
+}
+
+/// Splits the file at each line starting with `@`.
+fn sections(text: &str) -> Vec<&str> {
+    let mut starts: Vec<usize> = text
+        .match_indices('@')
+        .map(|(index, _)| index)
+        .filter(|&index| index == 0 || text.as_bytes()[index - 1] == b'\n')
+        .collect();
+    starts.push(text.len());
+    return starts.windows(2).map(|w| &text[w[0]..w[1]]).collect();
+}
+
+/// Parses `state r g b` lines, and `r1 g1 b1 r2 g2 b2` lines which fade
+/// across the live states like Golly does.
+fn parse_colours(section: &str, states: u32) -> Result<Vec<(u8, [u8; 3])>> {
+    let mut colours = Vec::new();
+    for line in section.lines() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let values = line
+            .split_whitespace()
+            .map(str::parse::<u8>)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .with_context(|| format!("malformed colour: {line}"))?;
+        match values[..] {
+            [state, r, g, b] => colours.push((state, [r, g, b])),
+            [r1, g1, b1, r2, g2, b2] => {
+                let (start, end) = ([r1, g1, b1], [r2, g2, b2]);
+                let steps = states.saturating_sub(2).max(1) as f32;
+                for state in 1..states {
+                    let t = (state - 1) as f32 / steps;
+                    let colour = [0, 1, 2]
+                        .map(|i| (start[i] as f32 + (end[i] as f32 - start[i] as f32) * t) as u8);
+                    colours.push((state as u8, colour));
+                }
+            }
+            _ => bail!("malformed colour: {line}"),
+        }
+    }
+    return Ok(colours);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "\
+@RULE Spread
+A state that spreads north each generation.
+
+@TABLE
+n_states:3
+neighborhood:vonNeumann
+symmetries:none
+0,0,0,1,0,1
+
+@COLORS
+1 255 0 0 # red
+";
+
+    #[test]
+    fn reads_the_name_table_and_colours() {
+        let rule = GollyRule::parse(FILE).unwrap();
+        assert_eq!(rule.name, "Spread");
+        assert_eq!(rule.tree.states, 3);
+        assert_eq!(rule.tree.next(&[0, 0, 0, 1, 0]), 1);
+        assert_eq!(rule.colours, [(1, [255, 0, 0])]);
+        assert_eq!(rule.palette().colours[1], [1.0, 0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn fades_colours_across_the_live_states() {
+        let file = FILE.replace("1 255 0 0", "0 0 0 255 255 255");
+        let rule = GollyRule::parse(&file).unwrap();
+        assert_eq!(rule.colours, [(1, [0, 0, 0]), (2, [255, 255, 255])]);
+    }
+
+    #[test]
+    fn needs_a_name_and_a_rule() {
+        assert!(GollyRule::parse(&FILE.replace("@RULE Spread", "")).is_err());
+        let without_table = &FILE[..FILE.find("@TABLE").unwrap()];
+        assert!(GollyRule::parse(without_table).is_err());
+        assert!(GollyRule::parse(&FILE.replace("1 255 0 0", "1 red")).is_err());
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::*;
+
+use super::ruletree::RuleTree;
+
+/// Positions of the tree levels in a Moore transition, which is written as
+/// C, N, NE, E, SE, S, SW, W, NW followed by the new state.
+const MOORE_ORDER: [usize; 9] = [8, 2, 6, 4, 1, 7, 3, 5, 0];
+
+/// Positions of the tree levels in a von Neumann transition, written as
+/// C, N, E, S, W followed by the new state.
+const VON_NEUMANN_ORDER: [usize; 5] = [1, 4, 2, 3, 0];
+
+/// One cell of a transition: the states it matches and, for variables, the
+/// name that binds it to other cells using the same variable.
+struct Entry {
+    states: Vec<u8>,
+    variable: Option<String>,
+}
+
+/// A transition with every bound variable substituted.
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Transition {
+    inputs: Vec<Vec<u8>>,
+    output: u8,
+}
+
+/// Compiles the `@TABLE` section of a rule file into a [`RuleTree`], the same
+/// way Golly turns rule tables into rule trees. As in Golly, the first
+/// matching transition wins and cells with no matching transition keep
+/// their state.
+pub fn compile(section: &str) -> Result<RuleTree> {
+    let mut states = None;
+    let mut neighbours = 8;
+    let mut symmetries = "none".to_string();
+    let mut variables = HashMap::new();
+    let mut transitions = Vec::new();
+
+    for line in section.lines() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(definition) = line.strip_prefix("var ") {
+            let (name, values) = definition
+                .split_once('=')
+                .ok_or_else(|| anyhow!("malformed variable: {line}"))?;
+            let states = parse_states(values.trim(), &variables)?;
+            variables.insert(name.trim().to_string(), states);
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            match key.trim() {
+                "n_states" => states = Some(value.parse::<u32>()?),
+                "neighborhood" => {
+                    neighbours = match value {
+                        "Moore" => 8,
+                        "vonNeumann" => 4,
+                        _ => bail!("the {value} neighbourhood is not supported"),
+                    }
+                }
+                "symmetries" => symmetries = value.to_string(),
+                key => bail!("unknown rule table setting {key}"),
+            }
+            continue;
+        }
+
+        let entries = parse_transition(line, neighbours + 2, &variables)?;
+        transitions.extend(substitute(&entries)?);
+    }
+
+    let states = states.ok_or_else(|| anyhow!("missing n_states"))?;
+    if !(2..=256).contains(&states) {
+        bail!("rule tables need between 2 and 256 states, got {states}");
+    }
+    let out_of_range = transitions.iter().any(|transition| {
+        transition.output as u32 >= states
+            || transition
+                .inputs
+                .iter()
+                .flatten()
+                .any(|&s| s as u32 >= states)
+    });
+    if out_of_range {
+        bail!("transition uses a state above n_states");
+    }
+
+    let permutations = symmetry_permutations(&symmetries, neighbours)?;
+    let mut seen = HashSet::new();
+    let mut symmetric = Vec::new();
+    for transition in transitions {
+        for permutation in &permutations {
+            let mut inputs = transition.inputs.clone();
+            for (i, &target) in permutation.iter().enumerate() {
+                inputs[target + 1] = transition.inputs[i + 1].clone();
+            }
+            let transition = Transition {
+                inputs,
+                output: transition.output,
+            };
+            if seen.insert(transition.clone()) {
+                symmetric.push(transition);
+            }
+        }
+    }
+
+    let order: &[usize] = if neighbours == 8 {
+        &MOORE_ORDER
+    } else {
+        &VON_NEUMANN_ORDER
+    };
+    let mut builder = TreeBuilder {
+        states,
+        order,
+        transitions: symmetric
+            .iter()
+            .map(|transition| {
+                let matches = transition
+                    .inputs
+                    .iter()
+                    .map(|cell| {
+                        let mut matches = vec![false; states as usize];
+                        for &state in cell {
+                            matches[state as usize] = true;
+                        }
+                        matches
+                    })
+                    .collect();
+                (matches, transition.output)
+            })
+            .collect(),
+        nodes: Vec::new(),
+        interned: HashMap::new(),
+        memo: HashMap::new(),
+    };
+    let all = (0..builder.transitions.len() as u32).collect();
+    builder.build(0, all);
+
+    return RuleTree::from_nodes(states, neighbours as u32, &builder.nodes);
+}
+
+/// Parses a set of states such as `{0,1,a}`, which may name earlier
+/// variables, or a single state.
+fn parse_states(value: &str, variables: &HashMap<String, Vec<u8>>) -> Result<Vec<u8>> {
+    if let Some(inner) = value.strip_prefix('{').and_then(|v| v.strip_suffix('}')) {
+        let mut states = Vec::new();
+        for item in inner
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+        {
+            states.extend(parse_states(item, variables)?);
+        }
+        states.sort();
+        states.dedup();
+        return Ok(states);
+    }
+    if let Some(states) = variables.get(value) {
+        return Ok(states.clone());
+    }
+    return Ok(vec![value.parse().with_context(|| {
+        format!("unknown state or variable {value:?}")
+    })?]);
+}
+
+/// Splits a transition into its entries, either separated by commas and
+/// spaces or, for rules with fewer than 11 states, one digit per entry.
+fn parse_transition(
+    line: &str,
+    length: usize,
+    variables: &HashMap<String, Vec<u8>>,
+) -> Result<Vec<Entry>> {
+    let mut tokens = Vec::new();
+    let mut depth = 0;
+    let mut token = String::new();
+    for c in line.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 && (c == ',' || c.is_whitespace()) {
+            if !token.is_empty() {
+                tokens.push(std::mem::take(&mut token));
+            }
+            continue;
+        }
+        token.push(c);
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    if tokens.len() == 1
+        && tokens[0].len() == length
+        && tokens[0].bytes().all(|b| b.is_ascii_digit())
+    {
+        tokens = tokens[0].chars().map(String::from).collect();
+    }
+    if tokens.len() != length {
+        bail!("expected {length} entries in transition: {line}");
+    }
+
+    return tokens
+        .iter()
+        .map(|token| {
+            let variable = variables
+                .contains_key(token.as_str())
+                .then(|| token.clone());
+            let states = parse_states(token, variables)?;
+            Ok(Entry { states, variable })
+        })
+        .collect();
+}
+
+/// Expands the variables that appear more than once in a transition, which
+/// Golly binds to the same state everywhere they appear.
+fn substitute(entries: &[Entry]) -> Result<Vec<Transition>> {
+    let mut bound: Vec<&str> = Vec::new();
+    for entry in entries {
+        if let Some(name) = &entry.variable {
+            let count = entries
+                .iter()
+                .filter(|e| e.variable.as_ref() == Some(name))
+                .count();
+            if count > 1 && !bound.contains(&name.as_str()) {
+                bound.push(name);
+            }
+        }
+    }
+
+    let mut transitions = Vec::new();
+    let mut assignment = vec![0; bound.len()];
+    loop {
+        let resolve = |entry: &Entry| {
+            let index = entry
+                .variable
+                .as_deref()
+                .and_then(|name| bound.iter().position(|&b| b == name));
+            match index {
+                Some(index) => vec![entry.states[assignment[index]]],
+                None => entry.states.clone(),
+            }
+        };
+        let (output, inputs) = entries.split_last().unwrap();
+        let output = match resolve(output)[..] {
+            [state] => state,
+            _ => bail!("the new state must be a single state or a bound variable"),
+        };
+        transitions.push(Transition {
+            inputs: inputs.iter().map(resolve).collect(),
+            output,
+        });
+
+        // Step to the next combination of bound states, like an odometer.
+        let mut index = 0;
+        loop {
+            if index == bound.len() {
+                return Ok(transitions);
+            }
+            let size = entries
+                .iter()
+                .find(|e| e.variable.as_deref() == Some(bound[index]))
+                .unwrap()
+                .states
+                .len();
+            assignment[index] += 1;
+            if assignment[index] < size {
+                break;
+            }
+            assignment[index] = 0;
+            index += 1;
+        }
+    }
+}
+
+/// Where each neighbour moves to under every symmetry of the rule, with the
+/// neighbours listed clockwise from north.
+fn symmetry_permutations(symmetries: &str, neighbours: usize) -> Result<Vec<Vec<usize>>> {
+    let rotate = |steps: usize| (0..neighbours).map(|i| (i + steps) % neighbours).collect();
+    // Mirrors east and west, keeping north and south in place.
+    let reflect: Vec<usize> = (0..neighbours)
+        .map(|i| (neighbours - i) % neighbours)
+        .collect();
+    let quarter = neighbours / 4;
+
+    let rotations: Vec<Vec<usize>> = match symmetries {
+        "none" | "reflect_horizontal" => vec![rotate(0)],
+        "rotate4" | "rotate4reflect" => (0..4).map(|r| rotate(r * quarter)).collect(),
+        "rotate8" | "rotate8reflect" if neighbours == 8 => (0..8).map(rotate).collect(),
+        "permute" => return Ok(permutations(neighbours)),
+        _ => bail!("unsupported symmetry {symmetries}"),
+    };
+
+    let mut permutations = rotations.clone();
+    if symmetries.ends_with("reflect") || symmetries == "reflect_horizontal" {
+        permutations.extend(
+            rotations
+                .iter()
+                .map(|rotation| reflect.iter().map(|&i| rotation[i]).collect()),
+        );
+    }
+    return Ok(permutations);
+}
+
+fn permutations(length: usize) -> Vec<Vec<usize>> {
+    if length == 0 {
+        return vec![Vec::new()];
+    }
+    let mut result = Vec::new();
+    for permutation in permutations(length - 1) {
+        for position in 0..length {
+            let mut permutation = permutation.clone();
+            permutation.insert(position, length - 1);
+            result.push(permutation);
+        }
+    }
+    return result;
+}
+
+struct TreeBuilder<'a> {
+    states: u32,
+    order: &'a [usize],
+    /// For each transition, which states each cell matches and the new state.
+    transitions: Vec<(Vec<Vec<bool>>, u8)>,
+    nodes: Vec<(u32, Vec<u32>)>,
+    interned: HashMap<(u32, Vec<u32>), u32>,
+    /// Subtrees already built for a depth and set of matching transitions.
+    memo: HashMap<(usize, Vec<u32>), u32>,
+}
+
+impl TreeBuilder<'_> {
+    /// Builds the subtree for the cells from `depth` onwards, given the
+    /// transitions that still match the cells before it, and returns its
+    /// node index.
+    fn build(&mut self, depth: usize, remaining: Vec<u32>) -> u32 {
+        let key = (depth, remaining);
+        if let Some(&node) = self.memo.get(&key) {
+            return node;
+        }
+        let (depth, remaining) = key;
+
+        let position = self.order[depth];
+        let level = (self.order.len() - depth) as u32;
+        let mut entries = Vec::with_capacity(self.states as usize);
+        for state in 0..self.states {
+            let matching: Vec<u32> = remaining
+                .iter()
+                .copied()
+                .filter(|&t| self.transitions[t as usize].0[position][state as usize])
+                .collect();
+            let entry = if level == 1 {
+                // The cell itself is read last, so with no matching
+                // transition it keeps this state.
+                matching
+                    .first()
+                    .map_or(state, |&t| self.transitions[t as usize].1 as u32)
+            } else {
+                self.build(depth + 1, matching)
+            };
+            entries.push(entry);
+        }
+
+        let node = self.intern(level, entries);
+        self.memo.insert((depth, remaining), node);
+        return node;
+    }
+
+    fn intern(&mut self, level: u32, entries: Vec<u32>) -> u32 {
+        let key = (level, entries);
+        if let Some(&node) = self.interned.get(&key) {
+            return node;
+        }
+        let node = self.nodes.len() as u32;
+        self.nodes.push(key.clone());
+        self.interned.insert(key, node);
+        return node;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automata::Rule;
+
+    /// Life written out as a table, relying on the first transition that
+    /// matches winning.
+    const LIFE: &str = "\
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b=a
+var c=a
+var d=a
+var e=a
+var f=a
+var g=a
+var h=a
+0,1,1,1,0,0,0,0,0,1
+1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1
+1,a,b,c,d,e,f,g,h,0
+";
+
+    #[test]
+    fn compiles_life_to_the_same_table() {
+        let tree = compile(LIFE).unwrap();
+        let life = Rule::Life.moore_table().unwrap();
+        // Bits of the `MapRule` index in the order the tree reads the cells:
+        // NW, NE, SW, SE, N, W, E, S and the cell itself.
+        const BITS: [u32; 9] = [8, 6, 2, 0, 7, 5, 3, 1, 4];
+        for (index, &alive) in life.iter().enumerate() {
+            let cells = BITS.map(|bit| (index as u32) >> bit & 1);
+            assert_eq!(tree.next(&cells), alive as u32, "neighbourhood {index:09b}");
+        }
+    }
+
+    #[test]
+    fn rotates_transitions_by_the_symmetries() {
+        let table = "\
+n_states:3
+neighborhood:vonNeumann
+symmetries:rotate4
+0,1,0,0,0,2
+";
+        let tree = compile(table).unwrap();
+        // The tree reads N, W, E and S before the cell itself.
+        assert_eq!(tree.next(&[1, 0, 0, 0, 0]), 2);
+        assert_eq!(tree.next(&[0, 1, 0, 0, 0]), 2);
+        assert_eq!(tree.next(&[0, 0, 0, 1, 0]), 2);
+        assert_eq!(tree.next(&[1, 1, 0, 0, 0]), 0);
+        // Cells no transition matches keep their state.
+        assert_eq!(tree.next(&[1, 0, 0, 0, 1]), 1);
+        let tree = compile(&table.replace("rotate4", "none")).unwrap();
+        assert_eq!(tree.next(&[1, 0, 0, 0, 0]), 2);
+        assert_eq!(tree.next(&[0, 1, 0, 0, 0]), 0);
+    }
+
+    #[test]
+    fn binds_variables_used_more_than_once() {
+        let table = "\
+n_states:3
+neighborhood:vonNeumann
+symmetries:none
+var a={1,2}
+0,a,a,0,0,a
+";
+        let tree = compile(table).unwrap();
+        // North and east in the table, read as N, W, E, S and the cell.
+        assert_eq!(tree.next(&[1, 0, 1, 0, 0]), 1);
+        assert_eq!(tree.next(&[2, 0, 2, 0, 0]), 2);
+        assert_eq!(tree.next(&[1, 0, 2, 0, 0]), 0);
+        // Tables with fewer than 11 states can be written a digit an entry.
+        let tree = compile(&table.replace("0,a,a,0,0,a", "010002")).unwrap();
+        assert_eq!(tree.next(&[1, 0, 0, 0, 0]), 2);
+    }
+
+    #[test]
+    fn rejects_malformed_tables() {
+        assert!(compile("neighborhood:Moore\n0,1,1,1,0,0,0,0,0,1").is_err());
+        assert!(compile("n_states:2\n0,1,1,1,0,0,0,0,0,2").is_err());
+        assert!(compile("n_states:2\n0,1,1,1,0,0,0,0,1").is_err());
+        assert!(compile("n_states:2\nneighborhood:hexagonal").is_err());
+        assert!(compile("n_states:2\nsymmetries:rotate6").is_err());
+        // The new state can't be a variable left free to take any state.
+        let free = "n_states:2\nvar a={0,1}\nvar b=a\n0,a,0,0,0,0,0,0,0,b";
+        assert!(compile(free).is_err());
+    }
+}
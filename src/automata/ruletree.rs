@@ -0,0 +1,159 @@
+use anyhow::*;
+
+/// A Golly RuleTree, the lookup structure `ruletree.wgsl` evaluates for rules
+/// loaded from `.rule` files.
+///
+/// The tree is a DAG with one level per cell of the neighbourhood, read in
+/// the order Golly uses: NW, NE, SW, SE, N, W, E, S and finally the cell
+/// itself for Moore neighbourhoods, or N, W, E, S and the cell for von
+/// Neumann neighbourhoods. Every node holds one entry per state, which is the
+/// offset of the next node in `nodes` or, on the last level, the new state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleTree {
+    pub states: u32,
+    /// 4 for von Neumann neighbourhoods and 8 for Moore neighbourhoods.
+    pub neighbours: u32,
+    pub nodes: Vec<u32>,
+    pub root: u32,
+}
+
+impl RuleTree {
+    /// Builds the tree from `(level, entries)` nodes where the entries of
+    /// nodes above level 1 are indices of earlier nodes. The root is the
+    /// last node.
+    pub fn from_nodes(states: u32, neighbours: u32, nodes: &[(u32, Vec<u32>)]) -> Result<Self> {
+        let (root_level, _) = nodes
+            .last()
+            .ok_or_else(|| anyhow!("rule tree has no nodes"))?;
+        if *root_level != neighbours + 1 {
+            bail!("expected the root at level {}", neighbours + 1);
+        }
+
+        let mut flattened = Vec::with_capacity(nodes.len() * states as usize);
+        for (index, (level, entries)) in nodes.iter().enumerate() {
+            if entries.len() != states as usize {
+                bail!(
+                    "node {index} has {} entries, expected {states}",
+                    entries.len()
+                );
+            }
+            for &entry in entries {
+                if *level == 1 {
+                    if entry >= states {
+                        bail!("node {index} leads to state {entry} of {states}");
+                    }
+                    flattened.push(entry);
+                    continue;
+                }
+                let child = entry as usize;
+                if child >= index || nodes[child].0 != level - 1 {
+                    bail!("node {index} refers to invalid node {entry}");
+                }
+                flattened.push(entry * states);
+            }
+        }
+
+        return Ok(RuleTree {
+            states,
+            neighbours,
+            root: (nodes.len() as u32 - 1) * states,
+            nodes: flattened,
+        });
+    }
+
+    /// Parses the `@TREE` section of a rule file.
+    pub fn parse(section: &str) -> Result<Self> {
+        let mut states = None;
+        let mut neighbours = None;
+        let mut nodes = Vec::new();
+
+        for line in section.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some((key, value)) = line.split_once('=') {
+                let value: u32 = value.trim().parse()?;
+                match key.trim() {
+                    "num_states" => states = Some(value),
+                    "num_neighbors" => neighbours = Some(value),
+                    "num_nodes" => nodes.reserve(value as usize),
+                    key => bail!("unknown rule tree setting {key}"),
+                }
+                continue;
+            }
+
+            let mut values = line.split_whitespace().map(str::parse::<u32>);
+            let level = values.next().unwrap()?;
+            let entries = values.collect::<std::result::Result<Vec<_>, _>>()?;
+            nodes.push((level, entries));
+        }
+
+        let states = states.ok_or_else(|| anyhow!("missing num_states"))?;
+        let neighbours = neighbours.ok_or_else(|| anyhow!("missing num_neighbors"))?;
+        if neighbours != 4 && neighbours != 8 {
+            bail!("only 4 or 8 neighbours are supported, got {neighbours}");
+        }
+        return Self::from_nodes(states, neighbours, &nodes);
+    }
+
+    /// The new state of a cell given the states of its neighbourhood in the
+    /// order the tree reads them, as `ruletree.wgsl` walks it.
+    pub fn next(&self, cells: &[u32]) -> u32 {
+        return cells.iter().fold(self.root, |node, &state| {
+            self.nodes[(node + state) as usize]
+        });
+    }
+
+    /// Layout of the `Tree` struct in `ruletree.wgsl`.
+    pub fn params(&self) -> Vec<u32> {
+        let mut params = vec![self.states, self.neighbours, self.root];
+        params.extend(&self.nodes);
+        return params;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A von Neumann rule where each cell takes the state of its northern
+    /// neighbour, one node to a level.
+    const NORTH: &str = "\
+num_states=2
+num_neighbors=4
+num_nodes=9
+1 0 0
+1 1 1
+2 0 0
+2 1 1
+3 2 2
+3 3 3
+4 4 4
+4 5 5
+5 6 7
+";
+
+    #[test]
+    fn reads_the_northern_neighbour_first() {
+        let tree = RuleTree::parse(NORTH).unwrap();
+        assert_eq!((tree.states, tree.neighbours), (2, 4));
+        assert_eq!(tree.root, 16);
+        for cells in [[1, 0, 0, 0, 0], [1, 1, 1, 1, 1], [1, 0, 1, 0, 0]] {
+            assert_eq!(tree.next(&cells), 1);
+        }
+        for cells in [[0, 0, 0, 0, 1], [0, 1, 1, 1, 1]] {
+            assert_eq!(tree.next(&cells), 0);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_trees() {
+        assert!(RuleTree::parse(&NORTH.replace("5 6 7", "5 6 9")).is_err());
+        assert!(RuleTree::parse(&NORTH.replace("5 6 7", "5 7 7 7")).is_err());
+        assert!(RuleTree::parse(&NORTH.replace("1 1 1", "1 1 2")).is_err());
+        assert!(RuleTree::parse(&NORTH.replace("num_neighbors=4", "num_neighbors=6")).is_err());
+        assert!(RuleTree::parse(&NORTH.replace("5 6 7\n", "")).is_err());
+        assert!(RuleTree::parse(&NORTH.replace("num_states=2\n", "")).is_err());
+    }
+}
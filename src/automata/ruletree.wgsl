@@ -0,0 +1,62 @@
+// Rules loaded from Golly .rule files, evaluated by walking the rule tree
+// built from their @TABLE or @TREE section.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+struct Tree {
+    states: u32,
+    // 4 for von Neumann neighbourhoods, 8 for Moore neighbourhoods.
+    neighbours: u32,
+    root: u32,
+    // One entry per state for each node, holding the offset of the next
+    // node or, on the last level, the new state.
+    nodes: array<u32>,
+}
+
+@group(2) @binding(0)
+var<storage, read> tree: Tree;
+
+fn state(position: vec2<i32>) -> u32 {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    if any(position < vec2<i32>(0)) || any(position >= size) {
+        return 0u;
+    }
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let size = textureDimensions(t_diffuse);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+    let position = vec2<i32>(global_invocation_id.xy);
+
+    // The order Golly reads the neighbourhood in: NW, NE, SW, SE, N, W, E, S
+    // and the cell itself, where von Neumann rules only use the last five.
+    let order = array<vec2<i32>, 9>(
+        vec2<i32>(-1, -1),
+        vec2<i32>(1, -1),
+        vec2<i32>(-1, 1),
+        vec2<i32>(1, 1),
+        vec2<i32>(0, -1),
+        vec2<i32>(-1, 0),
+        vec2<i32>(1, 0),
+        vec2<i32>(0, 1),
+        vec2<i32>(0, 0),
+    );
+
+    var node = tree.root;
+    for (var i = 8u - tree.neighbours; i < 9u; i++) {
+        node = tree.nodes[node + state(position + order[i])];
+    }
+
+    textureStore(t_output, position, vec4<f32>(f32(node) / 255.0, 0.0, 0.0, 1.0));
+}
@@ -0,0 +1,179 @@
+use std::fmt;
+
+/// How a material moves, `Kind` in `sand.wgsl`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// Empty space, which everything else moves through.
+    Empty,
+    /// Never moves, and nothing moves through it.
+    Static,
+    /// Falls straight down or slides off diagonally.
+    Powder,
+    /// Falls like a powder and also spreads sideways.
+    Liquid,
+    /// Rises, and spreads sideways.
+    Gas,
+}
+
+/// One state of the falling sand rule.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    pub name: &'static str,
+    /// Heavier materials sink through lighter ones, gases are lighter than
+    /// empty space.
+    pub density: i32,
+    pub kind: Kind,
+    /// The state the material turns into on its own, and the chance it does
+    /// each generation, e.g. fire burning down to smoke.
+    pub decay: Option<(u32, f32)>,
+    pub colour: [f32; 4],
+}
+
+/// Two materials meeting turn into two others, with some chance each
+/// generation they are next to each other.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reaction {
+    pub from: (u32, u32),
+    pub into: (u32, u32),
+    pub chance: f32,
+}
+
+/// Falling sand, where particles of sand, water, stone, fire and smoke move
+/// under gravity. The board is split into blocks of 2x2 cells, shifted by a
+/// cell every other generation, and each block is updated on its own, so
+/// particles move without two of them landing on the same cell.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FallingSandRule {
+    pub materials: Vec<Material>,
+    pub reactions: Vec<Reaction>,
+}
+
+const EMPTY: u32 = 0;
+const WATER: u32 = 2;
+const FIRE: u32 = 4;
+const SMOKE: u32 = 5;
+
+impl Default for FallingSandRule {
+    fn default() -> Self {
+        let material = |name, density, kind, decay, colour| Material {
+            name,
+            density,
+            kind,
+            decay,
+            colour,
+        };
+        return FallingSandRule {
+            materials: vec![
+                material("empty", 0, Kind::Empty, None, [0.0, 0.0, 0.0, 1.0]),
+                material("sand", 3, Kind::Powder, None, [0.9, 0.8, 0.5, 1.0]),
+                material("water", 2, Kind::Liquid, None, [0.1, 0.3, 0.9, 1.0]),
+                material("stone", 4, Kind::Static, None, [0.5, 0.5, 0.5, 1.0]),
+                material(
+                    "fire",
+                    -2,
+                    Kind::Gas,
+                    Some((SMOKE, 0.05)),
+                    [1.0, 0.4, 0.0, 1.0],
+                ),
+                material(
+                    "smoke",
+                    -1,
+                    Kind::Gas,
+                    Some((EMPTY, 0.02)),
+                    [0.3, 0.3, 0.3, 1.0],
+                ),
+            ],
+            // Water puts fire out.
+            reactions: vec![Reaction {
+                from: (FIRE, WATER),
+                into: (SMOKE, WATER),
+                chance: 1.0,
+            }],
+        };
+    }
+}
+
+/// Chances are passed to the shader as fractions of this.
+const CHANCE_SCALE: f32 = 65535.0;
+
+impl FallingSandRule {
+    pub fn parse(rule: &str) -> anyhow::Result<Self> {
+        if !rule.eq_ignore_ascii_case("sand") && !rule.eq_ignore_ascii_case("fallingsand") {
+            anyhow::bail!("expected a falling sand rule, got {rule}");
+        }
+        return Ok(FallingSandRule::default());
+    }
+
+    /// Layout of the `Rule` struct in `sand.wgsl`: the number of materials,
+    /// then the density, kind, decay state and decay chance of each, then a
+    /// table of what every pair of materials reacts into.
+    pub fn params(&self) -> Vec<u32> {
+        let count = self.materials.len() as u32;
+        let mut params = vec![count];
+        for material in &self.materials {
+            let (decay_into, decay_chance) = material.decay.unwrap_or((0, 0.0));
+            params.extend([
+                material.density as u32,
+                material.kind as u32,
+                decay_into,
+                (decay_chance * CHANCE_SCALE) as u32,
+            ]);
+        }
+
+        // Each entry packs the two materials the pair turns into and the
+        // chance it does, zero meaning they don't react.
+        let mut table = vec![0; (count * count) as usize];
+        for reaction in &self.reactions {
+            let chance = (reaction.chance * CHANCE_SCALE) as u32;
+            let (a, b) = reaction.from;
+            let (into_a, into_b) = reaction.into;
+            table[(a * count + b) as usize] = into_a | into_b << 8 | chance << 16;
+            table[(b * count + a) as usize] = into_b | into_a << 8 | chance << 16;
+        }
+        params.extend(table);
+        return params;
+    }
+}
+
+impl fmt::Display for FallingSandRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "FallingSand");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let rule = FallingSandRule::parse("sand").unwrap();
+        assert_eq!(FallingSandRule::parse(&rule.to_string()).unwrap(), rule);
+        assert!(FallingSandRule::parse("sandpile").is_err());
+    }
+
+    #[test]
+    fn lays_out_materials_then_reactions() {
+        let rule = FallingSandRule::default();
+        let count = rule.materials.len();
+        let params = rule.params();
+        assert_eq!(params.len(), 1 + 4 * count + count * count);
+        assert_eq!(params[0], count as u32);
+        let fire = &params[1 + 4 * FIRE as usize..][..4];
+        // Densities below empty space wrap around, as `i32`s in the shader.
+        assert_eq!(fire, [-2i32 as u32, Kind::Gas as u32, SMOKE, 3276]);
+    }
+
+    #[test]
+    fn reactions_work_either_way_round() {
+        let rule = FallingSandRule::default();
+        let count = rule.materials.len() as u32;
+        let table = &rule.params()[1 + 4 * count as usize..];
+        let entry = |a: u32, b: u32| table[(a * count + b) as usize];
+        // Fire next to water turns into smoke, the water staying.
+        assert_eq!(entry(FIRE, WATER), SMOKE | WATER << 8 | 65535 << 16);
+        assert_eq!(entry(WATER, FIRE), WATER | SMOKE << 8 | 65535 << 16);
+        let reacting = table.iter().filter(|&&entry| entry != 0).count();
+        assert_eq!(reacting, 2);
+    }
+}
@@ -0,0 +1,205 @@
+// Falling sand. The board is split into blocks of 2x2 cells, shifted by a
+// cell every other generation, and each block is updated by one invocation,
+// so particles can move without two of them landing on the same cell. The
+// invocation at the top left corner of a block writes all four of its cells,
+// cells at the edges of the board that aren't in a whole block stay as they
+// are.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+struct Rule {
+    materials: u32,
+    // Four entries for each material, its density, kind, decay state and
+    // decay chance, then what each pair of materials reacts into.
+    data: array<u32>,
+}
+
+@group(2) @binding(0)
+var<storage, read> rule: Rule;
+
+struct Step {
+    generation: u32,
+    agents: u32,
+    phase: u32,
+}
+
+@group(2) @binding(1)
+var<uniform> step: Step;
+
+const STATIC: u32 = 1u;
+const LIQUID: u32 = 3u;
+const GAS: u32 = 4u;
+
+// Chances are fractions of this.
+const CHANCE_SCALE: u32 = 65535u;
+
+fn state(position: vec2<i32>) -> u32 {
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+fn store(position: vec2<i32>, state: u32) {
+    textureStore(t_output, position, vec4<f32>(f32(state) / 255.0, 0.0, 0.0, 1.0));
+}
+
+fn density(material: u32) -> i32 {
+    return i32(rule.data[material * 4u]);
+}
+
+fn kind(material: u32) -> u32 {
+    return rule.data[material * 4u + 1u];
+}
+
+// Whether `a` sinks through `b`, neither of them being fixed in place.
+fn sinks(a: u32, b: u32) -> bool {
+    return kind(a) != STATIC && kind(b) != STATIC && density(a) > density(b);
+}
+
+// Whether a pair side by side swap places, one of them being of `spreading`
+// kind and neither fixed in place.
+fn spreads(left: u32, right: u32, spreading: u32) -> bool {
+    return left != right
+        && (kind(left) == spreading || kind(right) == spreading)
+        && kind(left) != STATIC
+        && kind(right) != STATIC;
+}
+
+fn hash(value: u32) -> u32 {
+    var x = value;
+    x ^= x >> 16u;
+    x *= 0x7feb352du;
+    x ^= x >> 15u;
+    x *= 0x846ca68bu;
+    x ^= x >> 16u;
+    return x;
+}
+
+// A random number up to `CHANCE_SCALE` for the block, different for every
+// `salt` and generation.
+fn random(block: vec2<u32>, salt: u32) -> u32 {
+    return hash(block.x ^ hash(block.y ^ hash(step.generation * 16u + salt))) % CHANCE_SCALE;
+}
+
+fn chance(block: vec2<u32>, salt: u32, odds: u32) -> bool {
+    return random(block, salt) < odds;
+}
+
+// The two materials of `pair` after any reaction between them.
+fn react(pair: vec2<u32>, block: vec2<u32>, salt: u32) -> vec2<u32> {
+    let entry = rule.data[rule.materials * 4u + pair.x * rule.materials + pair.y];
+    if chance(block, salt, entry >> 16u) {
+        return vec2<u32>(entry & 0xffu, (entry >> 8u) & 0xffu);
+    }
+    return pair;
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let size = textureDimensions(t_diffuse);
+    let position = global_invocation_id.xy;
+    if position.x >= size.x || position.y >= size.y {
+        return;
+    }
+
+    // Blocks start on odd rows and columns in odd phases, leaving the first
+    // row and column out.
+    let offset = vec2<u32>(step.phase);
+    if any(position < offset) {
+        store(vec2<i32>(position), state(vec2<i32>(position)));
+        return;
+    }
+    let corner = position - (position - offset) % 2u;
+    if any(corner + 1u >= size) {
+        store(vec2<i32>(position), state(vec2<i32>(position)));
+        return;
+    }
+    if any(position != corner) {
+        return;
+    }
+
+    // The block, top left, top right, bottom left and bottom right, with
+    // gravity pulling down the board.
+    let origin = vec2<i32>(corner);
+    var cells = array<u32, 4>(
+        state(origin),
+        state(origin + vec2<i32>(1, 0)),
+        state(origin + vec2<i32>(0, 1)),
+        state(origin + vec2<i32>(1, 1)),
+    );
+    let block = corner / 2u;
+
+    // Neighbouring pairs react, then materials decay on their own.
+    let pairs = array<vec2<u32>, 4>(
+        vec2<u32>(0u, 1u),
+        vec2<u32>(2u, 3u),
+        vec2<u32>(0u, 2u),
+        vec2<u32>(1u, 3u),
+    );
+    for (var i = 0u; i < 4u; i++) {
+        let pair = pairs[i];
+        let reacted = react(vec2<u32>(cells[pair.x], cells[pair.y]), block, i);
+        cells[pair.x] = reacted.x;
+        cells[pair.y] = reacted.y;
+    }
+    for (var i = 0u; i < 4u; i++) {
+        let material = cells[i];
+        if chance(block, 4u + i, rule.data[material * 4u + 3u]) {
+            cells[i] = rule.data[material * 4u + 2u];
+        }
+    }
+
+    // Heavier materials fall straight down through lighter ones, and gases
+    // rise the same way.
+    var moved = false;
+    for (var column = 0u; column < 2u; column++) {
+        let top = cells[column];
+        let bottom = cells[column + 2u];
+        if sinks(top, bottom) {
+            cells[column] = bottom;
+            cells[column + 2u] = top;
+            moved = true;
+        }
+    }
+
+    // Otherwise they slide off diagonally, trying the two diagonals in a
+    // random order so piles don't lean one way.
+    if !moved {
+        let first = select(0u, 1u, chance(block, 8u, CHANCE_SCALE / 2u));
+        for (var i = 0u; i < 2u; i++) {
+            // Top left to bottom right, or top right to bottom left.
+            let upper = (first + i) % 2u;
+            let lower = 3u - upper;
+            let top = cells[upper];
+            let bottom = cells[lower];
+            if sinks(top, bottom) {
+                cells[upper] = bottom;
+                cells[lower] = top;
+                moved = true;
+                break;
+            }
+        }
+    }
+
+    // Liquids spread along the bottom row, gases along the top.
+    if !moved && chance(block, 9u, CHANCE_SCALE / 2u) {
+        if spreads(cells[2], cells[3], LIQUID) {
+            let left = cells[2];
+            cells[2] = cells[3];
+            cells[3] = left;
+        }
+        if spreads(cells[0], cells[1], GAS) {
+            let left = cells[0];
+            cells[0] = cells[1];
+            cells[1] = left;
+        }
+    }
+
+    store(origin, cells[0]);
+    store(origin + vec2<i32>(1, 0), cells[1]);
+    store(origin + vec2<i32>(0, 1), cells[2]);
+    store(origin + vec2<i32>(1, 1), cells[3]);
+}
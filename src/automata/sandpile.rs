@@ -0,0 +1,95 @@
+use std::fmt;
+
+use anyhow::*;
+
+use super::continuous::parameters;
+
+/// The Bak-Tang-Wiesenfeld sandpile, where cells hold a number of grains and
+/// any with 4 or more topple, giving one to each of their von Neumann
+/// neighbours. Grains falling off the edge of the board are lost, e.g.
+/// `sandpile:steps=256,drop=10000`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SandpileRule {
+    /// Topples run for each generation.
+    pub substeps: u32,
+    /// Grains added to a cell when painting it.
+    pub drop: u32,
+    /// Whether to start by building the identity of the sandpile group, by
+    /// stabilising 6 grains everywhere and then stabilising what that leaves
+    /// short of 6.
+    pub identity: bool,
+}
+
+impl Default for SandpileRule {
+    fn default() -> Self {
+        return SandpileRule {
+            substeps: 256,
+            drop: 10000,
+            identity: false,
+        };
+    }
+}
+
+impl SandpileRule {
+    pub fn parse(rule: &str) -> Result<Self> {
+        let mut sandpile = SandpileRule::default();
+        for (key, value) in parameters(rule, "sandpile")? {
+            match key.as_str() {
+                "steps" => sandpile.substeps = value.parse()?,
+                "drop" => sandpile.drop = value.parse()?,
+                "identity" if value.is_empty() => sandpile.identity = true,
+                _ => bail!("unknown sandpile parameter {key}"),
+            }
+        }
+        if sandpile.substeps == 0 {
+            bail!("sandpiles need at least one topple per generation");
+        }
+        return Ok(sandpile);
+    }
+}
+
+impl fmt::Display for SandpileRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sandpile:steps={},drop={}", self.substeps, self.drop)?;
+        if self.identity {
+            write!(f, ",identity")?;
+        }
+        return fmt::Result::Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        for rule in [
+            "sandpile:steps=256,drop=10000",
+            "sandpile:steps=1,drop=4,identity",
+        ] {
+            assert_eq!(SandpileRule::parse(rule).unwrap().to_string(), rule);
+        }
+        let rule = SandpileRule::parse("sandpile: identity, steps=64").unwrap();
+        assert_eq!((rule.substeps, rule.drop, rule.identity), (64, 10000, true));
+        assert_eq!(
+            SandpileRule::parse("sandpile").unwrap(),
+            SandpileRule::default()
+        );
+    }
+
+    #[test]
+    fn rejects_rules_it_cant_run() {
+        for rule in [
+            "sandpile:steps=0",
+            "sandpile:steps=-1",
+            "sandpile:drop=x",
+            "sandpile:identity=1",
+            "sandpile:x=1",
+            "sandpile 256",
+            "grayscott",
+        ] {
+            assert!(SandpileRule::parse(rule).is_err(), "{rule}");
+        }
+    }
+}
@@ -0,0 +1,76 @@
+// The Bak-Tang-Wiesenfeld sandpile. Cells hold a number of grains, and any
+// with 4 or more give one to each neighbour. Grains falling off the edge of
+// the board are lost.
+
+@group(0) @binding(0)
+var t_grains: texture_2d<u32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<r32uint, write>;
+
+// Grains dropped onto each cell since the last topple.
+@group(0) @binding(4)
+var<storage, read_write> drops: array<u32>;
+
+// Cells that toppled or had grains dropped on them, zero once the pile is
+// stable.
+@group(0) @binding(5)
+var<storage, read_write> changed: atomic<u32>;
+
+fn grains(position: vec2<i32>) -> u32 {
+    let size = vec2<i32>(textureDimensions(t_grains));
+    if any(position < vec2<i32>(0)) || any(position >= size) {
+        return 0u;
+    }
+    return textureLoad(t_grains, position, 0).x;
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let size = textureDimensions(t_grains);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+    let position = vec2<i32>(global_invocation_id.xy);
+
+    var next = grains(position);
+    if next >= 4u {
+        next -= 4u;
+        atomicAdd(&changed, 1u);
+    }
+    let neighbours = array<vec2<i32>, 4>(
+        vec2<i32>(1, 0),
+        vec2<i32>(-1, 0),
+        vec2<i32>(0, 1),
+        vec2<i32>(0, -1),
+    );
+    for (var i = 0; i < 4; i++) {
+        if grains(position + neighbours[i]) >= 4u {
+            next += 1u;
+        }
+    }
+
+    let index = global_invocation_id.y * size.x + global_invocation_id.x;
+    if drops[index] > 0u {
+        next += drops[index];
+        drops[index] = 0u;
+        atomicAdd(&changed, 1u);
+    }
+
+    textureStore(t_output, position, vec4<u32>(next, 0u, 0u, 0u));
+}
+
+// Replaces a stable pile with what it is short of 6 grains everywhere, the
+// second half of building the identity.
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_complement(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let size = textureDimensions(t_grains);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+    let position = vec2<i32>(global_invocation_id.xy);
+    let next = 6u - min(grains(position), 6u);
+    textureStore(t_output, position, vec4<u32>(next, 0u, 0u, 0u));
+}
@@ -0,0 +1,69 @@
+// Draws boards too large for a single texture, a section at a time. Each
+// section's texture has a margin of cells copied from its neighbours around
+// it, which isn't drawn. The camera and the scale of the board are the same
+// as `gol.wgsl`.
+
+// Match `MARGIN` in tiles.rs.
+const MARGIN: u32 = 16u;
+
+struct Camera {
+    scale: f32,
+    x: f32,
+    y: f32
+}
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@group(0) @binding(0)
+var t_section: texture_2d<f32>;
+
+@group(0) @binding(3)
+var<storage, read> palette: array<vec4<f32>>;
+
+struct Section {
+    // First cell of the section on the board, and how many of its cells are
+    // on the board.
+    origin: vec2<f32>,
+    size: vec2<f32>,
+    // Size of the whole board in cells.
+    board: vec2<f32>,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(4)
+var<uniform> section: Section;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // Position in the section, measured in cells.
+    @location(0) cell: vec2<f32>,
+};
+
+@vertex
+fn vs_section(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[vertex_index] * section.size;
+    let cell = section.origin + corner;
+
+    // The board quad runs from -0.5 to 0.5, with rows going down it.
+    let position = vec2<f32>(cell.x / section.board.x - 0.5, 0.5 - cell.y / section.board.y);
+    var out: VertexOutput;
+    out.clip_position = vec4((position + vec2(camera.x, camera.y)) * camera.scale, 0.0, 1.0);
+    out.cell = corner;
+    return out;
+}
+
+@fragment
+fn fs_section(in: VertexOutput) -> @location(0) vec4<f32> {
+    let cell = min(vec2<u32>(max(in.cell, vec2<f32>(0.0))), vec2<u32>(section.size) - 1u);
+    let texel = textureLoad(t_section, cell + MARGIN, 0);
+    return palette[u32(round(texel.x * 255.0))];
+}
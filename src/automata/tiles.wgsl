@@ -0,0 +1,160 @@
+// Draws the tiles of the unbounded world, and finds the tiles still holding
+// live cells after each step. Tiles are kept side by side in an atlas, each
+// at its slot. The camera and the scale of the board are the same as
+// `gol.wgsl`, so the tiles over the board line up with its cells.
+
+// Match `TILE_SIZE`, `ATLAS_TILES` and `MARGIN` in tiles.rs.
+const TILE_SIZE: u32 = 64u;
+const ATLAS_TILES: u32 = 32u;
+const MARGIN: u32 = 16u;
+const SCRATCH_SIZE: u32 = TILE_SIZE + 2u * MARGIN;
+
+struct Camera {
+    scale: f32,
+    x: f32,
+    y: f32
+}
+
+@group(1) @binding(0)
+var<uniform> camera: Camera;
+
+@group(0) @binding(0)
+var t_atlas: texture_2d<f32>;
+
+@group(0) @binding(3)
+var<storage, read> palette: array<vec4<f32>>;
+
+struct Visible {
+    // Tile coordinates, the tile at (0, 0) starting at the board's first
+    // cell.
+    tile: vec2<i32>,
+    slot: u32,
+    _padding: u32,
+}
+
+@group(0) @binding(4)
+var<storage, read> visible: array<Visible>;
+
+struct View {
+    // Size of the board in cells, which sets the size cells are drawn at.
+    board: vec2<f32>,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(5)
+var<uniform> view: View;
+
+// Slots of the tiles stepped, and whether each slot has a live cell.
+@group(0) @binding(6)
+var<storage, read> stepped: array<u32>;
+
+@group(0) @binding(7)
+var<storage, read_write> alive: array<atomic<u32>>;
+
+// Tiles are gathered from one atlas into the other with their margins, or
+// scattered back without them.
+@group(0) @binding(8)
+var t_from: texture_2d<f32>;
+
+@group(0) @binding(9)
+var t_to: texture_storage_2d<rgba8unorm, write>;
+
+// Slots of the 3 by 3 tiles around each stepped tile, row by row, or -1
+// where there is none.
+@group(0) @binding(10)
+var<storage, read> neighbours: array<i32>;
+
+fn slot_origin(slot: u32) -> vec2<u32> {
+    return vec2<u32>(slot % ATLAS_TILES, slot / ATLAS_TILES) * TILE_SIZE;
+}
+
+fn scratch_origin(slot: u32) -> vec2<u32> {
+    return vec2<u32>(slot % ATLAS_TILES, slot / ATLAS_TILES) * SCRATCH_SIZE;
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // Position in the tile, measured in cells.
+    @location(0) cell: vec2<f32>,
+    @location(1) @interpolate(flat) slot: u32,
+};
+
+@vertex
+fn vs_tiles(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    let corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(0.0, 1.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let tile = visible[instance_index];
+    let corner = corners[vertex_index] * f32(TILE_SIZE);
+    let cell = vec2<f32>(tile.tile) * f32(TILE_SIZE) + corner;
+
+    // The board quad runs from -0.5 to 0.5, with rows going down it.
+    let position = vec2<f32>(cell.x / view.board.x - 0.5, 0.5 - cell.y / view.board.y);
+    var out: VertexOutput;
+    out.clip_position = vec4((position + vec2(camera.x, camera.y)) * camera.scale, 0.0, 1.0);
+    out.cell = corner;
+    out.slot = tile.slot;
+    return out;
+}
+
+@fragment
+fn fs_tiles(in: VertexOutput) -> @location(0) vec4<f32> {
+    let cell = min(vec2<u32>(max(in.cell, vec2<f32>(0.0))), vec2<u32>(TILE_SIZE - 1u));
+    let texel = textureLoad(t_atlas, slot_origin(in.slot) + cell, 0);
+    return palette[u32(round(texel.x * 255.0))];
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_alive(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    let slot = stepped[workgroup_id.z];
+    let texel = textureLoad(t_atlas, slot_origin(slot) + global_invocation_id.xy, 0);
+    if texel.x > 0.0 {
+        atomicMax(&alive[slot], 1u);
+    }
+}
+
+// Copies each stepped tile and the edges of its neighbours around it into
+// its slot of the scratch atlas.
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_gather(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    let slot = stepped[workgroup_id.z];
+    let cell = vec2<i32>(global_invocation_id.xy) - i32(MARGIN);
+    // Which of the 3 by 3 tiles the cell falls in, from -1 to 1.
+    let offset = (cell + i32(TILE_SIZE)) / i32(TILE_SIZE) - 1;
+    let neighbour = neighbours[workgroup_id.z * 9u + u32((offset.y + 1) * 3 + offset.x + 1)];
+    var texel = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    if neighbour >= 0 {
+        let inside = vec2<u32>(cell - offset * i32(TILE_SIZE));
+        texel = textureLoad(t_from, slot_origin(u32(neighbour)) + inside, 0);
+    }
+    textureStore(t_to, scratch_origin(slot) + global_invocation_id.xy, texel);
+}
+
+// Copies the next generation of each stepped tile, without its margin, back
+// to its slot of the atlas.
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_scatter(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    let slot = stepped[workgroup_id.z];
+    let texel = textureLoad(t_from, scratch_origin(slot) + MARGIN + global_invocation_id.xy, 0);
+    textureStore(t_to, slot_origin(slot) + global_invocation_id.xy, texel);
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+
+use anyhow::*;
+
+use super::hex::{parse_generations, write_generations};
+
+/// Which of the cells around a triangle count as its neighbours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriangularNeighbourhood {
+    /// Every triangle sharing an edge or a corner, 12 in all.
+    Moore,
+    /// Only the 3 triangles sharing an edge.
+    Edges,
+}
+
+impl TriangularNeighbourhood {
+    pub fn size(&self) -> u32 {
+        match self {
+            TriangularNeighbourhood::Moore => 12,
+            TriangularNeighbourhood::Edges => 3,
+        }
+    }
+}
+
+/// A totalistic or Generations rule on the triangular lattice, written like
+/// LifeViewer's triangular rules with an `L` suffix, e.g. `B4/S456L`, or `LE`
+/// to count only edge neighbours, e.g. `B1/S12LE`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TriangularRule {
+    /// Bit `n` is set when a dead cell with `n` live neighbours is born.
+    pub birth: u32,
+    /// Bit `n` is set when a live cell with `n` live neighbours survives.
+    pub survival: u32,
+    pub states: u32,
+    pub neighbourhood: TriangularNeighbourhood,
+}
+
+impl TriangularRule {
+    pub fn parse(rule: &str) -> Result<Self> {
+        let uppercase = rule.to_ascii_uppercase();
+        let (body, neighbourhood) = if uppercase.ends_with("LE") {
+            (&rule[..rule.len() - 2], TriangularNeighbourhood::Edges)
+        } else if let Some(body) = rule.strip_suffix(['L', 'l']) {
+            (body, TriangularNeighbourhood::Moore)
+        } else {
+            bail!("triangular rules end with L or LE, got {rule}");
+        };
+
+        let (birth, survival, states) = parse_generations(body, neighbourhood.size())?;
+        return Ok(TriangularRule {
+            birth,
+            survival,
+            states,
+            neighbourhood,
+        });
+    }
+
+    /// Layout of the `Rule` struct in `triangular.wgsl`.
+    pub fn params(&self) -> Vec<u32> {
+        return vec![
+            self.birth,
+            self.survival,
+            self.states,
+            (self.neighbourhood == TriangularNeighbourhood::Edges) as u32,
+        ];
+    }
+}
+
+impl fmt::Display for TriangularRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_generations(
+            f,
+            self.birth,
+            self.survival,
+            self.states,
+            self.neighbourhood.size(),
+        )?;
+        match self.neighbourhood {
+            TriangularNeighbourhood::Moore => write!(f, "L"),
+            TriangularNeighbourhood::Edges => write!(f, "LE"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        for rule in ["B4/S456L", "B1/S12LE", "B45/S34/3L", "B3/S0123LE"] {
+            assert_eq!(TriangularRule::parse(rule).unwrap().to_string(), rule);
+        }
+        let rule = TriangularRule::parse("b4/s4ac/C4l").unwrap();
+        assert_eq!(rule.to_string(), "B4/S4ac/4L");
+        assert_eq!(rule.neighbourhood, TriangularNeighbourhood::Moore);
+        assert_eq!(rule.params(), [1 << 4, 1 << 4 | 1 << 10 | 1 << 12, 4, 0]);
+    }
+
+    #[test]
+    fn only_counts_edges_with_le() {
+        let rule = TriangularRule::parse("B1/S12le").unwrap();
+        assert_eq!(rule.neighbourhood, TriangularNeighbourhood::Edges);
+        assert_eq!(rule.params()[3], 1);
+        // Only three triangles share an edge.
+        assert!(TriangularRule::parse("B4/S12LE").is_err());
+        assert!(TriangularRule::parse("B4/S12L").is_ok());
+        assert!(TriangularRule::parse("B4/S12").is_err());
+        assert!(TriangularRule::parse("B4/S1dL").is_err());
+    }
+}
@@ -0,0 +1,82 @@
+// Totalistic and Generations rules on a triangular lattice, see
+// `Lattice::Triangular`. Cells where `x + y` is even point up, the rest point
+// down.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+struct Rule {
+    // Bit `n` is set when a cell with `n` live neighbours is born or survives.
+    birth: u32,
+    survival: u32,
+    states: u32,
+    // Whether only the 3 cells sharing an edge count, rather than all 12
+    // sharing an edge or a corner.
+    edges: u32,
+}
+
+@group(2) @binding(0)
+var<storage, read> rule: Rule;
+
+fn state(position: vec2<i32>) -> u32 {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    if any(position < vec2<i32>(0)) || any(position >= size) {
+        return 0u;
+    }
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+fn live(position: vec2<i32>) -> u32 {
+    return select(0u, 1u, state(position) == 1u);
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    let position = vec2<i32>(global_invocation_id.xy);
+    if position.x >= size.x || position.y >= size.y {
+        return;
+    }
+
+    // The row a triangle shares its flat edge with, below it when it points
+    // up and above it when it points down. Its apex touches the other row.
+    let up = ((position.x + position.y) & 1) == 0;
+    let base = select(-1, 1, up);
+    let apex = -base;
+
+    var count = live(position + vec2<i32>(-1, 0)) + live(position + vec2<i32>(1, 0))
+        + live(position + vec2<i32>(0, base));
+    if rule.edges == 0u {
+        count += live(position + vec2<i32>(-2, 0)) + live(position + vec2<i32>(2, 0));
+        for (var i = -2; i <= 2; i++) {
+            if i != 0 {
+                count += live(position + vec2<i32>(i, base));
+            }
+        }
+        for (var i = -1; i <= 1; i++) {
+            count += live(position + vec2<i32>(i, apex));
+        }
+    }
+
+    let current = state(position);
+    var next = 0u;
+    if current == 0u {
+        next = (rule.birth >> count) & 1u;
+    } else if current == 1u {
+        if ((rule.survival >> count) & 1u) == 1u {
+            next = 1u;
+        } else {
+            next = 2u % rule.states;
+        }
+    } else {
+        next = (current + 1u) % rule.states;
+    }
+
+    textureStore(t_output, position, vec4<f32>(f32(next) / 255.0, 0.0, 0.0, 1.0));
+}
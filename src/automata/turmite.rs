@@ -0,0 +1,296 @@
+use std::fmt;
+
+use anyhow::*;
+
+/// Number of agents the ant buffer has room for. Match `MAX_ANTS` in
+/// `turmite.wgsl`.
+pub const MAX_ANTS: u32 = 1024;
+
+/// An agent walking over the board, laid out as `Ant` in `turmite.wgsl` and
+/// `agents.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Ant {
+    pub x: u32,
+    pub y: u32,
+    /// 0 is north, going clockwise.
+    pub heading: u32,
+    pub state: u32,
+}
+
+/// How far a turmite turns clockwise, in quarter turns.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Turn {
+    None,
+    Right,
+    UTurn,
+    Left,
+}
+
+impl Turn {
+    fn from_letter(letter: char) -> Result<Self> {
+        return Ok(match letter {
+            'N' => Turn::None,
+            'R' => Turn::Right,
+            'U' => Turn::UTurn,
+            'L' => Turn::Left,
+            _ => bail!("unknown turn {letter}, expected L, R, N or U"),
+        });
+    }
+
+    fn letter(self) -> char {
+        match self {
+            Turn::None => 'N',
+            Turn::Right => 'R',
+            Turn::UTurn => 'U',
+            Turn::Left => 'L',
+        }
+    }
+
+    /// Golly's numbering of turns in turmite rulestrings.
+    fn from_code(code: u32) -> Result<Self> {
+        return Ok(match code {
+            1 => Turn::None,
+            2 => Turn::Right,
+            4 => Turn::UTurn,
+            8 => Turn::Left,
+            _ => bail!("unknown turn {code}, expected 1, 2, 4 or 8"),
+        });
+    }
+
+    fn code(self) -> u32 {
+        match self {
+            Turn::None => 1,
+            Turn::Right => 2,
+            Turn::UTurn => 4,
+            Turn::Left => 8,
+        }
+    }
+}
+
+/// What a turmite does on seeing a colour while in a state.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transition {
+    /// Colour written to the cell before moving.
+    pub colour: u32,
+    pub turn: Turn,
+    /// State the turmite moves on in.
+    pub state: u32,
+}
+
+/// A 2D turmite, Langton's ant and its multi-colour generalisations being
+/// the turmites with a single state.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TurmiteRule {
+    pub states: u32,
+    pub colours: u32,
+    /// Indexed by `state * colours + colour`.
+    pub transitions: Vec<Transition>,
+}
+
+impl TurmiteRule {
+    /// Parses ants such as `RL` or `LLRR`, where each letter is the turn made
+    /// on a colour, and Golly's turmite notation such as
+    /// `{{{1, 2, 0}, {0, 8, 0}}}`, where each state lists a
+    /// `{colour, turn, state}` triple for every colour.
+    pub fn parse(rule: &str) -> Result<Self> {
+        let rule = rule.trim();
+        if rule.starts_with('{') {
+            return Self::parse_table(rule);
+        }
+
+        let turns = rule
+            .chars()
+            .map(Turn::from_letter)
+            .collect::<Result<Vec<_>>>()?;
+        if turns.len() < 2 {
+            bail!("an ant needs at least two colours, got {rule}");
+        }
+        let colours = turns.len() as u32;
+        let transitions = turns
+            .into_iter()
+            .enumerate()
+            .map(|(colour, turn)| Transition {
+                colour: (colour as u32 + 1) % colours,
+                turn,
+                state: 0,
+            })
+            .collect();
+
+        return Ok(TurmiteRule {
+            states: 1,
+            colours,
+            transitions,
+        });
+    }
+
+    fn parse_table(rule: &str) -> Result<Self> {
+        // Triples sit three braces deep, closing a second level brace ends a
+        // state.
+        let mut depth = 0;
+        let mut states: Vec<Vec<Transition>> = Vec::new();
+        let mut triple = Vec::new();
+        let mut number = String::new();
+        for c in rule.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            if !number.is_empty() {
+                triple.push(number.parse::<u32>()?);
+                number.clear();
+            }
+            match c {
+                '{' => {
+                    depth += 1;
+                    if depth == 2 {
+                        states.push(Vec::new());
+                    }
+                    if depth > 3 {
+                        bail!("too many nested braces in {rule}");
+                    }
+                }
+                '}' => {
+                    if depth == 3 {
+                        let [colour, turn, state] = triple[..] else {
+                            bail!("expected {{colour, turn, state}}, got {triple:?}");
+                        };
+                        states.last_mut().unwrap().push(Transition {
+                            colour,
+                            turn: Turn::from_code(turn)?,
+                            state,
+                        });
+                        triple.clear();
+                    }
+                    if depth == 0 {
+                        bail!("unbalanced braces in {rule}");
+                    }
+                    depth -= 1;
+                }
+                ',' | ' ' => {}
+                _ => bail!("unexpected {c} in turmite {rule}"),
+            }
+        }
+        if depth != 0 {
+            bail!("unbalanced braces in {rule}");
+        }
+
+        let colours = states.first().map_or(0, |state| state.len()) as u32;
+        if colours < 2 || states.iter().any(|state| state.len() as u32 != colours) {
+            bail!("every state of a turmite needs the same number of colours, at least two");
+        }
+        let count = states.len() as u32;
+        let transitions: Vec<Transition> = states.into_iter().flatten().collect();
+        if let Some(bad) = transitions
+            .iter()
+            .find(|transition| transition.colour >= colours || transition.state >= count)
+        {
+            bail!("transition {bad:?} refers to a colour or state that doesn't exist");
+        }
+
+        return Ok(TurmiteRule {
+            states: count,
+            colours,
+            transitions,
+        });
+    }
+
+    /// Whether the rule can be written as an ant, a single state turmite that
+    /// cycles through the colours.
+    fn is_ant(&self) -> bool {
+        return self.states == 1
+            && self
+                .transitions
+                .iter()
+                .enumerate()
+                .all(|(colour, transition)| {
+                    transition.colour == (colour as u32 + 1) % self.colours
+                });
+    }
+
+    /// Layout of the `Rule` struct in `turmite.wgsl`.
+    pub fn params(&self) -> Vec<u32> {
+        let mut params = vec![self.colours, self.states];
+        for transition in &self.transitions {
+            params.extend([transition.colour, transition.turn as u32, transition.state]);
+        }
+        return params;
+    }
+}
+
+impl fmt::Display for TurmiteRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_ant() {
+            for transition in &self.transitions {
+                write!(f, "{}", transition.turn.letter())?;
+            }
+            return fmt::Result::Ok(());
+        }
+
+        write!(f, "{{")?;
+        for (i, state) in self.transitions.chunks(self.colours as usize).enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{{")?;
+            for (j, transition) in state.iter().enumerate() {
+                if j > 0 {
+                    write!(f, ",")?;
+                }
+                write!(
+                    f,
+                    "{{{},{},{}}}",
+                    transition.colour,
+                    transition.turn.code(),
+                    transition.state
+                )?;
+            }
+            write!(f, "}}")?;
+        }
+        return write!(f, "}}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ants_cycle_through_their_colours() {
+        let ant = TurmiteRule::parse("RL").unwrap();
+        assert_eq!((ant.states, ant.colours), (1, 2));
+        // Quarter turns clockwise, after the colours and states.
+        assert_eq!(ant.params(), [2, 1, 1, 1, 0, 0, 3, 0]);
+        assert_eq!(ant.to_string(), "RL");
+        let table = TurmiteRule::parse("{{{1, 2, 0}, {0, 8, 0}}}").unwrap();
+        assert_eq!(table, ant);
+        assert_eq!(TurmiteRule::parse("LLRRNU").unwrap().to_string(), "LLRRNU");
+    }
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        // The Fibonacci spiral, which has two states.
+        let rule = "{{{1,2,1},{1,8,1}},{{1,2,1},{0,1,0}}}";
+        let turmite = TurmiteRule::parse(rule).unwrap();
+        assert_eq!((turmite.states, turmite.colours), (2, 2));
+        assert_eq!(turmite.transitions[3].turn, Turn::None);
+        assert_eq!(turmite.to_string(), rule);
+    }
+
+    #[test]
+    fn rejects_turmites_that_cant_run() {
+        for rule in [
+            "R",
+            "RX",
+            "{{{1,2,0}}}",
+            "{{{1,3,0},{0,8,0}}}",
+            "{{{2,2,0},{0,8,0}}}",
+            "{{{1,2,1},{0,8,0}}}",
+            "{{{1,2,0},{0,8,0}},{{1,2,0}}}",
+            "{{{1,2,0},{0,8,0}}",
+            "{{{{1,2,0}}}}",
+        ] {
+            assert!(TurmiteRule::parse(rule).is_err(), "{rule}");
+        }
+    }
+}
@@ -0,0 +1,117 @@
+// Turmites, moved by a single workgroup. The board is copied to `t_output`
+// before the pass so only the cells under the ants are written here.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+struct Transition {
+    colour: u32,
+    // Clockwise quarter turns.
+    turn: u32,
+    state: u32,
+}
+
+struct Rule {
+    colours: u32,
+    states: u32,
+    // Indexed by `state * colours + colour`.
+    transitions: array<Transition>,
+}
+
+@group(2) @binding(0)
+var<storage, read> rule: Rule;
+
+struct Step {
+    generation: u32,
+    agents: u32,
+}
+
+@group(2) @binding(1)
+var<uniform> step: Step;
+
+struct Ant {
+    x: u32,
+    y: u32,
+    // 0 is north, going clockwise.
+    heading: u32,
+    state: u32,
+}
+
+@group(3) @binding(0)
+var<storage, read_write> ants: array<Ant>;
+
+fn state(position: vec2<i32>) -> u32 {
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+// Match `MAX_ANTS` in turmite.rs.
+const MAX_ANTS: u32 = 1024u;
+const WORKGROUP_SIZE: u32 = 256u;
+
+// Where every ant stood and the state it was in before this step, as
+// `x | y << 16` and the state.
+var<workgroup> cells: array<u32, MAX_ANTS>;
+var<workgroup> states: array<u32, MAX_ANTS>;
+
+// A single workgroup moves every ant, so ants sharing a cell can take their
+// turns in order as if they moved one after another: each sees the colour the
+// ants before it left, and only the last writes the cell.
+@compute
+@workgroup_size(256, 1, 1)
+fn cm_main(
+    @builtin(local_invocation_index) local_index: u32
+) {
+    let agents = min(step.agents, MAX_ANTS);
+    for (var index = local_index; index < agents; index += WORKGROUP_SIZE) {
+        cells[index] = ants[index].x | (ants[index].y << 16u);
+        states[index] = ants[index].state;
+    }
+    workgroupBarrier();
+
+    for (var index = local_index; index < agents; index += WORKGROUP_SIZE) {
+        move_ant(index, agents);
+    }
+}
+
+fn move_ant(index: u32, agents: u32) {
+    var ant = ants[index];
+    let position = vec2<i32>(i32(ant.x), i32(ant.y));
+    var colour = state(position) % rule.colours;
+    for (var other = 0u; other < index; other++) {
+        if cells[other] == cells[index] {
+            colour = rule.transitions[states[other] * rule.colours + colour].colour;
+        }
+    }
+    let transition = rule.transitions[ant.state * rule.colours + colour];
+    var last = true;
+    for (var other = index + 1u; other < agents; other++) {
+        last = last && cells[other] != cells[index];
+    }
+    if last {
+        textureStore(t_output, position, vec4<f32>(f32(transition.colour) / 255.0, 0.0, 0.0, 1.0));
+    }
+
+    ant.heading = (ant.heading + transition.turn) % 4u;
+    ant.state = transition.state;
+
+    // The board wraps around so ants can't walk off it.
+    let size = textureDimensions(t_diffuse);
+    switch ant.heading {
+        case 0u: {
+            ant.y = (ant.y + size.y - 1u) % size.y;
+        }
+        case 1u: {
+            ant.x = (ant.x + 1u) % size.x;
+        }
+        case 2u: {
+            ant.y = (ant.y + 1u) % size.y;
+        }
+        default: {
+            ant.x = (ant.x + size.x - 1u) % size.x;
+        }
+    }
+    ants[index] = ant;
+}
@@ -0,0 +1,83 @@
+// Stochastic and asynchronous updates, run after the rule on the board. Each
+// cell takes the state the rule gave it only by chance, otherwise keeping the
+// state it had before the generation. The chances are decided by a counter
+// based random number generator, hashing the cell, generation and seed, so a
+// run can be repeated from its seed.
+
+// The board before the rule ran.
+@group(0) @binding(0)
+var t_previous: texture_2d<f32>;
+
+// The board the rule gave.
+@group(0) @binding(1)
+var t_next: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+struct Update {
+    // Chance a birth the rule makes happens.
+    birth: f32,
+    // Chance a cell the rule keeps in its state stays in it.
+    survival: f32,
+    // Chance a cell is updated at all.
+    fraction: f32,
+    generation: u32,
+    seed: vec2<u32>,
+}
+
+@group(0) @binding(3)
+var<uniform> update: Update;
+
+// Jarzynski and Olano's pcg4d, four random numbers from four counters.
+fn pcg4d(counters: vec4<u32>) -> vec4<u32> {
+    var v = counters * 1664525u + 1013904223u;
+    v.x += v.y * v.w;
+    v.y += v.z * v.x;
+    v.z += v.x * v.y;
+    v.w += v.y * v.z;
+    v ^= v >> vec4<u32>(16u);
+    v.x += v.y * v.w;
+    v.y += v.z * v.x;
+    v.z += v.x * v.y;
+    v.w += v.y * v.z;
+    return v;
+}
+
+// A number in [0, 1) from the top 24 bits of `bits`.
+fn unit(bits: u32) -> f32 {
+    return f32(bits >> 8u) / 16777216.0;
+}
+
+fn state(texture: texture_2d<f32>, position: vec2<i32>) -> u32 {
+    return u32(round(textureLoad(texture, position, 0).x * 255.0));
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_update(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let size = textureDimensions(t_next);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+    let position = vec2<i32>(global_invocation_id.xy);
+    let previous = state(t_previous, position);
+    let next = state(t_next, position);
+
+    let random = pcg4d(vec4<u32>(
+        global_invocation_id.xy,
+        update.generation ^ update.seed.y,
+        update.seed.x,
+    ));
+
+    var state = next;
+    if unit(random.x) >= update.fraction {
+        state = previous;
+    } else if previous == 0u && next != 0u && unit(random.y) >= update.birth {
+        state = previous;
+    } else if previous != 0u && next == previous && unit(random.y) >= update.survival {
+        state = 0u;
+    }
+
+    textureStore(t_output, position, vec4<f32>(f32(state) / 255.0, 0.0, 0.0, 1.0));
+}
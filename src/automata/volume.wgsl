@@ -0,0 +1,113 @@
+// Draws the volume by marching a ray through its voxels for every pixel. The
+// volume fills the cube from -1 to 1, viewed by the orbit camera.
+
+@group(0) @binding(0)
+var t_volume: texture_3d<f32>;
+
+@group(0) @binding(3)
+var<storage, read> palette: array<vec4<f32>>;
+
+struct OrbitCamera {
+    eye: vec4<f32>,
+    // The w components hold the tangent of half the vertical field of view
+    // and the aspect ratio of the window.
+    forward: vec4<f32>,
+    right: vec4<f32>,
+    up: vec4<f32>,
+}
+
+@group(1) @binding(0)
+var<uniform> camera: OrbitCamera;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+// A single triangle covering the whole window.
+@vertex
+fn vs_volume(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let ndc = vec2<f32>(f32(vertex_index == 1u) * 4.0 - 1.0, f32(vertex_index == 2u) * 4.0 - 1.0);
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 0.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+fn state(position: vec3<i32>) -> u32 {
+    return u32(round(textureLoad(t_volume, position, 0).x * 255.0));
+}
+
+@fragment
+fn fs_volume(in: VertexOutput) -> @location(0) vec4<f32> {
+    let tan_half_fov = camera.forward.w;
+    let aspect = camera.right.w;
+    let direction = normalize(
+        camera.forward.xyz
+        + camera.right.xyz * in.ndc.x * tan_half_fov * aspect
+        + camera.up.xyz * in.ndc.y * tan_half_fov
+    );
+
+    // March in voxel coordinates, where the volume runs from 0 to `size`.
+    let size = vec3<f32>(textureDimensions(t_volume));
+    let origin = (camera.eye.xyz + 1.0) * 0.5 * size;
+    let ray = direction * 0.5 * size;
+    // Avoid dividing by zero for rays parallel to a face.
+    let safe = select(ray, vec3<f32>(1e-6), abs(ray) < vec3<f32>(1e-6));
+    let inverse = 1.0 / safe;
+
+    let t0 = (vec3<f32>(0.0) - origin) * inverse;
+    let t1 = (size - origin) * inverse;
+    let near = min(t0, t1);
+    let far = max(t0, t1);
+    let t_enter = max(max(near.x, near.y), near.z);
+    let t_exit = min(min(far.x, far.y), far.z);
+    let background = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    if t_exit < max(t_enter, 0.0) {
+        return background;
+    }
+
+    let start = origin + safe * max(t_enter, 0.0);
+    var cell = clamp(vec3<i32>(floor(start)), vec3<i32>(0), vec3<i32>(size) - 1);
+    let step = vec3<i32>(sign(safe));
+    let delta = abs(inverse);
+    var next = (vec3<f32>(cell) + select(vec3<f32>(0.0), vec3<f32>(1.0), step > vec3<i32>(0)) - start) * inverse;
+
+    // The axis of the face the ray last crossed, starting with the face of
+    // the volume it entered through.
+    var axis = 0;
+    if near.y >= near.x && near.y >= near.z {
+        axis = 1;
+    } else if near.z >= near.x && near.z >= near.y {
+        axis = 2;
+    }
+
+    let limit = i32(size.x + size.y + size.z);
+    for (var i = 0; i < limit; i++) {
+        let current = state(cell);
+        if current != 0u {
+            var normal = vec3<f32>(0.0);
+            normal[axis] = -f32(step[axis]);
+            let light = normalize(vec3<f32>(0.4, 0.8, 0.5));
+            let shade = 0.35 + 0.65 * max(dot(normal, light), 0.0);
+            let colour = palette[current];
+            return vec4<f32>(colour.rgb * shade, colour.a);
+        }
+
+        if next.x < next.y && next.x < next.z {
+            axis = 0;
+        } else if next.y < next.z {
+            axis = 1;
+        } else {
+            axis = 2;
+        }
+        cell[axis] += step[axis];
+        next[axis] += delta[axis];
+        if cell[axis] < 0 || cell[axis] >= i32(size[axis]) {
+            break;
+        }
+    }
+
+    // Tint the empty parts of the volume so its extent is visible.
+    return vec4<f32>(0.05, 0.05, 0.07, 1.0);
+}
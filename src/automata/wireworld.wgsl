@@ -0,0 +1,57 @@
+// WireWorld, using Golly's numbering of the states: 0 is empty, 1 an
+// electron head, 2 an electron tail and 3 a conductor.
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+
+@group(0) @binding(2)
+var t_output: texture_storage_2d<rgba8unorm, write>;
+
+const EMPTY: u32 = 0u;
+const HEAD: u32 = 1u;
+const TAIL: u32 = 2u;
+const CONDUCTOR: u32 = 3u;
+
+fn state(position: vec2<i32>) -> u32 {
+    let size = vec2<i32>(textureDimensions(t_diffuse));
+    if any(position < vec2<i32>(0)) || any(position >= size) {
+        return EMPTY;
+    }
+    return u32(round(textureLoad(t_diffuse, position, 0).x * 255.0));
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn cm_main(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>
+) {
+    let size = textureDimensions(t_diffuse);
+    if global_invocation_id.x >= size.x || global_invocation_id.y >= size.y {
+        return;
+    }
+    let position = vec2<i32>(global_invocation_id.xy);
+
+    var next = EMPTY;
+    switch state(position) {
+        case HEAD: {
+            next = TAIL;
+        }
+        case TAIL: {
+            next = CONDUCTOR;
+        }
+        case CONDUCTOR: {
+            var heads = 0;
+            for (var i: i32 = -1; i <= 1; i++) {
+                for (var j: i32 = -1; j <= 1; j++) {
+                    if state(position + vec2<i32>(i, j)) == HEAD {
+                        heads += 1;
+                    }
+                }
+            }
+            next = select(CONDUCTOR, HEAD, heads == 1 || heads == 2);
+        }
+        default: {}
+    }
+
+    textureStore(t_output, position, vec4<f32>(f32(next) / 255.0, 0.0, 0.0, 1.0));
+}
@@ -1,4 +1,7 @@
-use winit::keyboard::KeyCode;
+use winit::{
+    dpi::{PhysicalPosition, PhysicalSize},
+    keyboard::KeyCode,
+};
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -8,6 +11,24 @@ pub struct Camera {
     pub y: f32
 }
 
+impl Camera {
+    /// Where a point on the window lands on the board, in texture coordinates
+    /// that run from 0 to 1 across the board.
+    pub fn to_board(
+        &self,
+        position: PhysicalPosition<f64>,
+        window: PhysicalSize<u32>,
+    ) -> (f32, f32) {
+        let x = position.x as f32 / window.width as f32 * 2.0 - 1.0;
+        let y = 1.0 - position.y as f32 / window.height as f32 * 2.0;
+
+        // Undo `vs_main`, the board is drawn on a quad from -0.5 to 0.5.
+        let x = x / self.scale - self.x;
+        let y = y / self.scale - self.y;
+        return (x + 0.5, 0.5 - y);
+    }
+}
+
 pub struct CameraController {
     speed: f32,
     is_forward_pressed: bool,
@@ -82,3 +103,78 @@ impl CameraController {
         }
     }
 }
+
+/// A camera circling the centre of the volume for 3D rules, always looking
+/// at the origin.
+#[derive(Copy, Clone, Debug)]
+pub struct OrbitCamera {
+    /// Angle around the vertical axis, in radians.
+    pub yaw: f32,
+    /// Angle above the horizontal, in radians.
+    pub pitch: f32,
+    pub distance: f32,
+}
+
+/// Layout of `OrbitCamera` in `volume.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OrbitUniform {
+    pub eye: [f32; 4],
+    pub forward: [f32; 4],
+    pub right: [f32; 4],
+    pub up: [f32; 4],
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        return OrbitCamera {
+            yaw: 0.6,
+            pitch: 0.4,
+            distance: 4.0,
+        };
+    }
+}
+
+impl OrbitCamera {
+    /// Turns the camera by a mouse drag of `(dx, dy)` pixels.
+    pub fn rotate(&mut self, dx: f32, dy: f32) {
+        self.yaw -= dx * 0.01;
+        self.pitch = (self.pitch + dy * 0.01).clamp(-1.5, 1.5);
+    }
+
+    pub fn zoom(&mut self, amount: f32) {
+        self.distance = (self.distance - amount).clamp(1.5, 20.0);
+    }
+
+    pub fn uniform(&self, aspect: f32) -> OrbitUniform {
+        let eye = [
+            self.distance * self.pitch.cos() * self.yaw.sin(),
+            self.distance * self.pitch.sin(),
+            self.distance * self.pitch.cos() * self.yaw.cos(),
+        ];
+        let forward = normalize([-eye[0], -eye[1], -eye[2]]);
+        let right = normalize(cross(forward, [0.0, 1.0, 0.0]));
+        let up = cross(right, forward);
+        let tan_half_fov = (30.0f32).to_radians().tan();
+
+        return OrbitUniform {
+            eye: [eye[0], eye[1], eye[2], 1.0],
+            forward: [forward[0], forward[1], forward[2], tan_half_fov],
+            right: [right[0], right[1], right[2], aspect],
+            up: [up[0], up[1], up[2], 0.0],
+        };
+    }
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    return [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ];
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    return [v[0] / length, v[1] / length, v[2] / length];
+}
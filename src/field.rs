@@ -0,0 +1,404 @@
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BlendState, ColorTargetState, ColorWrites, CommandEncoder, ComputePassDescriptor,
+    ComputePipelineDescriptor, Device, Extent3d, FragmentState, MultisampleState, Origin3d,
+    PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPass, RenderPipelineDescriptor,
+    ShaderStages, TextureUsages, TextureViewDimension, VertexState,
+};
+
+use crate::{automata::Rule, random::Random, vertex::Vertex, world::World};
+
+/// Format of the field textures. Rules that keep a second quantity alongside
+/// the one that is drawn get a second texture for it, since two channel float
+/// textures can't be written from shaders on downlevel adapters.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Float;
+
+/// The board for continuous rules, cells holding floats rather than states.
+/// It is stepped like the board and drawn on the same quad with the same
+/// camera.
+pub struct Field {
+    pub size: Extent3d,
+    /// Read by the compute and render passes, the drawn quantity and the
+    /// second one.
+    textures: [wgpu::Texture; 2],
+    /// Written by the compute pass, then copied back to `textures`.
+    outputs: [wgpu::Texture; 2],
+    bind_group: wgpu::BindGroup,
+    compute_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    workgroup_size: (u32, u32),
+}
+
+impl Field {
+    /// A field of `width` by `height` cells running `rule`, starting from
+    /// `values`. The board's camera is bound at `@group(1)` and the rule's
+    /// parameters at `@group(2)`, like the board.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        format: wgpu::TextureFormat,
+        palette_buffer: &wgpu::Buffer,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        rule_bind_group_layout: &wgpu::BindGroupLayout,
+        rule: &Rule,
+        width: u32,
+        height: u32,
+        values: &[[f32; 2]],
+    ) -> Field {
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let textures = [
+            create_field_texture(device, size, "Field texture"),
+            create_field_texture(device, size, "Field second texture"),
+        ];
+        let outputs = [
+            create_field_texture(device, size, "Field output texture"),
+            create_field_texture(device, size, "Field second output texture"),
+        ];
+        for (channel, texture) in textures.iter().enumerate() {
+            let channel: Vec<f32> = values.iter().map(|value| value[channel]).collect();
+            queue.write_texture(
+                texture.as_image_copy(),
+                bytemuck::cast_slice(&channel),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * width),
+                    rows_per_image: Some(height),
+                },
+                size,
+            );
+        }
+
+        let texture_entry = |binding, visibility| BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let output_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format: FORMAT,
+                view_dimension: TextureViewDimension::D2,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Field bind group layout"),
+            entries: &[
+                texture_entry(0, ShaderStages::FRAGMENT | ShaderStages::COMPUTE),
+                texture_entry(1, ShaderStages::COMPUTE),
+                output_entry(2),
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                output_entry(4),
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Field bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &textures[0].create_view(&Default::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(
+                        &textures[1].create_view(&Default::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &outputs[0].create_view(&Default::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: palette_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(
+                        &outputs[1].create_view(&Default::default()),
+                    ),
+                },
+            ],
+        });
+
+        let compute_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Field compute pipeline layout"),
+            bind_group_layouts: &[
+                &bind_group_layout,
+                camera_bind_group_layout,
+                rule_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Field automata shader"),
+            source: wgpu::ShaderSource::Wgsl(rule.shader().into()),
+        });
+        let compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Field automata"),
+            layout: Some(&compute_pipeline_layout),
+            module: &compute_shader,
+            entry_point: Some("cm_main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Field render pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout, camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let render_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Field shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("automata/field.wgsl").into()),
+        });
+        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Field pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &render_shader,
+                entry_point: Some("vs_field"),
+                compilation_options: Default::default(),
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(FragmentState {
+                module: &render_shader,
+                entry_point: Some("fs_field"),
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        return Field {
+            size,
+            textures,
+            outputs,
+            bind_group,
+            compute_pipeline,
+            render_pipeline,
+            workgroup_size: rule.workgroup_size(),
+        };
+    }
+
+    /// The cell for `value` under `rule`. Gray-Scott keeps the concentration
+    /// of A alongside B, starting at 1 where there is no B.
+    pub fn cell(rule: &Rule, value: f32) -> [f32; 2] {
+        if let Rule::GrayScott(_) = rule {
+            return [value, 1.0 - value];
+        }
+        return [value, 0.0];
+    }
+
+    /// Random values in a square covering the middle of the field, the rest
+    /// empty. Gray-Scott instead gets scattered squares of B over the whole
+    /// field, so every part of a parameter map has something to grow from.
+    pub fn soup(rule: &Rule, width: u32, height: u32, seed: u64) -> Vec<[f32; 2]> {
+        let mut random = Random::new(seed);
+        let mut values = vec![Field::cell(rule, 0.0); (width * height) as usize];
+        if let Rule::GrayScott(_) = rule {
+            for y in (0..height).step_by(16) {
+                for x in (0..width).step_by(16) {
+                    if random.next_f32() >= 0.25 {
+                        continue;
+                    }
+                    for cell_y in y..(y + 6).min(height) {
+                        for cell_x in x..(x + 6).min(width) {
+                            values[(cell_y * width + cell_x) as usize] = Field::cell(rule, 1.0);
+                        }
+                    }
+                }
+            }
+            return values;
+        }
+
+        for y in height / 4..3 * height / 4 {
+            for x in width / 4..3 * width / 4 {
+                values[(y * width + x) as usize] = Field::cell(rule, random.next_f32());
+            }
+        }
+        return values;
+    }
+
+    /// Live cells of `world` set to 1, everything else 0.
+    pub fn from_world(rule: &Rule, world: &World) -> Vec<[f32; 2]> {
+        return world
+            .cells
+            .iter()
+            .map(|&state| Field::cell(rule, f32::from(state.min(1))))
+            .collect();
+    }
+
+    /// Records `substeps` generations of the rule into `encoder`.
+    pub fn step(
+        &self,
+        encoder: &mut CommandEncoder,
+        camera_bind_group: &wgpu::BindGroup,
+        rule_bind_group: &wgpu::BindGroup,
+        substeps: u32,
+    ) {
+        for _ in 0..substeps {
+            self.substep(encoder, camera_bind_group, rule_bind_group);
+        }
+    }
+
+    fn substep(
+        &self,
+        encoder: &mut CommandEncoder,
+        camera_bind_group: &wgpu::BindGroup,
+        rule_bind_group: &wgpu::BindGroup,
+    ) {
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Field compute pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            compute_pass.set_bind_group(1, camera_bind_group, &[]);
+            compute_pass.set_bind_group(2, rule_bind_group, &[]);
+            compute_pass.set_pipeline(&self.compute_pipeline);
+
+            let (workgroup_width, workgroup_height) = self.workgroup_size;
+            compute_pass.dispatch_workgroups(
+                self.size.width.div_ceil(workgroup_width),
+                self.size.height.div_ceil(workgroup_height),
+                1,
+            );
+        }
+
+        for (output, texture) in self.outputs.iter().zip(&self.textures) {
+            encoder.copy_texture_to_texture(
+                output.as_image_copy(),
+                texture.as_image_copy(),
+                self.size,
+            );
+        }
+    }
+
+    /// Sets the cell at `(x, y)` to `value`.
+    pub fn paint(&self, queue: &Queue, x: u32, y: u32, value: [f32; 2]) {
+        for (texture, value) in self.textures.iter().zip(value) {
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture,
+                    mip_level: 0,
+                    origin: Origin3d { x, y, z: 0 },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                bytemuck::bytes_of(&value),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4),
+                    rows_per_image: Some(1),
+                },
+                Extent3d {
+                    width: 1,
+                    height: 1,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    /// Draws the field on the board quad, with the board's camera bind group
+    /// and vertex and index buffers already set on `render_pass`.
+    pub fn render(&self, render_pass: &mut RenderPass, indices: u32) {
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.draw_indexed(0..indices, 0, 0..1);
+    }
+}
+
+fn create_field_texture(device: &Device, size: Extent3d, label: &str) -> wgpu::Texture {
+    return device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: TextureUsages::TEXTURE_BINDING
+            | TextureUsages::STORAGE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soups_fill_the_middle_of_the_field() {
+        let lenia = Rule::parse("lenia").unwrap();
+        let values = Field::soup(&lenia, 16, 8, 1);
+        assert_eq!(values, Field::soup(&lenia, 16, 8, 1));
+        for (i, &[value, second]) in values.iter().enumerate() {
+            let (x, y) = (i % 16, i / 16);
+            let middle = (4..12).contains(&x) && (2..6).contains(&y);
+            assert!(value == 0.0 || middle, "({x}, {y})");
+            assert!((0.0..1.0).contains(&value) && second == 0.0);
+        }
+        assert!(values.iter().any(|&[value, _]| value > 0.0));
+    }
+
+    #[test]
+    fn gray_scott_gets_squares_of_b_over_a() {
+        let gray_scott = Rule::parse("grayscott").unwrap();
+        let values = Field::soup(&gray_scott, 64, 64, 3);
+        assert!(values
+            .iter()
+            .all(|&value| value == [0.0, 1.0] || value == [1.0, 0.0]));
+        let squares = values.iter().filter(|&&[b, _]| b == 1.0).count();
+        assert!(squares > 0 && squares % 36 == 0);
+    }
+
+    #[test]
+    fn keeps_live_cells_of_worlds() {
+        let mut world = World::new(3, 2);
+        world.set(1, 0, 1);
+        world.set(2, 1, 3);
+        let lenia = Rule::parse("lenia").unwrap();
+        let values: Vec<f32> = Field::from_world(&lenia, &world)
+            .iter()
+            .map(|&[value, _]| value)
+            .collect();
+        assert_eq!(values, [0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+    }
+}
@@ -0,0 +1,430 @@
+use std::sync::mpsc;
+
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BlendState, BufferUsages, ColorTargetState, ColorWrites, CommandEncoder, ComputePassDescriptor,
+    ComputePipelineDescriptor, Device, Extent3d, FragmentState, MultisampleState,
+    PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPass, RenderPipelineDescriptor,
+    ShaderStages, TextureUsages, TextureViewDimension, VertexState,
+};
+
+use crate::{automata::Rule, vertex::Vertex, world::World};
+
+/// Format of the grain textures, a count of grains per cell.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+/// Grains every cell starts with when building the identity.
+const IDENTITY_GRAINS: u32 = 6;
+
+/// The board for sandpiles, cells holding grain counts rather than states.
+/// It is stepped like the board and drawn on the same quad with the same
+/// camera.
+pub struct Grains {
+    pub size: Extent3d,
+    /// Read by the compute and render passes.
+    texture: wgpu::Texture,
+    /// Written by the compute pass, then copied back to `texture`.
+    output: wgpu::Texture,
+    /// Grains waiting to be dropped on each cell.
+    drops: wgpu::Buffer,
+    /// Grains dropped since the last step, added up on the CPU so several
+    /// drops on a cell all land.
+    pending: Vec<u32>,
+    dropped: bool,
+    /// Cells that changed in the last generation.
+    changed: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    /// The step whose changed cells are being read back, and where the
+    /// mapping reports back once it is done.
+    reading: Option<(u64, mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>)>,
+    /// Steps recorded so far, and the step the last drops landed on.
+    steps: u64,
+    landed: u64,
+    bind_group: wgpu::BindGroup,
+    compute_pipeline: wgpu::ComputePipeline,
+    complement_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    workgroup_size: (u32, u32),
+    /// Whether the pile is the first half of building the identity, and is
+    /// to be complemented once it is stable. Set for rules that build it.
+    pub building_identity: bool,
+}
+
+impl Grains {
+    /// A pile of `width` by `height` cells running `rule`, starting from
+    /// `grains`. The board's camera is bound at `@group(1)` and the rule's
+    /// parameters at `@group(2)`, like the board.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        format: wgpu::TextureFormat,
+        palette_buffer: &wgpu::Buffer,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        rule_bind_group_layout: &wgpu::BindGroupLayout,
+        rule: &Rule,
+        width: u32,
+        height: u32,
+        grains: &[u32],
+    ) -> Grains {
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = create_grains_texture(device, size, "Grains texture");
+        let output = create_grains_texture(device, size, "Grains output texture");
+        queue.write_texture(
+            texture.as_image_copy(),
+            bytemuck::cast_slice(grains),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            size,
+        );
+
+        let drops = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Drops buffer"),
+            contents: bytemuck::cast_slice(&vec![0u32; (width * height) as usize]),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+        let changed = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Changed buffer"),
+            size: 4,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Changed readback buffer"),
+            size: 4,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let storage_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Grains bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT | ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Uint,
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: FORMAT,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(4),
+                storage_entry(5),
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Grains bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &texture.create_view(&Default::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &output.create_view(&Default::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: palette_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: drops.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: changed.as_entire_binding(),
+                },
+            ],
+        });
+
+        let compute_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Grains compute pipeline layout"),
+            bind_group_layouts: &[
+                &bind_group_layout,
+                camera_bind_group_layout,
+                rule_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grains automata shader"),
+            source: wgpu::ShaderSource::Wgsl(rule.shader().into()),
+        });
+        let create_compute_pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&compute_pipeline_layout),
+                module: &compute_shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let compute_pipeline = create_compute_pipeline("Grains automata", "cm_main");
+        let complement_pipeline = create_compute_pipeline("Grains complement", "cm_complement");
+
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Grains render pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout, camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let render_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Grains shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("automata/grains.wgsl").into()),
+        });
+        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Grains pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &render_shader,
+                entry_point: Some("vs_grains"),
+                compilation_options: Default::default(),
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(FragmentState {
+                module: &render_shader,
+                entry_point: Some("fs_grains"),
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        return Grains {
+            size,
+            texture,
+            output,
+            drops,
+            pending: vec![0; (width * height) as usize],
+            dropped: false,
+            changed,
+            readback,
+            reading: None,
+            steps: 0,
+            landed: 0,
+            bind_group,
+            compute_pipeline,
+            complement_pipeline,
+            render_pipeline,
+            workgroup_size: rule.workgroup_size(),
+            building_identity: matches!(rule, Rule::Sandpile(rule) if rule.identity),
+        };
+    }
+
+    /// An empty pile, or 6 grains everywhere if `rule` builds the identity.
+    pub fn start(rule: &Rule, width: u32, height: u32) -> Vec<u32> {
+        let grains = match rule {
+            Rule::Sandpile(rule) if rule.identity => IDENTITY_GRAINS,
+            _ => 0,
+        };
+        return vec![grains; (width * height) as usize];
+    }
+
+    /// The state of each cell of `world` as its number of grains.
+    pub fn from_world(world: &World) -> Vec<u32> {
+        return world.cells.iter().map(|&state| u32::from(state)).collect();
+    }
+
+    /// Records `substeps` topples into `encoder`, dropping the grains
+    /// waiting to land and counting the cells that change in the last
+    /// topple for [`Grains::changed`].
+    pub fn step(
+        &mut self,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        camera_bind_group: &wgpu::BindGroup,
+        rule_bind_group: &wgpu::BindGroup,
+        substeps: u32,
+    ) {
+        self.steps += 1;
+        if self.dropped {
+            // Earlier steps have taken every drop already on the GPU.
+            queue.write_buffer(&self.drops, 0, bytemuck::cast_slice(&self.pending));
+            self.pending.fill(0);
+            self.dropped = false;
+            self.landed = self.steps;
+        }
+        for substep in 0..substeps {
+            if substep + 1 == substeps {
+                encoder.clear_buffer(&self.changed, 0, None);
+            }
+            self.dispatch(
+                encoder,
+                camera_bind_group,
+                rule_bind_group,
+                &self.compute_pipeline,
+            );
+        }
+
+        // Only one step is read back at a time, the rest aren't counted.
+        if self.reading.is_none() {
+            encoder.copy_buffer_to_buffer(&self.changed, 0, &self.readback, 0, 4);
+            let (sender, receiver) = mpsc::channel();
+            encoder.map_buffer_on_submit(&self.readback, wgpu::MapMode::Read, .., move |result| {
+                let _ = sender.send(result);
+            });
+            self.reading = Some((self.steps, receiver));
+        }
+    }
+
+    /// Records replacing the pile with what it is short of 6 grains
+    /// everywhere into `encoder`.
+    pub fn complement(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        camera_bind_group: &wgpu::BindGroup,
+        rule_bind_group: &wgpu::BindGroup,
+    ) {
+        self.dispatch(
+            encoder,
+            camera_bind_group,
+            rule_bind_group,
+            &self.complement_pipeline,
+        );
+        // Steps read back before the complement say nothing about it.
+        self.landed = self.steps + 1;
+    }
+
+    fn dispatch(
+        &self,
+        encoder: &mut CommandEncoder,
+        camera_bind_group: &wgpu::BindGroup,
+        rule_bind_group: &wgpu::BindGroup,
+        pipeline: &wgpu::ComputePipeline,
+    ) {
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Grains compute pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            compute_pass.set_bind_group(1, camera_bind_group, &[]);
+            compute_pass.set_bind_group(2, rule_bind_group, &[]);
+            compute_pass.set_pipeline(pipeline);
+
+            let (workgroup_width, workgroup_height) = self.workgroup_size;
+            compute_pass.dispatch_workgroups(
+                self.size.width.div_ceil(workgroup_width),
+                self.size.height.div_ceil(workgroup_height),
+                1,
+            );
+        }
+
+        encoder.copy_texture_to_texture(
+            self.output.as_image_copy(),
+            self.texture.as_image_copy(),
+            self.size,
+        );
+    }
+
+    /// Number of cells that changed in the last step read back, without
+    /// waiting for one. `Some(0)` once the pile is stable, counting only
+    /// steps since grains were last dropped.
+    pub fn changed(&mut self) -> anyhow::Result<Option<u32>> {
+        let Some((step, receiver)) = &self.reading else {
+            return Ok(None);
+        };
+        let step = *step;
+        match receiver.try_recv() {
+            Result::Ok(result) => {
+                self.reading = None;
+                result?;
+            }
+            Err(mpsc::TryRecvError::Empty) => return Ok(None),
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.reading = None;
+                anyhow::bail!("Changed cells were never read back");
+            }
+        }
+
+        let changed =
+            bytemuck::cast_slice::<u8, u32>(&self.readback.slice(..).get_mapped_range())[0];
+        self.readback.unmap();
+        return Ok((step >= self.landed).then_some(changed));
+    }
+
+    /// Drops `grains` onto the cell at `(x, y)`, landing on the next step.
+    pub fn drop(&mut self, x: u32, y: u32, grains: u32) {
+        let index = (y * self.size.width + x) as usize;
+        self.pending[index] = self.pending[index].saturating_add(grains);
+        self.dropped = true;
+    }
+
+    /// Draws the pile on the board quad, with the board's camera bind group
+    /// and vertex and index buffers already set on `render_pass`.
+    pub fn render(&self, render_pass: &mut RenderPass, indices: u32) {
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.draw_indexed(0..indices, 0, 0..1);
+    }
+}
+
+fn create_grains_texture(device: &Device, size: Extent3d, label: &str) -> wgpu::Texture {
+    return device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage: TextureUsages::TEXTURE_BINDING
+            | TextureUsages::STORAGE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+}
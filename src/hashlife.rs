@@ -0,0 +1,516 @@
+use std::{collections::HashMap, ops::Range};
+
+use crate::world::World;
+
+/// Nodes kept before the memoised results are thrown away and the tree is
+/// rebuilt from the root.
+const MAX_NODES: usize = 1 << 22;
+
+/// The level of the largest root, reaching 2^62 cells either side of the
+/// origin so its cells have `i64` coordinates. Cells that get further out
+/// than a quarter of that are dropped rather than growing it again.
+const MAX_LEVEL: u32 = 63;
+
+/// Steps run up to 2^MAX_STEP generations, as a step needs a root at least
+/// three levels above it.
+pub const MAX_STEP: u32 = MAX_LEVEL - 3;
+
+/// The two leaves every tree is built from.
+const DEAD: u32 = 0;
+const ALIVE: u32 = 1;
+
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    /// The north west, north east, south west and south east quadrants.
+    children: [u32; 4],
+    /// The node is 2^level cells along each side.
+    level: u32,
+    population: u128,
+}
+
+/// Gosper's HashLife, for two state rules on the Moore neighbourhood. The
+/// universe is a quadtree where equal subtrees are the same node, and the
+/// centre of each node some power of two generations on is memoised, so
+/// regular patterns can be run for astronomically many generations.
+///
+/// Cells have the same coordinates as on the board they were taken from,
+/// with the universe stretching out from it in every direction.
+pub struct HashLife {
+    /// The next state of the centre cell for each 3x3 neighbourhood, as in
+    /// `MapRule`.
+    table: Vec<bool>,
+    nodes: Vec<Node>,
+    /// Each node by its quadrants, so equal subtrees are shared.
+    ids: HashMap<[u32; 4], u32>,
+    /// The centre of a node after 2^j generations, by the node and j.
+    results: HashMap<(u32, u32), u32>,
+    /// The empty node of each level.
+    empty: Vec<u32>,
+    /// Centred on the origin, covering 2^(level - 1) cells either side of it.
+    root: u32,
+    pub generation: u64,
+    /// Each step runs 2^step generations.
+    pub step: u32,
+}
+
+impl HashLife {
+    /// A universe holding the live cells of `world`, to be run with the rule
+    /// given by `table`. Rules where empty space comes alive can't be run.
+    pub fn new(table: Vec<bool>, world: &World) -> Self {
+        assert!(!table[0], "empty space must stay empty");
+        let leaf = |population| Node {
+            children: [DEAD; 4],
+            level: 0,
+            population,
+        };
+        let mut hashlife = HashLife {
+            table,
+            nodes: vec![leaf(0), leaf(1)],
+            ids: HashMap::new(),
+            results: HashMap::new(),
+            empty: vec![DEAD],
+            root: DEAD,
+            generation: 0,
+            step: 0,
+        };
+
+        // The smallest root with the board in its south east quadrant.
+        let mut level = 3;
+        while 1 << (level - 1) < world.width.max(world.height) {
+            level += 1;
+        }
+        let corner = -half(level);
+        hashlife.root = hashlife.build(world, corner, corner, level);
+        return hashlife;
+    }
+
+    pub fn population(&self) -> u128 {
+        return self.nodes[self.root as usize].population;
+    }
+
+    /// Runs the universe on by 2^step generations.
+    pub fn run(&mut self) {
+        // Patterns grow by at most a cell a generation, so they have to start
+        // in the middle of the root to stay inside the centre it steps to.
+        while self.level() < self.step + 3 || !self.is_centred() {
+            if self.level() == MAX_LEVEL {
+                self.crop();
+            } else {
+                self.expand();
+            }
+        }
+        if self.nodes.len() > MAX_NODES {
+            self.collect();
+        }
+        self.root = self.successor(self.root, self.step);
+        self.generation = self.generation.wrapping_add(1 << self.step);
+    }
+
+    /// Sets the cell at `(x, y)`, growing the universe to reach it. Cells
+    /// past the edges of the largest universe are left out.
+    pub fn set(&mut self, x: i64, y: i64, alive: bool) {
+        loop {
+            let half = half(self.level());
+            if (-half..half).contains(&x) && (-half..half).contains(&y) {
+                self.root = self.set_in(self.root, x + half, y + half, alive);
+                return;
+            }
+            if self.level() == MAX_LEVEL {
+                return;
+            }
+            self.expand();
+        }
+    }
+
+    /// Draws the cells from `(x, y)` onto a `width` by `height` board, where
+    /// each cell of the board covers `scale` cells of the universe across and
+    /// down. Cells covering more than one are alive if any of those are.
+    pub fn rasterize(&self, x: f64, y: f64, scale: (f64, f64), width: u32, height: u32) -> World {
+        let mut world = World::new(width, height);
+        let half = half(self.level()) as f64;
+        let (corner, size) = (-half, 2.0 * half);
+        self.draw(
+            &mut world,
+            self.root,
+            ((corner - x) / scale.0, (corner - y) / scale.1),
+            (size / scale.0, size / scale.1),
+        );
+        return world;
+    }
+
+    fn draw(&self, world: &mut World, node: u32, corner: (f64, f64), size: (f64, f64)) {
+        let Node {
+            children,
+            level,
+            population,
+        } = self.nodes[node as usize];
+        if population == 0
+            || corner.0 >= world.width as f64
+            || corner.1 >= world.height as f64
+            || corner.0 + size.0 <= 0.0
+            || corner.1 + size.1 <= 0.0
+        {
+            return;
+        }
+
+        if level == 0 || (size.0 <= 1.0 && size.1 <= 1.0) {
+            for y in covered(corner.1, size.1, world.height) {
+                for x in covered(corner.0, size.0, world.width) {
+                    world.set(x, y, 1);
+                }
+            }
+            return;
+        }
+
+        let half = (size.0 / 2.0, size.1 / 2.0);
+        for (quadrant, child) in children.into_iter().enumerate() {
+            let x = corner.0 + half.0 * (quadrant % 2) as f64;
+            let y = corner.1 + half.1 * (quadrant / 2) as f64;
+            self.draw(world, child, (x, y), half);
+        }
+    }
+
+    fn level(&self) -> u32 {
+        return self.nodes[self.root as usize].level;
+    }
+
+    fn children(&self, node: u32) -> [u32; 4] {
+        return self.nodes[node as usize].children;
+    }
+
+    /// The node with `children` as its quadrants, shared with any other node
+    /// that has them.
+    fn join(&mut self, children: [u32; 4]) -> u32 {
+        if let Some(&id) = self.ids.get(&children) {
+            return id;
+        }
+        let level = self.nodes[children[0] as usize].level + 1;
+        let population = children
+            .iter()
+            .map(|&child| self.nodes[child as usize].population)
+            .sum();
+        let id = self.nodes.len() as u32;
+        self.nodes.push(Node {
+            children,
+            level,
+            population,
+        });
+        self.ids.insert(children, id);
+        return id;
+    }
+
+    fn empty(&mut self, level: u32) -> u32 {
+        while self.empty.len() <= level as usize {
+            let below = self.empty[self.empty.len() - 1];
+            let id = self.join([below; 4]);
+            self.empty.push(id);
+        }
+        return self.empty[level as usize];
+    }
+
+    /// The node of `level` with its north west corner at `(x, y)` on `world`.
+    fn build(&mut self, world: &World, x: i64, y: i64, level: u32) -> u32 {
+        let size = 1 << level;
+        if x >= world.width as i64 || y >= world.height as i64 || x + size <= 0 || y + size <= 0 {
+            return self.empty(level);
+        }
+        if level == 0 {
+            return if world.get(x as u32, y as u32) != 0 {
+                ALIVE
+            } else {
+                DEAD
+            };
+        }
+        let half = size / 2;
+        let children = [
+            self.build(world, x, y, level - 1),
+            self.build(world, x + half, y, level - 1),
+            self.build(world, x, y + half, level - 1),
+            self.build(world, x + half, y + half, level - 1),
+        ];
+        return self.join(children);
+    }
+
+    /// `node` with the cell at `(x, y)` from its north west corner set.
+    fn set_in(&mut self, node: u32, x: i64, y: i64, alive: bool) -> u32 {
+        let level = self.nodes[node as usize].level;
+        if level == 0 {
+            return if alive { ALIVE } else { DEAD };
+        }
+        let half = half(level);
+        let mut children = self.children(node);
+        let quadrant = (x >= half) as usize + 2 * (y >= half) as usize;
+        children[quadrant] = self.set_in(children[quadrant], x % half, y % half, alive);
+        return self.join(children);
+    }
+
+    /// Doubles the size of the root, keeping it centred on the origin.
+    fn expand(&mut self) {
+        let empty = self.empty(self.level() - 1);
+        let [nw, ne, sw, se] = self.children(self.root);
+        let children = [
+            self.join([empty, empty, empty, nw]),
+            self.join([empty, empty, ne, empty]),
+            self.join([empty, sw, empty, empty]),
+            self.join([se, empty, empty, empty]),
+        ];
+        self.root = self.join(children);
+    }
+
+    /// Drops every cell outside the middle quarter of the root, keeping its
+    /// size.
+    fn crop(&mut self) {
+        let middle = self.centre(self.root);
+        self.root = self.centre(middle);
+        self.expand();
+        self.expand();
+    }
+
+    /// Whether every live cell is in the middle quarter of the root.
+    fn is_centred(&self) -> bool {
+        let [nw, ne, sw, se] = self.children(self.root);
+        let middle = [
+            self.children(self.children(nw)[3])[3],
+            self.children(self.children(ne)[2])[2],
+            self.children(self.children(sw)[1])[1],
+            self.children(self.children(se)[0])[0],
+        ];
+        let population: u128 = middle
+            .iter()
+            .map(|&node| self.nodes[node as usize].population)
+            .sum();
+        return population == self.population();
+    }
+
+    /// The middle half of `node`, as it is.
+    fn centre(&mut self, node: u32) -> u32 {
+        let [nw, ne, sw, se] = self.children(node);
+        return self.join([
+            self.children(nw)[3],
+            self.children(ne)[2],
+            self.children(sw)[1],
+            self.children(se)[0],
+        ]);
+    }
+
+    /// The middle half of `node` after 2^j generations, for j up to two less
+    /// than its level.
+    fn successor(&mut self, node: u32, j: u32) -> u32 {
+        if let Some(&result) = self.results.get(&(node, j)) {
+            return result;
+        }
+        let Node {
+            children: [nw, ne, sw, se],
+            level,
+            population,
+        } = self.nodes[node as usize];
+        debug_assert!(j + 2 <= level);
+
+        let result = if population == 0 {
+            self.empty(level - 1)
+        } else if level == 2 {
+            self.generation_of(node)
+        } else {
+            // Nine overlapping nodes of half the size, each run on by up to
+            // half the generations, make up four that are run the rest.
+            let [_, nw_ne, nw_sw, nw_se] = self.children(nw);
+            let [ne_nw, _, ne_sw, ne_se] = self.children(ne);
+            let [sw_nw, sw_ne, _, sw_se] = self.children(sw);
+            let [se_nw, se_ne, se_sw, _] = self.children(se);
+            let nine = [
+                nw,
+                self.join([nw_ne, ne_nw, nw_se, ne_sw]),
+                ne,
+                self.join([nw_sw, nw_se, sw_nw, sw_ne]),
+                self.join([nw_se, ne_sw, sw_ne, se_nw]),
+                self.join([ne_sw, ne_se, se_nw, se_ne]),
+                sw,
+                self.join([sw_ne, se_nw, sw_se, se_sw]),
+                se,
+            ];
+            let full = j + 2 == level;
+            let c = nine.map(|node| {
+                if full {
+                    self.successor(node, j - 1)
+                } else {
+                    self.centre(node)
+                }
+            });
+            let rest = if full { j - 1 } else { j };
+            let quadrants = [
+                self.join([c[0], c[1], c[3], c[4]]),
+                self.join([c[1], c[2], c[4], c[5]]),
+                self.join([c[3], c[4], c[6], c[7]]),
+                self.join([c[4], c[5], c[7], c[8]]),
+            ];
+            let results = quadrants.map(|quadrant| self.successor(quadrant, rest));
+            self.join(results)
+        };
+        self.results.insert((node, j), result);
+        return result;
+    }
+
+    /// The middle 2x2 cells of a 4x4 node after a generation.
+    fn generation_of(&mut self, node: u32) -> u32 {
+        let mut cells = [[false; 4]; 4];
+        for (quadrant, child) in self.children(node).into_iter().enumerate() {
+            for (cell, leaf) in self.children(child).into_iter().enumerate() {
+                let x = (quadrant % 2) * 2 + cell % 2;
+                let y = (quadrant / 2) * 2 + cell / 2;
+                cells[y][x] = leaf == ALIVE;
+            }
+        }
+        let next = [(1, 1), (2, 1), (1, 2), (2, 2)].map(|(x, y)| {
+            // Read row by row, so the north west cell is the most significant
+            // bit.
+            let mut index = 0;
+            for row in &cells[y - 1..=y + 1] {
+                for &alive in &row[x - 1..=x + 1] {
+                    index = index << 1 | alive as usize;
+                }
+            }
+            return if self.table[index] { ALIVE } else { DEAD };
+        });
+        return self.join(next);
+    }
+
+    /// Throws away the memoised results, and every node the root doesn't
+    /// use along with them.
+    fn collect(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        self.nodes = nodes[..2].to_vec();
+        self.ids.clear();
+        self.results.clear();
+        self.empty = vec![DEAD];
+        let mut copied = HashMap::new();
+        self.root = self.copy(&nodes, self.root, &mut copied);
+    }
+
+    fn copy(&mut self, nodes: &[Node], node: u32, copied: &mut HashMap<u32, u32>) -> u32 {
+        if node == DEAD || node == ALIVE {
+            return node;
+        }
+        if let Some(&id) = copied.get(&node) {
+            return id;
+        }
+        let children = nodes[node as usize]
+            .children
+            .map(|child| self.copy(nodes, child, copied));
+        let id = self.join(children);
+        copied.insert(node, id);
+        return id;
+    }
+}
+
+/// How many cells a node of `level` reaches either side of its middle.
+fn half(level: u32) -> i64 {
+    return 2i64
+        .checked_pow(level - 1)
+        .expect("universes stop growing at MAX_LEVEL");
+}
+
+/// The cells of a row or column of `length` whose centres a span from
+/// `start` of `size` covers, or the one holding its centre when it is too
+/// small to cover any.
+fn covered(start: f64, size: f64, length: u32) -> Range<u32> {
+    let first = (start - 0.5).ceil().max(0.0);
+    let end = (start + size - 0.5).ceil().min(length as f64);
+    if first < end {
+        return first as u32..end as u32;
+    }
+    let middle = (start + size / 2.0).floor();
+    if middle < 0.0 || middle >= length as f64 {
+        return 0..0;
+    }
+    return middle as u32..middle as u32 + 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automata::Rule;
+
+    fn glider() -> World {
+        let mut world = World::new(8, 8);
+        for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+            world.set(x, y, 1);
+        }
+        return world;
+    }
+
+    /// `world` after a generation of the rule given by `table`, with the
+    /// cells past its edges dead.
+    fn generation(table: &[bool], world: &World) -> World {
+        let mut next = World::new(world.width, world.height);
+        for y in 0..world.height {
+            for x in 0..world.width {
+                let mut index = 0;
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let (x, y) = (x as i64 + dx, y as i64 + dy);
+                        let inside = (0..world.width as i64).contains(&x)
+                            && (0..world.height as i64).contains(&y);
+                        let alive = inside && world.get(x as u32, y as u32) != 0;
+                        index = index << 1 | alive as usize;
+                    }
+                }
+                next.set(x, y, table[index] as u8);
+            }
+        }
+        return next;
+    }
+
+    #[test]
+    fn runs_like_the_rule() {
+        for rule in ["B3/S23", "B36/S23", "B2-a/S12"] {
+            let table = Rule::parse(rule).unwrap().moore_table().unwrap();
+            let mut world = World::new(64, 64);
+            // An R-pentomino in the middle, which has room to grow for the
+            // generations run.
+            for (x, y) in [(31, 30), (32, 30), (30, 31), (31, 31), (31, 32)] {
+                world.set(x, y, 1);
+            }
+            let mut hashlife = HashLife::new(table.clone(), &world);
+            for step in [0, 0, 1, 2, 3] {
+                hashlife.step = step;
+                hashlife.run();
+                for _ in 0..1 << step {
+                    world = generation(&table, &world);
+                }
+                let population = world.cells.iter().filter(|&&cell| cell != 0).count();
+                assert_eq!(hashlife.population(), population as u128, "{rule}");
+                let drawn = hashlife.rasterize(0.0, 0.0, (1.0, 1.0), 64, 64);
+                assert_eq!(
+                    drawn.cells, world.cells,
+                    "{rule} at {}",
+                    hashlife.generation
+                );
+            }
+            assert_eq!(hashlife.generation, 16);
+        }
+    }
+
+    #[test]
+    fn sets_cells_anywhere() {
+        let mut hashlife = HashLife::new(Rule::Life.moore_table().unwrap(), &glider());
+        hashlife.set(-1 << 40, 1 << 50, true);
+        hashlife.set(1, 0, false);
+        assert_eq!(hashlife.population(), 5);
+        hashlife.set(i64::MAX, 0, true);
+        assert_eq!(hashlife.population(), 5);
+        assert!(hashlife.level() <= MAX_LEVEL);
+    }
+
+    #[test]
+    fn gliders_leaving_the_universe_are_dropped() {
+        let mut hashlife = HashLife::new(Rule::Life.moore_table().unwrap(), &glider());
+        hashlife.step = MAX_STEP;
+        for _ in 0..8 {
+            hashlife.run();
+            assert!(hashlife.level() <= MAX_LEVEL);
+        }
+        assert_eq!(hashlife.population(), 0);
+        let world = hashlife.rasterize(-1e18, -1e18, (1e10, 1e10), 64, 64);
+        assert!(world.cells.iter().all(|&cell| cell == 0));
+    }
+}
@@ -0,0 +1,354 @@
+use std::{fmt, io::Cursor};
+
+use anyhow::*;
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+
+use crate::world::World;
+
+/// Which part of each pixel decides the state of its cell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Channel {
+    /// How bright the pixel looks, weighting green most and blue least.
+    Luminance,
+    Red,
+    Green,
+    Blue,
+}
+
+/// How the brightness of pixels between states is spread over the cells.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dither {
+    /// Each cell takes the state nearest its pixel.
+    None,
+    /// The error each cell makes is pushed onto the cells right of and
+    /// below it.
+    FloydSteinberg,
+    /// Cells are compared against an 8x8 Bayer matrix repeated over the
+    /// board.
+    Ordered,
+}
+
+/// How images become boards. Written like
+/// `channel=luminance,cutoff=0.4,dither=floyd-steinberg,width=512,states=4`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Import {
+    pub channel: Channel,
+    /// Brightness from which cells are alive.
+    pub cutoff: f32,
+    pub dither: Dither,
+    /// Size of the board the image is resized to. When only one side is
+    /// given the other keeps the image's aspect ratio.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Number of states the brightness is quantized to, dead and then
+    /// brighter and brighter, limited to the states of the rule.
+    pub states: u32,
+}
+
+impl Default for Import {
+    fn default() -> Self {
+        return Import {
+            channel: Channel::Red,
+            cutoff: 0.5,
+            dither: Dither::None,
+            width: None,
+            height: None,
+            states: 2,
+        };
+    }
+}
+
+impl Import {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut import = Import::default();
+        for pair in text
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected key=value, got {pair}"))?;
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "channel" => {
+                    import.channel = match value.to_ascii_lowercase().as_str() {
+                        "luminance" => Channel::Luminance,
+                        "red" => Channel::Red,
+                        "green" => Channel::Green,
+                        "blue" => Channel::Blue,
+                        _ => {
+                            bail!("unknown channel {value}, expected luminance, red, green or blue")
+                        }
+                    }
+                }
+                "cutoff" => {
+                    import.cutoff = value
+                        .parse()
+                        .with_context(|| format!("expected a cutoff, got {value:?}"))?;
+                    if !(0.0..=1.0).contains(&import.cutoff) {
+                        bail!("cutoffs are between 0 and 1, got {}", import.cutoff);
+                    }
+                }
+                "dither" => {
+                    import.dither = match value.to_ascii_lowercase().as_str() {
+                        "none" => Dither::None,
+                        "floyd-steinberg" => Dither::FloydSteinberg,
+                        "ordered" => Dither::Ordered,
+                        _ => bail!(
+                            "unknown dither {value}, expected none, floyd-steinberg or ordered"
+                        ),
+                    }
+                }
+                "width" => import.width = Some(parse_side(value)?),
+                "height" => import.height = Some(parse_side(value)?),
+                "states" => {
+                    import.states = value
+                        .parse()
+                        .with_context(|| format!("expected a number of states, got {value:?}"))?;
+                    if !(2..=256).contains(&import.states) {
+                        bail!("images have 2 to 256 states, got {}", import.states);
+                    }
+                }
+                _ => bail!("unknown import setting {key}"),
+            }
+        }
+        return Ok(import);
+    }
+
+    /// Decodes an image and turns it into a board for a rule with `states`
+    /// states.
+    pub fn load(&self, bytes: &[u8], states: u32) -> Result<World> {
+        // Boards can be far larger than the decoder's default memory limit.
+        let mut reader = image::io::Reader::new(Cursor::new(bytes)).with_guessed_format()?;
+        reader.no_limits();
+        let img = reader.decode()?;
+        return self.world(&img, states);
+    }
+
+    pub fn world(&self, img: &DynamicImage, states: u32) -> Result<World> {
+        let (width, height) = self.size(img.dimensions())?;
+        let img = match (width, height) == img.dimensions() {
+            true => img.to_rgba8(),
+            false => img
+                .resize_exact(width, height, FilterType::Triangle)
+                .to_rgba8(),
+        };
+        let mut values: Vec<f32> = img
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b, _] = pixel.0.map(|c| c as f32 / 255.0);
+                return match self.channel {
+                    Channel::Luminance => 0.2126 * r + 0.7152 * g + 0.0722 * b,
+                    Channel::Red => r,
+                    Channel::Green => g,
+                    Channel::Blue => b,
+                };
+            })
+            .collect();
+
+        // Stretch the brightness so the cutoff falls halfway between dead
+        // and the first live state, and the rest of it is spread evenly
+        // over the live states.
+        let levels = (self.states.min(states).max(2) - 1) as f32;
+        let first = 0.5 / levels;
+        for value in values.iter_mut() {
+            *value = match *value < self.cutoff {
+                true => *value / self.cutoff * first,
+                false => {
+                    first
+                        + (*value - self.cutoff) / (1.0 - self.cutoff).max(f32::EPSILON)
+                            * (1.0 - first)
+                }
+            };
+        }
+
+        let quantize = |value: f32, threshold: f32| -> u8 {
+            return (value * levels + threshold).floor().clamp(0.0, levels) as u8;
+        };
+        let mut world = World::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let index = (y * width + x) as usize;
+                let value = values[index];
+                let state = match self.dither {
+                    Dither::None => quantize(value, 0.5),
+                    Dither::Ordered => quantize(value, (bayer(x, y) as f32 + 0.5) / 64.0),
+                    Dither::FloydSteinberg => {
+                        let state = quantize(value, 0.5);
+                        let error = value - state as f32 / levels;
+                        let mut spread = |dx: i32, dy: u32, weight: f32| {
+                            let (x, y) = (x as i32 + dx, y + dy);
+                            if (0..width as i32).contains(&x) && y < height {
+                                values[(y * width + x as u32) as usize] += error * weight;
+                            }
+                        };
+                        spread(1, 0, 7.0 / 16.0);
+                        spread(-1, 1, 3.0 / 16.0);
+                        spread(0, 1, 5.0 / 16.0);
+                        spread(1, 1, 1.0 / 16.0);
+                        state
+                    }
+                };
+                world.set(x, y, state);
+            }
+        }
+        return Ok(world);
+    }
+
+    /// Size of the board an image of `size` becomes.
+    fn size(&self, (width, height): (u32, u32)) -> Result<(u32, u32)> {
+        let scaled = |side: u32, from: u32, to: u32| -> u64 {
+            return ((side as u64 * to as u64 + from as u64 / 2) / from as u64).max(1);
+        };
+        let (width, height) = match (self.width, self.height) {
+            (Some(w), Some(h)) => (w as u64, h as u64),
+            (Some(w), None) => (w as u64, scaled(height, width, w)),
+            (None, Some(h)) => (scaled(width, height, h), h as u64),
+            (None, None) => (width as u64, height as u64),
+        };
+        if width * height > u32::MAX as u64 {
+            bail!("a {width}x{height} board has too many cells");
+        }
+        return Ok((width as u32, height as u32));
+    }
+}
+
+impl fmt::Display for Import {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let channel = match self.channel {
+            Channel::Luminance => "luminance",
+            Channel::Red => "red",
+            Channel::Green => "green",
+            Channel::Blue => "blue",
+        };
+        let dither = match self.dither {
+            Dither::None => "none",
+            Dither::FloydSteinberg => "floyd-steinberg",
+            Dither::Ordered => "ordered",
+        };
+        write!(
+            f,
+            "channel={channel},cutoff={},dither={dither}",
+            self.cutoff
+        )?;
+        if let Some(width) = self.width {
+            write!(f, ",width={width}")?;
+        }
+        if let Some(height) = self.height {
+            write!(f, ",height={height}")?;
+        }
+        return write!(f, ",states={}", self.states);
+    }
+}
+
+/// Entry of the 8x8 Bayer matrix at `(x, y)`, from 0 to 63, interleaving
+/// the bits of `x ^ y` and `y` in reverse.
+fn bayer(x: u32, y: u32) -> u32 {
+    let mut entry = 0;
+    for bit in 0..3 {
+        entry = (entry << 2) | (((x ^ y) >> bit & 1) << 1) | (y >> bit & 1);
+    }
+    return entry;
+}
+
+fn parse_side(value: &str) -> Result<u32> {
+    let side: u32 = value
+        .parse()
+        .with_context(|| format!("expected a number of cells, got {value:?}"))?;
+    if side == 0 {
+        bail!("boards need at least one cell along each side");
+    }
+    return Ok(side);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    /// An image of `width` by `height` grey pixels, each `grey(x, y)`.
+    fn image(width: u32, height: u32, grey: impl Fn(u32, u32) -> u8) -> DynamicImage {
+        let img = RgbaImage::from_fn(width, height, |x, y| {
+            let grey = grey(x, y);
+            return Rgba([grey, grey, grey, 255]);
+        });
+        return DynamicImage::ImageRgba8(img);
+    }
+
+    fn alive(world: &World) -> usize {
+        return world.cells.iter().filter(|&&state| state != 0).count();
+    }
+
+    #[test]
+    fn cells_are_alive_from_the_cutoff() {
+        let greys = [0, 63, 65, 255];
+        let img = image(4, 1, |x, _| greys[x as usize]);
+        let import = Import::parse("cutoff=0.25").unwrap();
+        let world = import.world(&img, 2).unwrap();
+        assert_eq!(world.cells, [0, 0, 1, 1]);
+        let world = Import::default().world(&img, 2).unwrap();
+        assert_eq!(world.cells, [0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn quantizes_to_the_states_of_the_rule() {
+        let img = image(256, 1, |x, _| x as u8);
+        let import = Import::parse("states=4").unwrap();
+        let world = import.world(&img, 8).unwrap();
+        // Brighter pixels never have earlier states, and every state is used.
+        assert!(world.cells.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(world.cells.first(), Some(&0));
+        assert_eq!(world.cells.last(), Some(&3));
+        assert!((0..4).all(|state| world.cells.contains(&state)));
+
+        let world = import.world(&img, 2).unwrap();
+        assert!(world.cells.iter().all(|&state| state < 2));
+        assert!(world.cells.contains(&1));
+    }
+
+    #[test]
+    fn dithering_keeps_the_brightness() {
+        // A quarter of the way to the cutoff, which alone keeps every cell
+        // dead.
+        let img = image(8, 8, |_, _| 64);
+        assert_eq!(alive(&Import::default().world(&img, 2).unwrap()), 0);
+        let ordered = Import::parse("dither=ordered").unwrap();
+        assert_eq!(alive(&ordered.world(&img, 2).unwrap()), 16);
+
+        let img = image(32, 32, |_, _| 64);
+        let floyd_steinberg = Import::parse("dither=floyd-steinberg").unwrap();
+        let live = alive(&floyd_steinberg.world(&img, 2).unwrap());
+        assert!((230..=282).contains(&live), "{live} of 1024 alive");
+    }
+
+    #[test]
+    fn bayer_matrix_holds_each_threshold_once() {
+        let mut entries: Vec<u32> = (0..64).map(|i| bayer(i % 8, i / 8)).collect();
+        entries.sort();
+        assert!(entries.into_iter().eq(0..64));
+        assert_eq!(bayer(8, 3), bayer(0, 3));
+    }
+
+    #[test]
+    fn resizing_keeps_the_aspect_ratio() {
+        let sizes = [
+            ("", (200, 100)),
+            ("width=50", (50, 25)),
+            ("height=10", (20, 10)),
+            ("width=3,height=7", (3, 7)),
+            ("width=1000", (1000, 500)),
+        ];
+        for (text, size) in sizes {
+            let import = Import::parse(text).unwrap();
+            assert_eq!(import.size((200, 100)).unwrap(), size, "{text}");
+        }
+        let img = image(200, 100, |_, _| 255);
+        let world = Import::parse("width=50").unwrap().world(&img, 2).unwrap();
+        assert_eq!((world.width, world.height), (50, 25));
+        assert_eq!(alive(&world), 50 * 25);
+        // Sides round to at least a cell.
+        let thin = Import::parse("width=1").unwrap();
+        assert_eq!(thin.size((1000, 1)).unwrap(), (1, 1));
+    }
+}
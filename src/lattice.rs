@@ -0,0 +1,140 @@
+/// The shape of the cells on the board, and how they are laid out in the
+/// rows and columns of the board texture.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Lattice {
+    #[default]
+    Square,
+    /// Pointy topped hexagons in "odd-r" offset coordinates, where every odd
+    /// row is pushed half a cell to the right. A cell's neighbours are the
+    /// two beside it and two each in the rows above and below, which are
+    /// the columns `x - 1` and `x` on even rows and `x` and `x + 1` on odd
+    /// rows.
+    Hexagonal,
+    /// Triangles pointing up where `x + y` is even and down where it is odd,
+    /// each spanning two columns so neighbouring triangles in a row
+    /// interlock. A triangle shares its flat edge with the cell in the same
+    /// column of the row below it when it points up, and above it when it
+    /// points down.
+    Triangular,
+}
+
+impl Lattice {
+    /// Fragment entry point in `gol.wgsl` that draws cells of this shape.
+    pub fn fragment_entry_point(&self) -> &'static str {
+        match self {
+            Lattice::Square => "fs_main",
+            Lattice::Hexagonal => "fs_hex",
+            Lattice::Triangular => "fs_triangle",
+        }
+    }
+
+    /// The cell under a point on a board of `width` by `height` cells, given
+    /// in texture coordinates running from 0 to 1 across the board.
+    pub fn cell_at(&self, u: f32, v: f32, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (column, row) = self.cell(u, v, width, height);
+        if column < 0 || row < 0 || column >= width as i64 || row >= height as i64 {
+            return None;
+        }
+        return Some((column as u32, row as u32));
+    }
+
+    /// The cell under a point as in [`Lattice::cell_at`], carrying on the
+    /// board's rows and columns past its edges.
+    pub fn cell(&self, u: f32, v: f32, width: u32, height: u32) -> (i64, i64) {
+        let x = u * width as f32;
+        let y = v * height as f32;
+        return match self {
+            Lattice::Square => (x.floor() as i64, y.floor() as i64),
+            Lattice::Hexagonal => hex_cell(x, y),
+            Lattice::Triangular => triangle_cell(x, y),
+        };
+    }
+}
+
+/// Same as `hex_cell` in `gol.wgsl`. Rows are one unit apart, so the
+/// fractional axial coordinates relative to the first cell are just a shear
+/// of `(x, y)`, which are then rounded to the nearest hexagon.
+fn hex_cell(x: f32, y: f32) -> (i64, i64) {
+    let r = y - 0.5;
+    let q = x - 0.5 - r / 2.0;
+    let s = -q - r;
+
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+
+    let (q, r) = (rq as i64, rr as i64);
+    return (q + (r - (r & 1)) / 2, r);
+}
+
+/// Same as `triangle_cell` in `gol.wgsl`.
+fn triangle_cell(x: f32, y: f32) -> (i64, i64) {
+    let t = x + 0.5;
+    let column = t.floor() as i64;
+    let across = t - t.floor();
+    let row = y.floor() as i64;
+    let down = y - y.floor();
+
+    // Of the two triangles the point could be in, the left one is centred
+    // at `column` and the right one at `column + 1`.
+    let left_up = (column - 1 + row) & 1 == 0;
+    let half_width = if left_up { down } else { 1.0 - down };
+    if across < half_width {
+        return (column - 1, row);
+    }
+    return (column, row);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Points `(x, y)` in cells across a board of `width` by `height` cells,
+    /// in the texture coordinates `Lattice::cell_at` takes.
+    fn uv(x: f32, y: f32, width: u32, height: u32) -> (f32, f32) {
+        return (x / width as f32, y / height as f32);
+    }
+
+    #[test]
+    fn picks_hexagons_at_their_centres() {
+        let (width, height) = (7, 6);
+        for row in 0..height {
+            for column in 0..width {
+                // Odd rows are pushed half a cell to the right.
+                let x = column as f32 + 0.5 + (row & 1) as f32 * 0.5;
+                let (u, v) = uv(x, row as f32 + 0.5, width, height);
+                let cell = Lattice::Hexagonal.cell_at(u, v, width, height);
+                assert_eq!(cell, Some((column, row)));
+            }
+        }
+    }
+
+    #[test]
+    fn picks_triangles_at_their_centres() {
+        let (width, height) = (8, 5);
+        for row in 0..height {
+            for column in 0..width {
+                // Triangles pointing up are wider at the bottom, so their
+                // centres are two thirds of the way down the row.
+                let down = if (column + row) % 2 == 0 { 2.0 } else { 1.0 } / 3.0;
+                let (u, v) = uv(column as f32 + 0.5, row as f32 + down, width, height);
+                let cell = Lattice::Triangular.cell_at(u, v, width, height);
+                assert_eq!(cell, Some((column, row)));
+            }
+        }
+    }
+
+    #[test]
+    fn picks_nothing_off_the_board() {
+        for lattice in [Lattice::Square, Lattice::Hexagonal, Lattice::Triangular] {
+            assert_eq!(lattice.cell_at(-0.1, 0.5, 8, 8), None, "{lattice:?}");
+            assert_eq!(lattice.cell_at(0.5, 1.1, 8, 8), None, "{lattice:?}");
+        }
+        let (u, v) = uv(-1.5, 12.5, 8, 8);
+        assert_eq!(Lattice::Square.cell(u, v, 8, 8), (-2, 12));
+    }
+}
@@ -1,16 +1,40 @@
+#![allow(clippy::needless_return)]
+
+pub mod automata;
 pub mod camera;
+pub mod field;
+pub mod grains;
+pub mod hashlife;
+pub mod import;
+pub mod lattice;
+pub mod new_world;
+pub mod packed;
+pub mod palette;
+pub mod pattern;
+pub mod random;
+pub mod recording;
 mod renderer;
+pub mod sections;
 pub mod texture;
+pub mod tiles;
+pub mod update;
 pub mod vertex;
+pub mod volume;
+pub mod world;
 
 use std::{error::Error, sync::Arc};
 
-use log::info;
-use renderer::State;
+use automata::Rule;
+use import::Import;
+use log::{error, info};
+use new_world::NewWorld;
+use recording::Recording;
+use renderer::{parse_view_size, State, TITLE};
+use update::Update;
 use wgpu::SurfaceError;
 use winit::{
     application::ApplicationHandler,
-    event::{ElementState, Event, KeyEvent, MouseScrollDelta, TouchPhase, WindowEvent},
+    event::{KeyEvent, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     keyboard::{KeyCode, PhysicalKey},
     window::Window,
@@ -22,39 +46,203 @@ async fn main() -> Result<(), Box<dyn Error>> {
 
     info!("ok");
 
+    let options = Options::parse(std::env::args().skip(1))?;
+
+    // The rule is the one argument without a name, e.g.
+    // `cellular_automata "R5,C0,M1,S34..58,B34..45,NM"`.
+    let rule = match &options.rule {
+        Some(rule) => Rule::parse_or_load(rule)?,
+        None => Rule::default(),
+    };
+    // How cells are updated, e.g.
+    // `cellular_automata B3/S23 --update=birth=0.9,survival=0.95,async=0.5,seed=42`.
+    let update = match &options.update {
+        Some(update) => Update::parse(update, random::seed)?,
+        None => Update::default(),
+    };
+    update.check(&rule)?;
+
+    // A new world to start from, e.g.
+    // `cellular_automata B3/S23 --world=width=1024,height=768,fill=random,density=0.3`.
+    let world = match &options.world {
+        Some(world) => Some(NewWorld::parse(world, random::seed)?),
+        None => None,
+    };
+
+    // How dropped images become boards, e.g.
+    // `cellular_automata B3/S23 --import=channel=luminance,dither=ordered,width=512`.
+    let import = match &options.import {
+        Some(import) => Import::parse(import)?,
+        None => Import::default(),
+    };
+
+    // What `G` records of a run, e.g.
+    // `cellular_automata B3/S23 --record=frames=200,step=2,scale=4,format=apng`.
+    let recording = match &options.record {
+        Some(recording) => Recording::parse(recording)?,
+        None => Recording::default(),
+    };
+
+    // The size `V` saves the view at, e.g.
+    // `cellular_automata B3/S23 --view=3840x2160`, the window's otherwise.
+    let view_size = match &options.view {
+        Some(view_size) => Some(parse_view_size(view_size)?),
+        None => None,
+    };
+
+    // `--headless` records a run without opening a window, e.g.
+    // `cellular_automata --headless B3/S23 --world=fill=random --record=frames=200`.
+    if options.headless {
+        return Ok(record_headless(rule, update, world, import, recording).await?);
+    }
+
     let event_loop = EventLoop::with_user_event().build()?;
     event_loop.set_control_flow(ControlFlow::Poll);
+    let _ = event_loop.run_app(&mut App::new(
+        rule, update, world, import, recording, view_size,
+    ));
 
-    let _ = event_loop.run_app(&mut App::new());
+    return Ok(());
+}
 
+/// What the program is started with, the rule being the one argument
+/// without a name and the rest given like `--update=async=0.5`. Blank values
+/// are the same as leaving an option out.
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    headless: bool,
+    rule: Option<String>,
+    update: Option<String>,
+    world: Option<String>,
+    import: Option<String>,
+    record: Option<String>,
+    view: Option<String>,
+}
+
+impl Options {
+    fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut options = Options::default();
+        let given = |value: &str| Some(value.to_string()).filter(|value| !value.trim().is_empty());
+        for arg in args {
+            if arg == "--headless" {
+                options.headless = true;
+                continue;
+            }
+            let Some(option) = arg.strip_prefix("--") else {
+                if options.rule.is_some() {
+                    anyhow::bail!("expected a single rule, got {arg} as well");
+                }
+                options.rule = given(&arg);
+                continue;
+            };
+            let Some((name, value)) = option.split_once('=') else {
+                anyhow::bail!("expected --{option}=<settings>");
+            };
+            let setting = match name {
+                "update" => &mut options.update,
+                "world" => &mut options.world,
+                "import" => &mut options.import,
+                "record" => &mut options.record,
+                "view" => &mut options.view,
+                _ => anyhow::bail!(
+                    "unknown option --{name}, expected --update, --world, --import, --record, --view or --headless"
+                ),
+            };
+            *setting = given(value);
+        }
+        return Ok(options);
+    }
+}
+
+/// Steps the world without a window until the recording has every frame,
+/// then waits for it to be written.
+async fn record_headless(
+    rule: Rule,
+    update: Update,
+    world: Option<NewWorld>,
+    import: Import,
+    recording: Recording,
+) -> anyhow::Result<()> {
+    let mut state = State::new(None, rule, update, import, recording, None).await;
+    if let Some(world) = world {
+        state.create_world(world)?;
+    }
+    state.start_recording()?;
+    while state.is_recording() {
+        state.run_compute();
+    }
+    state.wait_for_recordings();
     return Ok(());
 }
 
 struct App {
     state: Option<State>,
+    rule: Rule,
+    update: Update,
+    world: Option<NewWorld>,
+    import: Import,
+    recording: Recording,
+    view_size: Option<(u32, u32)>,
 }
 
 impl App {
-    fn new() -> App {
-        return App { state: None };
+    fn new(
+        rule: Rule,
+        update: Update,
+        world: Option<NewWorld>,
+        import: Import,
+        recording: Recording,
+        view_size: Option<(u32, u32)>,
+    ) -> App {
+        return App {
+            state: None,
+            rule,
+            update,
+            world,
+            import,
+            recording,
+            view_size,
+        };
     }
 }
 
 impl ApplicationHandler<App> for App {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
-        let window_attributes = Window::default_attributes();
+        let window_attributes = Window::default_attributes().with_title(TITLE);
         let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
 
-        self.state = Some(pollster::block_on(State::new(window)));
+        let mut state = pollster::block_on(State::new(
+            Some(window),
+            self.rule.clone(),
+            self.update,
+            self.import,
+            self.recording,
+            self.view_size,
+        ));
+        if let Some(world) = self.world {
+            if let Err(e) = state.create_world(world) {
+                error!("Could not create a world: {e:#}");
+            }
+        }
+        self.state = Some(state);
     }
 
-    fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+    fn about_to_wait(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
         let state = match &mut self.state {
             Some(canvas) => canvas,
             None => return,
         };
 
-        // state.window.request_redraw();
+        if state.running {
+            state.run_compute();
+            state.request_redraw();
+        }
+    }
+
+    fn exiting(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        if let Some(state) = &mut self.state {
+            state.wait_for_recordings();
+        }
     }
 
     fn window_event(
@@ -78,10 +266,11 @@ impl ApplicationHandler<App> for App {
                             ..
                         },
                     ..
-                } => match (code, key_state.is_pressed()) {
-                    (KeyCode::Escape, true) => event_loop.exit(),
-                    _ => {}
-                },
+                } => {
+                    if let (KeyCode::Escape, true) = (code, key_state.is_pressed()) {
+                        event_loop.exit()
+                    }
+                }
                 WindowEvent::CloseRequested => event_loop.exit(),
                 WindowEvent::Resized(new_size) => state.resize(new_size),
                 WindowEvent::RedrawRequested => {
@@ -97,7 +286,53 @@ impl ApplicationHandler<App> for App {
                 _ => {}
             }
         } else {
-            state.window().request_redraw();
+            state.request_redraw();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<Options> {
+        return Options::parse(args.iter().map(|arg| arg.to_string()));
+    }
+
+    #[test]
+    fn names_every_option_but_the_rule() {
+        let options = parse(&[
+            "--view=3840x2160",
+            "B36/S23",
+            "--headless",
+            "--record=frames=200,step=2",
+            "--world=width=64,height=64",
+        ])
+        .unwrap();
+        assert_eq!(
+            options,
+            Options {
+                headless: true,
+                rule: Some("B36/S23".to_string()),
+                world: Some("width=64,height=64".to_string()),
+                record: Some("frames=200,step=2".to_string()),
+                view: Some("3840x2160".to_string()),
+                ..Options::default()
+            }
+        );
+        assert_eq!(parse(&[]).unwrap(), Options::default());
+    }
+
+    #[test]
+    fn blank_values_are_left_out() {
+        let options = parse(&["", "--update=", "--import= "]).unwrap();
+        assert_eq!(options, Options::default());
+    }
+
+    #[test]
+    fn rejects_unknown_and_repeated_arguments() {
+        assert!(parse(&["--size=64x64"]).is_err());
+        assert!(parse(&["--update"]).is_err());
+        assert!(parse(&["B3/S23", "async=0.5"]).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+
+use anyhow::*;
+
+use crate::{automata::Rule, random::Random, world::World};
+
+/// How the cells of a new world start out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fill {
+    Empty,
+    /// Every cell alive with a chance of `density`.
+    Random {
+        density: f32,
+    },
+    /// Random cells in the top left quarter, mirrored across the middle
+    /// row and column.
+    Symmetric {
+        density: f32,
+    },
+    /// Random cells in a square of `size` cells in the middle, or half the
+    /// shorter side of the world when none is given.
+    Square {
+        density: f32,
+        size: Option<u32>,
+    },
+}
+
+/// The size and fill of a new world. Written like
+/// `width=1024,height=768,fill=random,density=0.3,seed=42`, where `fill` is
+/// `empty`, `random`, `symmetric` or `square`, and `square=64` sets the size
+/// of the square.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NewWorld {
+    pub width: u32,
+    pub height: u32,
+    pub fill: Fill,
+    /// Seed of the random numbers filling the world, so it can be made
+    /// again.
+    pub seed: u64,
+}
+
+impl Default for NewWorld {
+    fn default() -> Self {
+        return NewWorld {
+            width: 512,
+            height: 512,
+            fill: Fill::Random { density: 0.5 },
+            seed: 0,
+        };
+    }
+}
+
+impl NewWorld {
+    /// Parses the settings, taking the seed from `seed` when none is given.
+    pub fn parse(text: &str, seed: impl FnOnce() -> u64) -> Result<Self> {
+        let mut world = NewWorld::default();
+        let mut fill = "random".to_string();
+        let mut density = 0.5;
+        let mut size = None;
+        let mut given_seed = None;
+        for pair in text
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected key=value, got {pair}"))?;
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "width" => world.width = parse_side(value)?,
+                "height" => world.height = parse_side(value)?,
+                "fill" => fill = value.to_ascii_lowercase(),
+                "density" => {
+                    density = value
+                        .parse()
+                        .with_context(|| format!("expected a density, got {value:?}"))?;
+                    if !(0.0..=1.0).contains(&density) {
+                        bail!("densities are between 0 and 1, got {density}");
+                    }
+                }
+                "square" => size = Some(parse_side(value)?),
+                "seed" => given_seed = Some(value.parse()?),
+                _ => bail!("unknown world setting {key}"),
+            }
+        }
+        if world.width as u64 * world.height as u64 > u32::MAX as u64 {
+            bail!(
+                "a {}x{} world has too many cells",
+                world.width,
+                world.height
+            );
+        }
+        world.fill = match fill.as_str() {
+            "empty" => Fill::Empty,
+            "random" => Fill::Random { density },
+            "symmetric" => Fill::Symmetric { density },
+            "square" => Fill::Square { density, size },
+            _ => bail!("unknown fill {fill}, expected empty, random, symmetric or square"),
+        };
+        world.seed = given_seed.unwrap_or_else(seed);
+        return Ok(world);
+    }
+
+    /// Parses the settings along with the rule the world runs, given last
+    /// as `rule=` as rules can contain commas themselves, such as
+    /// `width=256,height=256,rule=R5,C0,M1,S34..58,B34..45,NM`.
+    pub fn parse_with_rule(text: &str, seed: impl FnOnce() -> u64) -> Result<(Self, Option<Rule>)> {
+        let (settings, rule) = match text.find("rule=") {
+            Some(index) => (
+                &text[..index],
+                Some(Rule::parse_or_load(&text[index + "rule=".len()..])?),
+            ),
+            None => (text, None),
+        };
+        return Ok((NewWorld::parse(settings, seed)?, rule));
+    }
+
+    /// The world the settings describe, live cells taking any of the states
+    /// from 1 to `states - 1`.
+    pub fn create(&self, states: u32) -> World {
+        let mut world = World::new(self.width, self.height);
+        let mut random = Random::new(self.seed);
+        let mut cell = |density: f32| -> u8 {
+            if random.next_f32() >= density {
+                return 0;
+            }
+            return 1 + (random.next_u64() % states.saturating_sub(1).max(1) as u64) as u8;
+        };
+        match self.fill {
+            Fill::Empty => {}
+            Fill::Random { density } => {
+                for state in world.cells.iter_mut() {
+                    *state = cell(density);
+                }
+            }
+            Fill::Symmetric { density } => {
+                let (width, height) = (self.width, self.height);
+                for y in 0..height.div_ceil(2) {
+                    for x in 0..width.div_ceil(2) {
+                        let state = cell(density);
+                        for (x, y) in [
+                            (x, y),
+                            (width - 1 - x, y),
+                            (x, height - 1 - y),
+                            (width - 1 - x, height - 1 - y),
+                        ] {
+                            world.set(x, y, state);
+                        }
+                    }
+                }
+            }
+            Fill::Square { density, size } => {
+                let shorter = self.width.min(self.height);
+                let size = size.unwrap_or(shorter / 2).min(shorter);
+                let (left, top) = ((self.width - size) / 2, (self.height - size) / 2);
+                for y in top..top + size {
+                    for x in left..left + size {
+                        world.set(x, y, cell(density));
+                    }
+                }
+            }
+        }
+        return world;
+    }
+}
+
+impl fmt::Display for NewWorld {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "width={},height={},", self.width, self.height)?;
+        match self.fill {
+            Fill::Empty => write!(f, "fill=empty")?,
+            Fill::Random { density } => write!(f, "fill=random,density={density}")?,
+            Fill::Symmetric { density } => write!(f, "fill=symmetric,density={density}")?,
+            Fill::Square { density, size } => {
+                write!(f, "fill=square,density={density}")?;
+                if let Some(size) = size {
+                    write!(f, ",square={size}")?;
+                }
+            }
+        }
+        return write!(f, ",seed={}", self.seed);
+    }
+}
+
+fn parse_side(value: &str) -> Result<u32> {
+    let side: u32 = value
+        .parse()
+        .with_context(|| format!("expected a number of cells, got {value:?}"))?;
+    if side == 0 {
+        bail!("worlds need at least one cell along each side");
+    }
+    return Ok(side);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_rule_last() {
+        let (world, rule) = NewWorld::parse_with_rule(
+            "width=256,height=128,rule=R5,C0,M1,S34..58,B34..45,NM",
+            || 1,
+        )
+        .unwrap();
+        assert_eq!((world.width, world.height, world.seed), (256, 128, 1));
+        assert_eq!(
+            rule,
+            Some(Rule::parse("R5,C0,M1,S34..58,B34..45,NM").unwrap())
+        );
+        let (_, rule) = NewWorld::parse_with_rule("width=256", || 1).unwrap();
+        assert_eq!(rule, None);
+        assert!(NewWorld::parse_with_rule("width=256,rule=nonsense", || 1).is_err());
+    }
+}
@@ -0,0 +1,352 @@
+use std::sync::mpsc;
+
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BlendState, BufferUsages, ColorTargetState, ColorWrites, CommandEncoder, ComputePassDescriptor,
+    ComputePipelineDescriptor, Device, FragmentState, MultisampleState, PipelineLayoutDescriptor,
+    PrimitiveState, Queue, RenderPass, RenderPipelineDescriptor, ShaderStages, VertexState,
+};
+
+use crate::{vertex::Vertex, world::World};
+
+/// Cells packed into each word of the board.
+const CELLS_PER_WORD: u32 = 32;
+
+/// Size of the board and the rule, bound as a uniform at `@group(0)
+/// @binding(4)` of `packed.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PackedBoard {
+    width: u32,
+    height: u32,
+    words: u32,
+    birth: u32,
+    survival: u32,
+}
+
+/// A single cell to set or clear, applied by `cm_paint`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Edit {
+    word: u32,
+    bit: u32,
+    alive: u32,
+    _padding: u32,
+}
+
+/// The board for two state rules that only count their live neighbours, a
+/// bit per cell in a storage buffer rather than four bytes per cell in a
+/// texture. It is drawn on the board quad with the board's camera.
+pub struct Packed {
+    pub width: u32,
+    pub height: u32,
+    /// Words in each row.
+    words: u32,
+    /// Read by the compute and render passes.
+    cells: wgpu::Buffer,
+    /// Written by the compute pass, then copied back to `cells`.
+    next: wgpu::Buffer,
+    /// Cells painted since they were last applied.
+    edits: Vec<Edit>,
+    /// The `PackedBoard` the passes are run with.
+    board_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    paint_bind_group_layout: wgpu::BindGroupLayout,
+    compute_pipeline: wgpu::ComputePipeline,
+    paint_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Packed {
+    /// The cells of `world` packed a bit each, any state but 0 being alive,
+    /// running the rule with the given birth and survival conditions as in
+    /// [`Rule::life_like`](crate::automata::Rule::life_like). The board's
+    /// camera is bound at `@group(1)` when drawing.
+    pub fn new(
+        device: &Device,
+        format: wgpu::TextureFormat,
+        palette_buffer: &wgpu::Buffer,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        (birth, survival): (u32, u32),
+        world: &World,
+    ) -> Packed {
+        let words = world.width.div_ceil(CELLS_PER_WORD);
+        let mut packed = vec![0u32; (words * world.height) as usize];
+        for y in 0..world.height {
+            for x in 0..world.width {
+                if world.get(x, y) != 0 {
+                    packed[(y * words + x / CELLS_PER_WORD) as usize] |= 1 << (x % CELLS_PER_WORD);
+                }
+            }
+        }
+
+        let storage_usage = BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST;
+        let cells = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Packed cells buffer"),
+            contents: bytemuck::cast_slice(&packed),
+            usage: storage_usage,
+        });
+        let next = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Packed next buffer"),
+            size: cells.size(),
+            usage: storage_usage,
+            mapped_at_creation: false,
+        });
+        let board = PackedBoard {
+            width: world.width,
+            height: world.height,
+            words,
+            birth,
+            survival,
+        };
+        let board_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Packed board buffer"),
+            contents: bytemuck::cast_slice(&[board]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let buffer_entry = |binding, visibility, ty| BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let read_only = wgpu::BufferBindingType::Storage { read_only: true };
+        let read_write = wgpu::BufferBindingType::Storage { read_only: false };
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Packed bind group layout"),
+            entries: &[
+                buffer_entry(0, ShaderStages::FRAGMENT | ShaderStages::COMPUTE, read_only),
+                buffer_entry(1, ShaderStages::COMPUTE, read_write),
+                buffer_entry(3, ShaderStages::FRAGMENT, read_only),
+                buffer_entry(
+                    4,
+                    ShaderStages::FRAGMENT | ShaderStages::COMPUTE,
+                    wgpu::BufferBindingType::Uniform,
+                ),
+            ],
+        });
+        let paint_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Packed paint bind group layout"),
+            entries: &[
+                buffer_entry(5, ShaderStages::COMPUTE, read_write),
+                buffer_entry(6, ShaderStages::COMPUTE, read_only),
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Packed bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: cells.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: next.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: palette_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: board_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Packed shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("automata/packed.wgsl").into()),
+        });
+        let create_compute_pipeline = |label, layout: &wgpu::BindGroupLayout, entry_point| {
+            let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            });
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let compute_pipeline =
+            create_compute_pipeline("Packed automata", &bind_group_layout, "cm_main");
+        let paint_pipeline =
+            create_compute_pipeline("Packed paint", &paint_bind_group_layout, "cm_paint");
+
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Packed render pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout, camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Packed pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_packed"),
+                compilation_options: Default::default(),
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_packed"),
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        return Packed {
+            width: world.width,
+            height: world.height,
+            words,
+            cells,
+            next,
+            edits: Vec::new(),
+            board_buffer,
+            bind_group,
+            paint_bind_group_layout,
+            compute_pipeline,
+            paint_pipeline,
+            render_pipeline,
+        };
+    }
+
+    /// Runs the rule with the given birth and survival conditions from the
+    /// next generation on.
+    pub fn set_conditions(&self, queue: &Queue, (birth, survival): (u32, u32)) {
+        let offset = std::mem::offset_of!(PackedBoard, birth) as u64;
+        queue.write_buffer(
+            &self.board_buffer,
+            offset,
+            bytemuck::cast_slice(&[birth, survival]),
+        );
+    }
+
+    /// Records a generation into `encoder`.
+    pub fn step(&self, encoder: &mut CommandEncoder) {
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Packed compute pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            compute_pass.set_pipeline(&self.compute_pipeline);
+            compute_pass.dispatch_workgroups(self.words.div_ceil(8), self.height.div_ceil(8), 1);
+        }
+        encoder.copy_buffer_to_buffer(&self.next, 0, &self.cells, 0, self.cells.size());
+    }
+
+    /// Sets the cell at `(x, y)` alive or dead, landing with the next
+    /// [`Packed::apply_edits`].
+    pub fn paint(&mut self, x: u32, y: u32, alive: bool) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        self.edits.push(Edit {
+            word: y * self.words + x / CELLS_PER_WORD,
+            bit: x % CELLS_PER_WORD,
+            alive: alive as u32,
+            _padding: 0,
+        });
+    }
+
+    /// Records writing the cells painted since the last call into `encoder`.
+    pub fn apply_edits(&mut self, device: &Device, encoder: &mut CommandEncoder) {
+        if self.edits.is_empty() {
+            return;
+        }
+        let edits = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Packed edits buffer"),
+            contents: bytemuck::cast_slice(&self.edits),
+            usage: BufferUsages::STORAGE,
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Packed paint bind group"),
+            layout: &self.paint_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 5,
+                    resource: self.cells.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: edits.as_entire_binding(),
+                },
+            ],
+        });
+        let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Packed paint pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        compute_pass.set_pipeline(&self.paint_pipeline);
+        compute_pass.dispatch_workgroups((self.edits.len() as u32).div_ceil(64), 1, 1);
+        self.edits.clear();
+    }
+
+    /// Copies the cells back from the GPU a byte each, blocking until they
+    /// are available.
+    pub fn read_world(&self, device: &Device, queue: &Queue) -> anyhow::Result<World> {
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Packed readback buffer"),
+            size: self.cells.size(),
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Packed readback"),
+        });
+        encoder.copy_buffer_to_buffer(&self.cells, 0, &readback, 0, self.cells.size());
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        let slice = readback.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::PollType::wait_indefinitely())?;
+        receiver.recv()??;
+
+        let data = slice.get_mapped_range();
+        let packed: &[u32] = bytemuck::cast_slice(&data);
+        let mut world = World::new(self.width, self.height);
+        for y in 0..self.height {
+            let row = &packed[(y * self.words) as usize..][..self.words as usize];
+            for x in 0..self.width {
+                let bit = row[(x / CELLS_PER_WORD) as usize] >> (x % CELLS_PER_WORD) & 1;
+                world.set(x, y, bit as u8);
+            }
+        }
+        return Ok(world);
+    }
+
+    /// Draws the board on the board quad, with the board's camera bind group
+    /// and vertex and index buffers already set on `render_pass`.
+    pub fn render(&self, render_pass: &mut RenderPass, indices: u32) {
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.draw_indexed(0..indices, 0, 0..1);
+    }
+}
@@ -0,0 +1,103 @@
+/// Number of entries in the palette buffer, one for every value a cell state
+/// can take in the red channel of the board texture.
+pub const MAX_STATES: usize = 256;
+
+/// Colours the fragment shader uses to draw each cell state.
+#[derive(Clone, Debug)]
+pub struct Palette {
+    pub colours: Vec<[f32; 4]>,
+}
+
+impl Palette {
+    /// Black for dead cells, white for live cells and a fade from white
+    /// towards black for any decaying states after that.
+    pub fn gradient(states: u32) -> Palette {
+        let mut colours = vec![[0.0, 0.0, 0.0, 1.0], [1.0, 1.0, 1.0, 1.0]];
+        let decaying = states.saturating_sub(2);
+        for i in 0..decaying {
+            let value = 1.0 - (i + 1) as f32 / (decaying + 1) as f32;
+            colours.push([value, value * 0.5, 0.0, 1.0]);
+        }
+
+        return Palette { colours };
+    }
+
+    /// Evenly spaced hues around the colour wheel, for cyclic rules where no
+    /// state is any more alive than the others.
+    pub fn hues(states: u32) -> Palette {
+        let colours = (0..states)
+            .map(|i| {
+                let hue = i as f32 / states as f32 * 6.0;
+                let [r, g, b] = [0.0, 4.0, 2.0]
+                    .map(|offset: f32| (((hue + offset) % 6.0 - 3.0).abs() - 1.0).clamp(0.0, 1.0));
+                [r, g, b, 1.0]
+            })
+            .collect();
+
+        return Palette { colours };
+    }
+
+    /// A smooth colour map over every entry, for continuous rules whose
+    /// values from 0 to 1 are looked up as `value * 255`.
+    pub fn colour_map() -> Palette {
+        let stops = [
+            [0.0, 0.0, 0.0],
+            [0.1, 0.1, 0.5],
+            [0.0, 0.6, 0.8],
+            [1.0, 0.9, 0.2],
+            [1.0, 1.0, 1.0],
+        ];
+        let colours = (0..MAX_STATES)
+            .map(|i| {
+                let position = i as f32 / (MAX_STATES - 1) as f32 * (stops.len() - 1) as f32;
+                let stop = (position as usize).min(stops.len() - 2);
+                let t = position - stop as f32;
+                let [r, g, b] =
+                    [0, 1, 2].map(|c| stops[stop][c] * (1.0 - t) + stops[stop + 1][c] * t);
+                [r, g, b, 1.0]
+            })
+            .collect();
+
+        return Palette { colours };
+    }
+
+    /// The colour of `state` as 8 bit sRGB, the way it looks on screen.
+    /// Alpha isn't a colour, so it is kept linear.
+    pub fn srgb(&self, state: u8) -> [u8; 4] {
+        let [r, g, b, a] = *self
+            .colours
+            .get(state as usize)
+            .unwrap_or(&[0.0, 0.0, 0.0, 1.0]);
+        let [r, g, b] = [r, g, b].map(|c| {
+            let c = c.clamp(0.0, 1.0);
+            return match c <= 0.0031308 {
+                true => c * 12.92,
+                false => 1.055 * c.powf(1.0 / 2.4) - 0.055,
+            };
+        });
+        return [r, g, b, a.clamp(0.0, 1.0)].map(|c| (c * 255.0).round() as u8);
+    }
+
+    /// The palette padded out to [`MAX_STATES`] entries, ready to be written
+    /// to the palette buffer.
+    pub fn to_buffer(&self) -> [[f32; 4]; MAX_STATES] {
+        let mut buffer = [[0.0, 0.0, 0.0, 1.0]; MAX_STATES];
+        for (entry, colour) in buffer.iter_mut().zip(&self.colours) {
+            *entry = *colour;
+        }
+        return buffer;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_alpha_linear() {
+        let palette = Palette {
+            colours: vec![[0.5, 0.0, 1.0, 0.5]],
+        };
+        assert_eq!(palette.srgb(0), [188, 0, 255, 128]);
+    }
+}
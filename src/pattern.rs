@@ -0,0 +1,286 @@
+use anyhow::*;
+
+use crate::{
+    automata::Rule,
+    update::Update,
+    world::{World, MAX_SIDE},
+};
+
+/// Longest line written to an RLE file, as recommended by the format.
+const LINE_LENGTH: usize = 70;
+
+/// Comment line prefix recording how the pattern's cells are updated.
+const UPDATE_COMMENT: &str = "#C update ";
+
+/// A pattern read from an RLE file, along with the rule from its header.
+pub struct Pattern {
+    pub world: World,
+    pub rule: Option<Rule>,
+    /// How cells are updated, kept in a comment line when it isn't
+    /// synchronous so stochastic runs can be repeated.
+    pub update: Option<Update>,
+}
+
+impl Pattern {
+    /// Reads the RLE format used by Golly, including the extended format
+    /// where states above 1 are written as `A` to `X` with an optional `p`
+    /// to `y` prefix.
+    pub fn from_rle(text: &str) -> Result<Self> {
+        let mut width = 0;
+        let mut height = 0;
+        let mut rule = None;
+        let mut cells = Vec::new();
+        // Patterns saved without a seed still load the same every time.
+        let update = text
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(UPDATE_COMMENT))
+            .map(|update| Update::parse(update, || 0))
+            .transpose()?;
+
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'));
+        if let Some(header) = lines.next() {
+            // Rules such as Larger than Life contain commas themselves, so
+            // everything after `rule =` belongs to the rule.
+            let (dimensions, rule_string) = match header.find("rule") {
+                Some(index) => (&header[..index], Some(&header[index + "rule".len()..])),
+                None => (header, None),
+            };
+            for field in dimensions
+                .split(',')
+                .filter(|field| !field.trim().is_empty())
+            {
+                let (key, value) = field
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("malformed RLE header: {header}"))?;
+                let value: u32 = value.trim().parse()?;
+                match key.trim() {
+                    "x" => width = value,
+                    "y" => height = value,
+                    _ => {}
+                }
+                if value > MAX_SIDE {
+                    bail!("patterns are at most {MAX_SIDE} cells a side, got {value}");
+                }
+            }
+            if let Some(rule_string) = rule_string {
+                let rule_string = rule_string.trim_start().trim_start_matches('=');
+                rule = Some(Rule::parse(rule_string)?);
+            }
+        }
+
+        let (mut x, mut y) = (0u32, 0u32);
+        let mut count: Option<u32> = None;
+        let mut prefix: Option<u8> = None;
+        'body: for line in lines {
+            for c in line.chars() {
+                if let Some(digit) = c.to_digit(10) {
+                    count = count
+                        .unwrap_or(0)
+                        .checked_mul(10)
+                        .and_then(|count| count.checked_add(digit));
+                    if count.is_none() {
+                        bail!("run length in RLE pattern is too long");
+                    }
+                    continue;
+                }
+                if c.is_whitespace() {
+                    continue;
+                }
+                if let 'p'..='y' = c {
+                    prefix = Some(c as u8);
+                    continue;
+                }
+
+                let run = count.take().unwrap_or(1);
+                let state = match c {
+                    'b' | '.' => 0,
+                    'o' => 1,
+                    'A'..='X' => {
+                        let offset = prefix.take().map_or(0, |p| (p - b'p' + 1) as u32 * 24);
+                        offset + (c as u8 - b'A') as u32 + 1
+                    }
+                    '$' => {
+                        x = 0;
+                        y = y.saturating_add(run);
+                        continue;
+                    }
+                    '!' => break 'body,
+                    _ => bail!("unexpected {c:?} in RLE pattern"),
+                };
+                if state > u8::MAX as u32 {
+                    bail!("state {state} is out of range");
+                }
+                if x as u64 + run as u64 > MAX_SIDE as u64 || y >= MAX_SIDE {
+                    bail!("patterns are at most {MAX_SIDE} cells a side");
+                }
+                if state != 0 {
+                    cells.extend((x..x + run).map(|x| (x, y, state as u8)));
+                }
+                x += run;
+            }
+        }
+
+        let width = cells.iter().map(|&(x, _, _)| x + 1).fold(width, u32::max);
+        let height = cells.iter().map(|&(_, y, _)| y + 1).fold(height, u32::max);
+        let mut world = World::new(width, height);
+        for (x, y, state) in cells {
+            world.set(x, y, state);
+        }
+
+        return Ok(Pattern {
+            world,
+            rule,
+            update,
+        });
+    }
+
+    pub fn to_rle(&self) -> String {
+        let world = &self.world;
+        let multistate = world.cells.iter().any(|&state| state > 1);
+        let symbol = |state: u8| match (multistate, state) {
+            (false, 0) => "b".to_string(),
+            (false, _) => "o".to_string(),
+            (true, 0) => ".".to_string(),
+            (true, 1..=24) => ((b'A' + state - 1) as char).to_string(),
+            (true, _) => {
+                let prefix = (b'p' + (state - 25) / 24) as char;
+                let state = (b'A' + (state - 25) % 24) as char;
+                format!("{prefix}{state}")
+            }
+        };
+
+        let mut runs = Vec::new();
+        // Rows to move down before the next row with live cells, including
+        // any empty rows at the top.
+        let mut rows = 0;
+        // Worlds with no columns have no rows to write either.
+        for row in world.cells.chunks(world.width.max(1) as usize) {
+            let end = row
+                .iter()
+                .rposition(|&state| state != 0)
+                .map_or(0, |x| x + 1);
+            if end == 0 {
+                rows += 1;
+                continue;
+            }
+            if rows > 0 {
+                runs.push(run_length(rows, "$"));
+            }
+            rows = 1;
+
+            let mut x = 0;
+            while x < end {
+                let state = row[x];
+                let length = row[x..end].iter().take_while(|&&s| s == state).count();
+                runs.push(run_length(length as u32, &symbol(state)));
+                x += length;
+            }
+        }
+        runs.push("!".to_string());
+
+        let mut rle = String::new();
+        if let Some(update) = self.update.filter(|update| !update.is_synchronous()) {
+            rle += &format!("{UPDATE_COMMENT}{update}\n");
+        }
+        if let Some(rule) = &self.rule {
+            rle += &format!(
+                "x = {}, y = {}, rule = {}\n",
+                world.width, world.height, rule
+            );
+        } else {
+            rle += &format!("x = {}, y = {}\n", world.width, world.height);
+        }
+        let mut line_length = 0;
+        for run in runs {
+            if line_length + run.len() > LINE_LENGTH {
+                rle.push('\n');
+                line_length = 0;
+            }
+            line_length += run.len();
+            rle += &run;
+        }
+        rle.push('\n');
+
+        return rle;
+    }
+}
+
+fn run_length(length: u32, symbol: &str) -> String {
+    if length == 1 {
+        return symbol.to_string();
+    }
+    return format!("{length}{symbol}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(world: World, rule: Option<Rule>) -> Pattern {
+        let pattern = Pattern {
+            world,
+            rule,
+            update: None,
+        };
+        return Pattern::from_rle(&pattern.to_rle()).unwrap();
+    }
+
+    #[test]
+    fn keeps_empty_rows_above_the_pattern() {
+        let mut world = World::new(5, 6);
+        world.set(1, 2, 1);
+        world.set(2, 2, 1);
+        world.set(3, 4, 1);
+        let loaded = round_trip(world.clone(), None);
+        assert_eq!((loaded.world.width, loaded.world.height), (5, 6));
+        assert_eq!(loaded.world.cells, world.cells);
+    }
+
+    #[test]
+    fn keeps_the_rule() {
+        let loaded = round_trip(World::new(3, 3), Some(Rule::Life));
+        assert_eq!(loaded.rule, Some(Rule::Life));
+        let rule = Rule::parse("R5,C0,M1,S34..58,B34..45,NM").unwrap();
+        let loaded = round_trip(World::new(3, 3), Some(rule.clone()));
+        assert_eq!(loaded.rule, Some(rule));
+    }
+
+    #[test]
+    fn round_trips_many_states() {
+        let mut world = World::new(30, 3);
+        for x in 0..30 {
+            world.set(x, x % 3, (x * 9) as u8);
+        }
+        let loaded = round_trip(world.clone(), None);
+        assert_eq!(loaded.world.cells, world.cells);
+    }
+
+    #[test]
+    fn reads_golly_rle() {
+        let pattern =
+            Pattern::from_rle("#N Glider\nx = 3, y = 3, rule = B3/S23\nbo$2bo$3o!").unwrap();
+        assert_eq!(pattern.world.cells, [0, 1, 0, 0, 0, 1, 1, 1, 1]);
+        assert!(pattern.rule.is_some());
+    }
+
+    #[test]
+    fn round_trips_empty_patterns() {
+        let pattern = Pattern::from_rle("x = 0, y = 0\n!").unwrap();
+        assert_eq!(pattern.world.cells.len(), 0);
+        let loaded = Pattern::from_rle(&pattern.to_rle()).unwrap();
+        assert_eq!((loaded.world.width, loaded.world.height), (0, 0));
+        let loaded = round_trip(World::new(0, 4), None);
+        assert_eq!((loaded.world.width, loaded.world.height), (0, 4));
+    }
+
+    #[test]
+    fn rejects_patterns_too_large_for_a_board() {
+        assert!(Pattern::from_rle("x = 70000, y = 70000\no!").is_err());
+        assert!(Pattern::from_rle("x = 3, y = 3\n70000bo!").is_err());
+        assert!(Pattern::from_rle("x = 3, y = 3\n70000$o!").is_err());
+        assert!(Pattern::from_rle("x = 3, y = 3\n99999999999o!").is_err());
+    }
+}
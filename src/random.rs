@@ -0,0 +1,39 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::info;
+
+/// A small seeded random number generator (SplitMix64), so anything random
+/// about a board can be recreated from its seed.
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        return Random { state: seed };
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        return z ^ (z >> 31);
+    }
+
+    /// A number in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        return (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32;
+    }
+}
+
+/// A seed for anything random about a new board, taken from the clock and
+/// logged so the board can be recreated.
+pub fn seed() -> u64 {
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64);
+    info!("Using random seed {seed}");
+    return seed;
+}
@@ -0,0 +1,687 @@
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt,
+    fs::{File, OpenOptions},
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::JoinHandle,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::*;
+use log::{error, info};
+use wgpu::{BufferUsages, CommandEncoder, Device, Extent3d, Origin3d};
+
+use crate::{
+    automata::Rule,
+    palette::{Palette, MAX_STATES},
+};
+
+/// The file a recording is written to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Gif,
+    Apng,
+}
+
+/// The colours states are drawn in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Colours {
+    /// The colours the rule draws its states in.
+    Rule,
+    /// Black for dead cells through to white for the last state.
+    Gray,
+    /// Evenly spaced hues, as cyclic rules are drawn.
+    Hues,
+}
+
+/// What to record of a run and how. Written like
+/// `frames=100,step=2,scale=4,crop=0:0:64:64,palette=gray,format=apng,delay=50`,
+/// where `crop` is the left, top, width and height of the part of the board
+/// recorded.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Recording {
+    /// Number of frames recorded, the first being the board when the
+    /// recording starts.
+    pub frames: u32,
+    /// Generations between frames.
+    pub step: u32,
+    /// Pixels along each side of a cell.
+    pub scale: u32,
+    pub crop: Option<[u32; 4]>,
+    pub colours: Colours,
+    pub format: Format,
+    /// Milliseconds each frame is shown for.
+    pub delay: u32,
+}
+
+impl Default for Recording {
+    fn default() -> Self {
+        return Recording {
+            frames: 100,
+            step: 1,
+            scale: 1,
+            crop: None,
+            colours: Colours::Rule,
+            format: Format::Gif,
+            delay: 50,
+        };
+    }
+}
+
+impl Recording {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut recording = Recording::default();
+        for pair in text
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected key=value, got {pair}"))?;
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "frames" => recording.frames = parse_count(value)?,
+                "step" => recording.step = parse_count(value)?,
+                "scale" => recording.scale = parse_count(value)?,
+                "crop" => {
+                    let crop: Vec<u32> = value
+                        .split(':')
+                        .map(|part| part.trim().parse())
+                        .collect::<Result<_, _>>()
+                        .with_context(|| format!("expected left:top:width:height, got {value}"))?;
+                    match crop[..] {
+                        [left, top, width, height] if width > 0 && height > 0 => {
+                            recording.crop = Some([left, top, width, height]);
+                        }
+                        _ => bail!("expected left:top:width:height, got {value}"),
+                    }
+                }
+                "palette" => {
+                    recording.colours = match value.to_ascii_lowercase().as_str() {
+                        "rule" => Colours::Rule,
+                        "gray" => Colours::Gray,
+                        "hues" => Colours::Hues,
+                        _ => bail!("unknown palette {value}, expected rule, gray or hues"),
+                    }
+                }
+                "format" => {
+                    recording.format = match value.to_ascii_lowercase().as_str() {
+                        "gif" => Format::Gif,
+                        "apng" => Format::Apng,
+                        _ => bail!("unknown format {value}, expected gif or apng"),
+                    }
+                }
+                "delay" => {
+                    recording.delay = value
+                        .parse()
+                        .with_context(|| format!("expected milliseconds, got {value:?}"))?;
+                }
+                _ => bail!("unknown recording setting {key}"),
+            }
+        }
+        return Ok(recording);
+    }
+}
+
+impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "frames={},step={},scale={},",
+            self.frames, self.step, self.scale
+        )?;
+        if let Some([left, top, width, height]) = self.crop {
+            write!(f, "crop={left}:{top}:{width}:{height},")?;
+        }
+        let colours = match self.colours {
+            Colours::Rule => "rule",
+            Colours::Gray => "gray",
+            Colours::Hues => "hues",
+        };
+        let format = match self.format {
+            Format::Gif => "gif",
+            Format::Apng => "apng",
+        };
+        return write!(f, "palette={colours},format={format},delay={}", self.delay);
+    }
+}
+
+/// The state of each cell of a mapped frame `width` cells across, unmapping
+/// it.
+fn cells(buffer: &wgpu::Buffer, width: u32) -> Vec<u8> {
+    let bytes_per_row = (4 * width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+    let cells = buffer
+        .slice(..)
+        .get_mapped_range()
+        .chunks(bytes_per_row as usize)
+        .flat_map(|row| row[..4 * width as usize].chunks(4).map(|pixel| pixel[0]))
+        .collect();
+    buffer.unmap();
+    return cells;
+}
+
+/// Left, top, width and height of the part of a board of `board` cells
+/// `settings` records, if it is on the board and fits in the file.
+fn region(settings: &Recording, board: (u32, u32)) -> Result<[u32; 4]> {
+    let region = settings.crop.unwrap_or([0, 0, board.0, board.1]);
+    let [left, top, width, height] = region;
+    if left as u64 + width as u64 > board.0 as u64 || top as u64 + height as u64 > board.1 as u64 {
+        bail!(
+            "Can't crop {width}x{height} cells at {left}, {top} from a {}x{} board",
+            board.0,
+            board.1
+        );
+    }
+    let size = (
+        width as u64 * settings.scale as u64,
+        height as u64 * settings.scale as u64,
+    );
+    if settings.format == Format::Gif && (size.0 > u16::MAX as u64 || size.1 > u16::MAX as u64) {
+        bail!("A {}x{} recording is too large for a GIF", size.0, size.1);
+    }
+    if size.0 * size.1 > u32::MAX as u64 {
+        bail!("A {}x{} recording is too large", size.0, size.1);
+    }
+    return Ok(region);
+}
+
+fn parse_count(value: &str) -> Result<u32> {
+    let count: u32 = value
+        .parse()
+        .with_context(|| format!("expected a number, got {value:?}"))?;
+    if count == 0 {
+        bail!("expected a number above 0, got {count}");
+    }
+    return Ok(count);
+}
+
+enum Message {
+    /// A frame's cells, read out of the buffer they were copied into, or
+    /// why it failed to map.
+    Frame(u32, Result<Vec<u8>, wgpu::BufferAsyncError>),
+    /// No more frames will be captured after the first `frames`.
+    Finish { frames: u32 },
+}
+
+/// Records frames of the board, copying each into a buffer of its own that
+/// is read once the GPU has mapped it. The frames are encoded on a thread of
+/// their own as they arrive, so neither waits on the other or on the window.
+/// Buffers are only read and unmapped where the device is polled, as the
+/// OpenGL backend can't share its context with the encoding thread. Dropping
+/// the recorder leaves the thread to write the last frames, `finish` hands
+/// it over to be waited on.
+pub struct Recorder {
+    settings: Recording,
+    /// Size of the board the recording started on.
+    pub board: (u32, u32),
+    /// Left, top, width and height of the part of the board recorded.
+    region: [u32; 4],
+    /// Generations since the recording started.
+    generations: u32,
+    captured: u32,
+    sender: Sender<Message>,
+    /// Polled when finishing, as frames still being mapped only reach the
+    /// encoding thread once the device is.
+    device: Device,
+    encoding: Option<JoinHandle<()>>,
+}
+
+impl Recorder {
+    pub fn new(
+        device: &Device,
+        settings: Recording,
+        rule: &Rule,
+        board: (u32, u32),
+    ) -> Result<Self> {
+        let region = region(&settings, board)?;
+        let [_, _, width, height] = region;
+
+        let states = rule.states();
+        let palette = match settings.colours {
+            Colours::Rule => rule.palette(),
+            Colours::Gray => Palette {
+                colours: (0..states)
+                    .map(|state| {
+                        let value = state as f32 / (states - 1).max(1) as f32;
+                        [value, value, value, 1.0]
+                    })
+                    .collect(),
+            },
+            Colours::Hues => Palette::hues(states),
+        };
+        let palette: Vec<u8> = (0..MAX_STATES)
+            .flat_map(|state| {
+                let [r, g, b, _] = palette.srgb(state as u8);
+                [r, g, b]
+            })
+            .collect();
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let path = match settings.format {
+            Format::Gif => format!("recording-{timestamp}.gif"),
+            Format::Apng => format!("recording-{timestamp}.png"),
+        };
+        let (sender, receiver) = mpsc::channel();
+        let encoding = Encoding {
+            settings,
+            width,
+            height,
+            palette,
+            path: PathBuf::from(&path),
+        };
+        let encoding = std::thread::spawn(move || match encoding.run(receiver) {
+            Result::Ok(()) => info!("Saved recording to {path}"),
+            Err(e) => error!("Could not save recording: {e:#}"),
+        });
+
+        info!("Recording {settings}");
+        return Ok(Recorder {
+            settings,
+            board,
+            region,
+            generations: 0,
+            captured: 0,
+            sender,
+            device: device.clone(),
+            encoding: Some(encoding),
+        });
+    }
+
+    /// Captures the board in `texture` if it is a frame of the recording,
+    /// returning whether the recording has every frame it needs.
+    pub fn capture(
+        &mut self,
+        device: &Device,
+        encoder: &mut CommandEncoder,
+        texture: &wgpu::Texture,
+    ) -> bool {
+        if self.generations.is_multiple_of(self.settings.step) {
+            let [left, top, width, height] = self.region;
+            let bytes_per_row = (4 * width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Recording buffer"),
+                size: (bytes_per_row * height) as u64,
+                usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            encoder.copy_texture_to_buffer(
+                wgpu::TexelCopyTextureInfo {
+                    texture,
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: left,
+                        y: top,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::TexelCopyBufferInfo {
+                    buffer: &buffer,
+                    layout: wgpu::TexelCopyBufferLayout {
+                        offset: 0,
+                        bytes_per_row: Some(bytes_per_row),
+                        rows_per_image: Some(height),
+                    },
+                },
+                Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+            // Mapping only starts once the copy has been submitted.
+            let (frame, sender, mapped) = (self.captured, self.sender.clone(), buffer.clone());
+            encoder.map_buffer_on_submit(&buffer, wgpu::MapMode::Read, .., move |result| {
+                let cells = result.map(|()| cells(&mapped, width));
+                let _ = sender.send(Message::Frame(frame, cells));
+            });
+            self.captured += 1;
+        }
+        self.generations += 1;
+        return self.captured == self.settings.frames;
+    }
+}
+
+impl Recorder {
+    /// Stops capturing frames, returning the thread still writing them.
+    pub fn finish(mut self) -> Option<JoinHandle<()>> {
+        return self.encoding.take();
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Message::Finish {
+            frames: self.captured,
+        });
+        if let Err(e) = self.device.poll(wgpu::PollType::wait_indefinitely()) {
+            error!("Could not wait for the last frames of the recording: {e}");
+        }
+    }
+}
+
+/// What the encoding thread needs to write the frames to a file.
+struct Encoding {
+    settings: Recording,
+    width: u32,
+    height: u32,
+    /// RGB colour of every state.
+    palette: Vec<u8>,
+    /// Where the file is written once the first frame arrives.
+    path: PathBuf,
+}
+
+impl Encoding {
+    /// Writes frames in order as they arrive, until the recorder is finished
+    /// and every frame it captured has arrived. Only frames arriving ahead of
+    /// one still being mapped are held on to.
+    fn run(&self, receiver: Receiver<Message>) -> Result<()> {
+        let mut writer = None;
+        let mut early = BTreeMap::new();
+        let mut next = 0;
+        let mut written = 0;
+        let mut expected = None;
+        while expected.is_none_or(|expected| next < expected) {
+            // A closed channel means every sender, including the ones
+            // waiting on buffers, has gone.
+            let Result::Ok(message) = receiver.recv() else {
+                break;
+            };
+            match message {
+                Message::Frame(frame, Result::Ok(cells)) => {
+                    early.insert(frame, Some(cells));
+                }
+                Message::Frame(frame, Err(e)) => {
+                    error!("Could not read frame {frame} of the recording: {e}");
+                    early.insert(frame, None);
+                }
+                Message::Finish { frames } => expected = Some(frames),
+            }
+            while let Some(cells) = early.remove(&next) {
+                next += 1;
+                let Some(cells) = cells else {
+                    continue;
+                };
+                if writer.is_none() {
+                    writer = Some(self.create()?);
+                }
+                writer.as_mut().unwrap().write(&self.scale(&cells))?;
+                written += 1;
+            }
+        }
+        let Some(writer) = writer else {
+            bail!("No frames were recorded");
+        };
+        writer.finish()?;
+        if self.settings.format == Format::Apng && written < self.settings.frames {
+            set_apng_frames(&self.path, written)?;
+        }
+        return Ok(());
+    }
+
+    /// Creates the file and writes its header.
+    fn create(&self) -> Result<Writer> {
+        let file = BufWriter::new(File::create(&self.path)?);
+        let (width, height) = (
+            self.width * self.settings.scale,
+            self.height * self.settings.scale,
+        );
+        let writer = match self.settings.format {
+            Format::Gif => {
+                let mut encoder =
+                    gif::Encoder::new(file, width as u16, height as u16, &self.palette)?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+                Writer::Gif {
+                    encoder,
+                    width: width as u16,
+                    height: height as u16,
+                    // GIFs count delays in hundredths of a second.
+                    delay: (self.settings.delay / 10).min(u16::MAX as u32) as u16,
+                }
+            }
+            Format::Apng => {
+                let mut encoder = png::Encoder::new(file, width, height);
+                encoder.set_color(png::ColorType::Indexed);
+                encoder.set_depth(png::BitDepth::Eight);
+                encoder.set_palette(self.palette.as_slice());
+                // Rewritten by `set_apng_frames` if the recording stops early.
+                encoder.set_animated(self.settings.frames, 0)?;
+                encoder.set_frame_delay(self.settings.delay.min(u16::MAX as u32) as u16, 1000)?;
+                Writer::Apng(encoder.write_header()?)
+            }
+        };
+        return Ok(writer);
+    }
+
+    /// A frame's cells blown up to `scale` pixels a side.
+    fn scale(&self, cells: &[u8]) -> Vec<u8> {
+        let scale = self.settings.scale as usize;
+        if scale == 1 {
+            return cells.to_vec();
+        }
+        return cells
+            .chunks(self.width as usize)
+            .flat_map(|row| {
+                let row: Vec<u8> = row
+                    .iter()
+                    .flat_map(|&state| [state].repeat(scale))
+                    .collect();
+                row.repeat(scale)
+            })
+            .collect();
+    }
+}
+
+/// An open recording, taking frames one at a time.
+enum Writer {
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        width: u16,
+        height: u16,
+        delay: u16,
+    },
+    Apng(png::Writer<BufWriter<File>>),
+}
+
+impl Writer {
+    fn write(&mut self, pixels: &[u8]) -> Result<()> {
+        match self {
+            Writer::Gif {
+                encoder,
+                width,
+                height,
+                delay,
+            } => {
+                encoder.write_frame(&gif::Frame {
+                    width: *width,
+                    height: *height,
+                    delay: *delay,
+                    buffer: Cow::Borrowed(pixels),
+                    ..Default::default()
+                })?;
+            }
+            Writer::Apng(writer) => writer.write_image_data(pixels)?,
+        }
+        return Ok(());
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Writer::Gif { encoder, .. } => {
+                encoder.into_inner()?.flush()?;
+            }
+            Writer::Apng(writer) => writer.finish()?,
+        }
+        return Ok(());
+    }
+}
+
+/// Sets the number of frames the APNG at `path` says it has, as it is
+/// written before the frames are. Its `acTL` chunk follows the signature
+/// and the `IHDR` chunk, as nothing else is set before it.
+fn set_apng_frames(path: &Path, frames: u32) -> Result<()> {
+    const ACTL: u64 = 8 + 25;
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut chunk = [0; 20];
+    file.seek(SeekFrom::Start(ACTL))?;
+    file.read_exact(&mut chunk)?;
+    if &chunk[4..8] != b"acTL" {
+        bail!("Could not find the frame count of {}", path.display());
+    }
+    chunk[8..12].copy_from_slice(&frames.to_be_bytes());
+    let crc = crc32fast::hash(&chunk[4..16]);
+    chunk[16..].copy_from_slice(&crc.to_be_bytes());
+    file.seek(SeekFrom::Start(ACTL))?;
+    file.write_all(&chunk)?;
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_it_writes() {
+        let text = "frames=20,step=2,scale=4,crop=1:2:30:40,palette=gray,format=apng,delay=80";
+        let recording = Recording::parse(text).unwrap();
+        assert_eq!(recording.crop, Some([1, 2, 30, 40]));
+        assert_eq!(recording.to_string(), text);
+        assert_eq!(
+            Recording::parse("").unwrap().to_string(),
+            "frames=100,step=1,scale=1,palette=rule,format=gif,delay=50"
+        );
+        assert_eq!(
+            Recording::parse(" Palette = HUES ").unwrap().colours,
+            Colours::Hues
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_settings() {
+        for text in [
+            "frames=0",
+            "step=-1",
+            "crop=0:0:10",
+            "crop=0:0:0:10",
+            "crop=a:0:1:1",
+            "palette=sepia",
+            "format=mp4",
+            "delay",
+            "speed=2",
+        ] {
+            assert!(Recording::parse(text).is_err(), "{text}");
+        }
+    }
+
+    #[test]
+    fn only_crops_the_board() {
+        let region = |text: &str| region(&Recording::parse(text).unwrap(), (64, 48));
+        assert_eq!(region("").unwrap(), [0, 0, 64, 48]);
+        assert_eq!(region("crop=4:8:60:40").unwrap(), [4, 8, 60, 40]);
+        assert!(region("crop=5:8:60:40").is_err());
+        assert!(region("crop=4:9:60:40").is_err());
+        assert!(region("crop=4294967295:0:1:1").is_err());
+    }
+
+    #[test]
+    fn only_gifs_limit_each_side() {
+        let board = (2048, 1);
+        let gif = Recording::parse("scale=32").unwrap();
+        assert!(region(&gif, board).is_err());
+        let apng = Recording::parse("scale=32,format=apng").unwrap();
+        assert_eq!(region(&apng, board).unwrap(), [0, 0, 2048, 1]);
+        let huge = Recording::parse("scale=65536,format=apng").unwrap();
+        assert!(region(&huge, (2, 1)).is_err());
+    }
+
+    #[test]
+    fn scales_cells_up_to_squares_of_pixels() {
+        let encoding = Encoding {
+            settings: Recording::parse("scale=2").unwrap(),
+            width: 3,
+            height: 1,
+            palette: Vec::new(),
+            path: PathBuf::new(),
+        };
+        assert_eq!(
+            encoding.scale(&[1, 2, 3]),
+            [1, 1, 2, 2, 3, 3, 1, 1, 2, 2, 3, 3]
+        );
+        let encoding = Encoding {
+            settings: Recording::default(),
+            ..encoding
+        };
+        assert_eq!(encoding.scale(&[1, 2, 3]), [1, 2, 3]);
+    }
+
+    /// Runs an encoding of 2x1 frames into a file named `name`, with
+    /// `messages` sent as the recorder would, returning its path.
+    fn encode(name: &str, settings: &str, messages: Vec<Message>) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+        let encoding = Encoding {
+            settings: Recording::parse(settings).unwrap(),
+            width: 2,
+            height: 1,
+            palette: (0..MAX_STATES).flat_map(|state| [state as u8; 3]).collect(),
+            path: path.clone(),
+        };
+        let (sender, receiver) = mpsc::channel();
+        for message in messages {
+            sender.send(message).unwrap();
+        }
+        encoding.run(receiver).unwrap();
+        return path;
+    }
+
+    #[test]
+    fn writes_frames_in_order_as_they_arrive() {
+        let path = encode(
+            "order.gif",
+            "frames=3",
+            vec![
+                Message::Frame(1, Result::Ok(vec![1, 0])),
+                Message::Frame(0, Result::Ok(vec![0, 1])),
+                Message::Finish { frames: 3 },
+                Message::Frame(2, Result::Ok(vec![1, 1])),
+            ],
+        );
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            frames.push(frame.buffer.to_vec());
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(frames, [[0, 1], [1, 0], [1, 1]]);
+    }
+
+    #[test]
+    fn apngs_stopped_early_count_the_frames_they_have() {
+        let path = encode(
+            "early.png",
+            "frames=10,format=apng",
+            vec![
+                Message::Frame(0, Result::Ok(vec![0, 1])),
+                Message::Frame(1, Err(wgpu::BufferAsyncError)),
+                Message::Frame(2, Result::Ok(vec![1, 0])),
+                Message::Finish { frames: 3 },
+            ],
+        );
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let frames = reader.info().animation_control.unwrap().num_frames;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let mut cells = Vec::new();
+        for _ in 0..frames {
+            reader.next_frame(&mut buffer).unwrap();
+            cells.push(buffer[..2].to_vec());
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(cells, [[0, 1], [1, 0]]);
+    }
+}
@@ -1,4 +1,9 @@
-use std::{sync::Arc};
+use std::{
+    path::Path,
+    sync::{mpsc, Arc},
+    thread::JoinHandle,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use wgpu::{
     util::{BufferInitDescriptor, DeviceExt},
@@ -12,21 +17,44 @@ use wgpu::{
     RequestAdapterOptions, ShaderStages, Surface, SurfaceConfiguration, SurfaceError,
     TextureUsages, TextureViewDescriptor, TextureViewDimension, VertexState,
 };
+use image::{Rgba, RgbaImage};
+use log::{error, info, warn};
 use winit::{
     dpi::{PhysicalPosition, PhysicalSize},
     event::{KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
-    keyboard::{KeyCode, PhysicalKey},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
     window::Window,
 };
 
 use crate::{
+    automata::{
+        rulefile::GollyRule,
+        turmite::{Ant, MAX_ANTS},
+        Rule, Step,
+    },
     camera::{Camera, CameraController},
+    field::Field,
+    grains::Grains,
+    hashlife::{HashLife, MAX_STEP},
+    import::Import,
+    lattice::Lattice,
+    new_world::NewWorld,
+    packed::Packed,
+    pattern::Pattern,
+    random::seed,
+    recording::{Recorder, Recording},
+    sections::Sections,
     texture::Texture,
+    tiles::Tiles,
+    update::Update,
     vertex::Vertex,
+    volume::Volume,
+    world::World,
 };
 
 pub struct State {
-    surface: Surface<'static>,
+    /// Missing when running headless.
+    surface: Option<Surface<'static>>,
     device: Device,
     queue: Queue,
     config: SurfaceConfiguration,
@@ -34,16 +62,20 @@ pub struct State {
 
     pub position: PhysicalPosition<f64>,
     render_pipeline: RenderPipeline,
+    render_pipeline_layout: wgpu::PipelineLayout,
+    /// Lattice of the board, which decides the shape cells are drawn and
+    /// picked with.
+    lattice: Lattice,
 
     pub render_state: RenderState,
 
-    pub window: Arc<Window>,
+    pub window: Option<Arc<Window>>,
     vertex_buffer: wgpu::Buffer,
 
-    num_verticies: u32,
     index_buffer: wgpu::Buffer,
     pub camera: Camera,
     camera_bind_group: wgpu::BindGroup,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
 
     texture_bind_group: wgpu::BindGroup,
     presentation_texture: Texture,
@@ -52,9 +84,80 @@ pub struct State {
     pub camera_controller: CameraController,
     camera_uniform_buffer: wgpu::Buffer,
     compute_pipeline: wgpu::ComputePipeline,
+    compute_pipeline_layout: wgpu::PipelineLayout,
     compute_texture: Texture,
     is_mouse_pressed: bool,
+    /// State placed on the board while the right mouse button is held.
+    brush: u8,
+    is_painting: bool,
+    modifiers: ModifiersState,
+    last_painted: Option<(i64, i64)>,
+    pub running: bool,
     texture_bind_group_layout: wgpu::BindGroupLayout,
+
+    rule: Rule,
+    rule_bind_group: wgpu::BindGroup,
+    rule_bind_group_layout: wgpu::BindGroupLayout,
+    step_buffer: wgpu::Buffer,
+    /// Generations run since the board was loaded, which HashLife can take
+    /// well past `u32::MAX`.
+    generation: u64,
+    /// Whether the rule is being run backwards, for reversible rules.
+    reversed: bool,
+    /// How cells take on the states the rule gives them, applied by a pass
+    /// of its own unless it is synchronous.
+    update: Update,
+    update_buffer: wgpu::Buffer,
+    update_bind_group: wgpu::BindGroup,
+    update_bind_group_layout: wgpu::BindGroupLayout,
+    update_pipeline: wgpu::ComputePipeline,
+    /// The board before the last generation, for the update pass.
+    previous_texture: Texture,
+    palette_buffer: wgpu::Buffer,
+
+    /// Turmites walking over the board, only the first `ant_count` are live.
+    ants_buffer: wgpu::Buffer,
+    ant_count: u32,
+    agent_bind_group: wgpu::BindGroup,
+    agent_render_bind_group: wgpu::BindGroup,
+    agent_pipeline: RenderPipeline,
+
+    /// Stepped and drawn in place of the board while the rule is 3D.
+    volume: Option<Volume>,
+    /// Stepped and drawn in place of the board while the rule is continuous.
+    field: Option<Field>,
+    /// Stepped and drawn in place of the board while the rule is a sandpile.
+    grains: Option<Grains>,
+    /// Stepped in place of the board while HashLife is on, with the part of
+    /// its universe in view drawn onto the board.
+    hashlife: Option<HashLife>,
+    /// Stepped and drawn in place of the board while the world is unbounded.
+    tiles: Option<Tiles>,
+    /// Stepped and drawn in place of the board while it is packed a bit per
+    /// cell.
+    packed: Option<Packed>,
+    /// Stepped and drawn in place of the board while it is too large for a
+    /// single texture.
+    sections: Option<Sections>,
+    /// Cells along each side of the largest texture a board is kept in,
+    /// larger boards being packed or split into sections.
+    texture_limit: u32,
+    /// Settings of the last world created, made again with a new seed by
+    /// `N`.
+    world_settings: NewWorld,
+    /// How dropped images become boards.
+    import: Import,
+    /// What `G` records of a run.
+    recording: Recording,
+    /// Capturing frames of the board while it is being recorded.
+    recorder: Option<Recorder>,
+    /// Threads still writing out recordings that have finished capturing.
+    encodings: Vec<JoinHandle<()>>,
+    /// Size `V` saves the view at, the window's size when not given.
+    view_size: Option<(u32, u32)>,
+    /// Settings for a new world being typed after `Shift+N`, shown in the
+    /// window's title until `Enter` creates it.
+    typing: Option<String>,
 }
 
 pub enum RenderState {
@@ -97,22 +200,46 @@ const VERTICES: &[Vertex] = &[
 
 const INDICES: &[u32] = &[0, 2, 1, 3, 1, 2];
 
+/// Title of the window while nothing is being typed into it.
+pub const TITLE: &str = "Cellular automata";
+
+/// Size the view is taken to be without a window.
+const HEADLESS_SIZE: PhysicalSize<u32> = PhysicalSize::new(800, 600);
+
 impl State {
-    pub async fn new<'a>(window: Arc<Window>) -> State {
-        let size = window.inner_size();
+    /// Sets up drawing to `window`, or only stepping and recording the board
+    /// when there is none.
+    pub async fn new(
+        window: Option<Arc<Window>>,
+        rule: Rule,
+        update: Update,
+        import: Import,
+        recording: Recording,
+        view_size: Option<(u32, u32)>,
+    ) -> State {
+        let size = window
+            .as_ref()
+            .map_or(HEADLESS_SIZE, |window| window.inner_size());
 
+        // Without a window any backend will do, such as OpenGL on a server
+        // with only a software renderer.
         let instance = Instance::new(&wgpu::InstanceDescriptor {
-            backends: Backends::PRIMARY,
+            backends: match window {
+                Some(_) => Backends::PRIMARY,
+                None => Backends::all(),
+            },
             ..Default::default()
         });
 
-        let surface = instance.create_surface(window.clone()).unwrap();
+        let surface = window
+            .clone()
+            .map(|window| instance.create_surface(window).unwrap());
 
         let adapter = instance
             .request_adapter(&RequestAdapterOptions {
                 power_preference: PowerPreference::None,
                 force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
+                compatible_surface: surface.as_ref(),
             })
             .await
             .unwrap();
@@ -129,14 +256,22 @@ impl State {
             .await
             .unwrap();
 
-        let surface_capabilites = surface.get_capabilities(&adapter);
-
-        let surface_format = surface_capabilites
-            .formats
-            .iter()
-            .find(|f| f.is_srgb())
-            .copied()
-            .unwrap_or(surface_capabilites.formats[0]);
+        let (surface_format, alpha_mode) = match &surface {
+            Some(surface) => {
+                let surface_capabilites = surface.get_capabilities(&adapter);
+                let surface_format = surface_capabilites
+                    .formats
+                    .iter()
+                    .find(|f| f.is_srgb())
+                    .copied()
+                    .unwrap_or(surface_capabilites.formats[0]);
+                (surface_format, surface_capabilites.alpha_modes[0])
+            }
+            None => (
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                wgpu::CompositeAlphaMode::Auto,
+            ),
+        };
 
         let config = SurfaceConfiguration {
             usage: TextureUsages::RENDER_ATTACHMENT,
@@ -145,7 +280,7 @@ impl State {
             height: size.height,
             present_mode: wgpu::PresentMode::Fifo,
             desired_maximum_frame_latency: 2,
-            alpha_mode: surface_capabilites.alpha_modes[0],
+            alpha_mode,
             view_formats: vec![],
         };
 
@@ -157,12 +292,23 @@ impl State {
 
         let camera_controller = CameraController::new(0.1);
 
-        let diffuse_bytes = include_bytes!("ok.png");
+        let limit = device.limits().max_texture_dimension_2d;
+        let view_size = view_size.filter(|&(width, height)| {
+            let fits = width <= limit && height <= limit;
+            if !fits {
+                error!("Views are at most {limit} pixels a side, saving them at the window's size");
+            }
+            return fits;
+        });
+
+        let world = import.load(include_bytes!("ok.png"), rule.states()).unwrap();
         let presentation_texture =
-            Texture::from_bytes(&device, &queue, diffuse_bytes, "Presentation texture").unwrap();
+            Texture::from_image(&device, &queue, &world.to_image(), Some("Presentation texture"))
+                .unwrap();
 
         let compute_texture =
-            Texture::from_bytes(&device, &queue, diffuse_bytes, "Compute texture").unwrap();
+            Texture::from_image(&device, &queue, &world.to_image(), Some("Compute texture"))
+                .unwrap();
 
         let texture_bind_group_layout =
             device.create_bind_group_layout(&BindGroupLayoutDescriptor {
@@ -170,7 +316,9 @@ impl State {
                 entries: &[
                     BindGroupLayoutEntry {
                         binding: 0,
-                        visibility: ShaderStages::FRAGMENT | ShaderStages::COMPUTE,
+                        visibility: ShaderStages::VERTEX
+                            | ShaderStages::FRAGMENT
+                            | ShaderStages::COMPUTE,
                         ty: wgpu::BindingType::Texture {
                             sample_type: wgpu::TextureSampleType::Float { filterable: true },
                             view_dimension: wgpu::TextureViewDimension::D2,
@@ -194,28 +342,33 @@ impl State {
                         },
                         count: None,
                     },
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: true },
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
                 ],
             });
 
-        let texture_bind_group = device.create_bind_group(&BindGroupDescriptor {
-            label: None,
-            layout: &texture_bind_group_layout,
-            entries: &[
-                BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&presentation_texture.view),
-                },
-                BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&presentation_texture.sampler),
-                },
-                BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&compute_texture.view),
-                },
-            ],
+        let palette_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Palette buffer"),
+            contents: bytemuck::cast_slice(&rule.palette().to_buffer()),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
         });
 
+        let texture_bind_group = create_texture_bind_group(
+            &device,
+            &texture_bind_group_layout,
+            &presentation_texture,
+            &compute_texture,
+            &palette_buffer,
+        );
+
         let camera_uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
             label: None,
             contents: bytemuck::cast_slice(&[camera]),
@@ -246,17 +399,186 @@ impl State {
             }],
         });
 
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("automata/gol.wgsl").into()),
-        });
-
         let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: Some("Pipeline layout"),
             bind_group_layouts: &[&texture_bind_group_layout, &camera_bind_group_layout],
             push_constant_ranges: &[],
         });
 
+        let rule_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Rule bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let ants_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Ants buffer"),
+            size: (MAX_ANTS as usize * std::mem::size_of::<Ant>()) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Ants are moved by the compute pass, and only read when drawing them.
+        let agent_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Agent bind group layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let agent_render_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Agent render bind group layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let agent_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Agent bind group"),
+            layout: &agent_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: ants_buffer.as_entire_binding(),
+            }],
+        });
+        let agent_render_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Agent render bind group"),
+            layout: &agent_render_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: ants_buffer.as_entire_binding(),
+            }],
+        });
+
+        let compute_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Compute pipeline layout"),
+            bind_group_layouts: &[
+                &texture_bind_group_layout,
+                &camera_bind_group_layout,
+                &rule_bind_group_layout,
+                &agent_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let step_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Step buffer"),
+            contents: bytemuck::cast_slice(&[Step::default()]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let rule_bind_group =
+            create_rule_bind_group(&device, &rule_bind_group_layout, &rule, &step_buffer);
+
+        // Stochastic and asynchronous updates run as a pass of their own after
+        // the rule, choosing between the board before and after it.
+        let previous_texture =
+            Texture::from_image(&device, &queue, &world.to_image(), Some("Previous texture"))
+                .unwrap();
+        let update_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Update buffer"),
+            contents: bytemuck::cast_slice(&[update.uniform(0)]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let board_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let update_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Update bind group layout"),
+                entries: &[
+                    board_entry(0),
+                    board_entry(1),
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::Rgba8Unorm,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let update_bind_group = create_update_bind_group(
+            &device,
+            &update_bind_group_layout,
+            &previous_texture,
+            &compute_texture,
+            &presentation_texture,
+            &update_buffer,
+        );
+        let update_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Update shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("automata/update.wgsl").into()),
+        });
+        let update_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Update pipeline layout"),
+            bind_group_layouts: &[&update_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let update_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Update"),
+            layout: Some(&update_pipeline_layout),
+            module: &update_shader,
+            entry_point: Some("cm_update"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
         // let storage_buffer = device.create_buffer(&BufferDescriptor {
         //     label: Some("test"),
         //     size: 10,
@@ -276,18 +598,40 @@ impl State {
             usage: BufferUsages::INDEX,
         });
 
-        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: None,
-            layout: Some(&render_pipeline_layout),
+        let render_pipeline = create_render_pipeline(
+            &device,
+            &render_pipeline_layout,
+            surface_format,
+            rule.lattice(),
+        );
+
+        let agent_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Agent shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("automata/agents.wgsl").into()),
+        });
+
+        let agent_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Agent pipeline layout"),
+            bind_group_layouts: &[
+                &texture_bind_group_layout,
+                &camera_bind_group_layout,
+                &agent_render_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let agent_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Agent pipeline"),
+            layout: Some(&agent_pipeline_layout),
             vertex: VertexState {
-                module: &shader,
-                entry_point: Some("vs_main"),
+                module: &agent_shader,
+                entry_point: Some("vs_agent"),
                 compilation_options: Default::default(),
-                buffers: &[Vertex::desc()],
+                buffers: &[],
             },
             fragment: Some(FragmentState {
-                module: &shader,
-                entry_point: Some("fs_main"),
+                module: &agent_shader,
+                entry_point: Some("fs_agent"),
                 compilation_options: Default::default(),
                 targets: &[Some(ColorTargetState {
                     format: surface_format,
@@ -297,33 +641,64 @@ impl State {
             }),
             primitive: PrimitiveState {
                 topology: PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                unclipped_depth: false,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                conservative: false,
+                cull_mode: None,
+                ..Default::default()
             },
             depth_stencil: None,
-            multisample: MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
+            multisample: MultisampleState::default(),
             multiview: None,
             cache: None,
         });
 
-        let compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
-            label: Some("Automota"),
-            layout: Some(&render_pipeline_layout),
-            module: &shader,
-            entry_point: None,
-            compilation_options: Default::default(),
-            cache: None,
+        // 3D, continuous and sandpile rules don't fit the board's layout, they
+        // get their own pipeline along with the volume, field or pile.
+        let compute_pipeline = create_compute_pipeline(
+            &device,
+            &compute_pipeline_layout,
+            if rule.runs_on_board() { &rule } else { &Rule::Life },
+        );
+        let volume = rule.is_volume().then(|| {
+            Volume::new(
+                &device,
+                &queue,
+                surface_format,
+                &palette_buffer,
+                &rule_bind_group_layout,
+                &rule,
+                seed(),
+            )
+        });
+        let field = rule.is_continuous().then(|| {
+            Field::new(
+                &device,
+                &queue,
+                surface_format,
+                &palette_buffer,
+                &camera_bind_group_layout,
+                &rule_bind_group_layout,
+                &rule,
+                world.width,
+                world.height,
+                &Field::soup(&rule, world.width, world.height, seed()),
+            )
         });
+        let grains = rule.is_sandpile().then(|| {
+            Grains::new(
+                &device,
+                &queue,
+                surface_format,
+                &palette_buffer,
+                &camera_bind_group_layout,
+                &rule_bind_group_layout,
+                &rule,
+                world.width,
+                world.height,
+                &Grains::start(&rule, world.width, world.height),
+            )
+        });
+        let texture_limit = device.limits().max_texture_dimension_2d;
 
-        return State {
+        let mut state = State {
             surface,
             device,
             queue,
@@ -331,11 +706,12 @@ impl State {
             size,
             window,
             render_pipeline,
+            render_pipeline_layout,
+            lattice: rule.lattice(),
             render_state: RenderState::Default,
             vertex_buffer,
             index_buffer,
             position: PhysicalPosition::new(0.0, 0.0),
-            num_verticies: VERTICES.len() as u32,
             texture_bind_group,
             texture_bind_group_layout,
             presentation_texture,
@@ -344,146 +720,1351 @@ impl State {
             camera,
             camera_controller,
             camera_bind_group,
+            camera_bind_group_layout,
             camera_uniform_buffer,
 
             compute_pipeline,
+            compute_pipeline_layout,
             is_mouse_pressed: false,
+            brush: 1,
+            is_painting: false,
+            modifiers: ModifiersState::empty(),
+            last_painted: None,
+            running: false,
+
+            rule,
+            rule_bind_group,
+            rule_bind_group_layout,
+            step_buffer,
+            generation: 0,
+            reversed: false,
+            update,
+            update_buffer,
+            update_bind_group,
+            update_bind_group_layout,
+            update_pipeline,
+            previous_texture,
+            palette_buffer,
+
+            ants_buffer,
+            ant_count: 0,
+            agent_bind_group,
+            agent_render_bind_group,
+            agent_pipeline,
+
+            volume,
+            field,
+            grains,
+            hashlife: None,
+            tiles: None,
+            packed: None,
+            sections: None,
+            texture_limit,
+            world_settings: NewWorld {
+                width: world.width,
+                height: world.height,
+                ..Default::default()
+            },
+            import,
+            recording,
+            recorder: None,
+            encodings: Vec::new(),
+            view_size,
+            typing: None,
         };
+        state.reset_ants();
+        return state;
     }
 
-    pub fn update(&mut self) {
-        self.camera_controller.update_camera(&mut self.camera);
-        self.queue.write_buffer(
-            &self.camera_uniform_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera]),
+    /// Replaces the board with `world`, resizing the textures to match.
+    pub fn set_world(&mut self, world: &World) -> anyhow::Result<()> {
+        // Packed boards stay packed, and boards too large for a texture are
+        // packed when the rule can run on them.
+        let limit = self.texture_limit;
+        let large = world.width > limit || world.height > limit;
+        return self.load_world(world, self.packed.is_some() || large);
+    }
+
+    /// Replaces the board with `world`, packed a bit per cell if `pack` and
+    /// the rule can run on a packed board that size.
+    fn load_world(&mut self, world: &World, pack: bool) -> anyhow::Result<()> {
+        let conditions = self.rule.life_like().filter(|_| {
+            pack && self.update.is_synchronous() && self.can_pack(world.width, world.height)
+        });
+        // Other boards too large for a texture are split into sections. Both
+        // leave the board's own textures a single cell.
+        let limit = self.texture_limit;
+        let large = conditions.is_none() && (world.width > limit || world.height > limit);
+        if large && (!self.rule.can_split() || world.lattice != Lattice::Square) {
+            anyhow::bail!(
+                "{} can't run on a {}x{} board, larger than the {limit} cells a texture can hold",
+                self.rule,
+                world.width,
+                world.height
+            );
+        }
+        if large && !self.update.is_synchronous() {
+            anyhow::bail!(
+                "Boards split into sections update every cell each generation, not with {}",
+                self.update
+            );
+        }
+        self.set_lattice(world.lattice);
+        let img = match large || conditions.is_some() {
+            true => World::new(1, 1).to_image(),
+            false => world.to_image(),
+        };
+        let presentation_texture =
+            Texture::from_image(&self.device, &self.queue, &img, Some("Presentation texture"))?;
+        let compute_texture =
+            Texture::from_image(&self.device, &self.queue, &img, Some("Compute texture"))?;
+        let previous_texture =
+            Texture::from_image(&self.device, &self.queue, &img, Some("Previous texture"))?;
+
+        self.texture_bind_group = create_texture_bind_group(
+            &self.device,
+            &self.texture_bind_group_layout,
+            &presentation_texture,
+            &compute_texture,
+            &self.palette_buffer,
         );
+        self.update_bind_group = create_update_bind_group(
+            &self.device,
+            &self.update_bind_group_layout,
+            &previous_texture,
+            &compute_texture,
+            &presentation_texture,
+            &self.update_buffer,
+        );
+        self.compute_texture = compute_texture;
+        self.presentation_texture = presentation_texture;
+        self.previous_texture = previous_texture;
+        self.generation = 0;
+        self.hashlife = None;
+        self.tiles = None;
+        self.packed = conditions.map(|conditions| {
+            Packed::new(
+                &self.device,
+                self.config.format,
+                &self.palette_buffer,
+                &self.camera_bind_group_layout,
+                conditions,
+                world,
+            )
+        });
+        if let Some(packed) = &self.packed {
+            info!("Packed the {}x{} board a bit per cell", packed.width, packed.height);
+        }
+        self.sections = large.then(|| {
+            Sections::new(
+                &self.device,
+                &self.queue,
+                self.config.format,
+                &self.palette_buffer,
+                &self.texture_bind_group_layout,
+                &self.camera_bind_group_layout,
+                world,
+                limit,
+            )
+        });
+        if let Some(sections) = &self.sections {
+            info!(
+                "Split the {}x{} board into {} sections",
+                world.width,
+                world.height,
+                sections.count()
+            );
+        }
+        self.reset_ants();
+        if self.rule.is_continuous() {
+            let values = Field::from_world(&self.rule, world);
+            self.field = Some(self.create_field(&self.rule, world.width, world.height, &values));
+        }
+        if self.rule.is_sandpile() {
+            let grains = Grains::from_world(world);
+            let mut pile = self.create_grains(&self.rule, world.width, world.height, &grains);
+            // The loaded pile is the one to stabilise, not the identity.
+            pile.building_identity = false;
+            self.grains = Some(pile);
+        }
+
+        return Ok(());
     }
 
-    pub fn run_compute(&mut self) {
-        let mut encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("Compute"),
-            });
+    /// Replaces the board with a world made from `settings`, logged so it
+    /// can be made again.
+    pub fn create_world(&mut self, settings: NewWorld) -> anyhow::Result<()> {
+        let mut world = settings.create(self.rule.states());
+        world.lattice = self.lattice;
+        self.set_world(&world)?;
+        info!("Created a world with {settings}");
+        self.world_settings = settings;
+        return Ok(());
+    }
 
-        {
-            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
-                label: Some("Compute pass"),
-                timestamp_writes: None,
-            });
-            compute_pass.set_bind_group(0, &self.texture_bind_group, &[]);
-            compute_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-            compute_pass.set_pipeline(&self.compute_pipeline);
+    /// Edits the new world settings being typed, creating the world on
+    /// `Enter` and giving up on `Escape`.
+    fn type_key(&mut self, event: &KeyEvent) {
+        let Some(typing) = &mut self.typing else {
+            return;
+        };
+        match event.physical_key {
+            PhysicalKey::Code(KeyCode::Enter | KeyCode::NumpadEnter) => {
+                let text = self.typing.take().unwrap();
+                match NewWorld::parse_with_rule(&text, seed) {
+                    Result::Ok((settings, rule)) => {
+                        let created = match rule {
+                            Some(rule) => self.set_rule(rule),
+                            None => Ok(()),
+                        };
+                        if let Err(e) = created.and_then(|_| self.create_world(settings)) {
+                            error!("Could not create a world: {e:#}");
+                        }
+                    }
+                    Err(e) => error!("Could not read the new world's settings: {e:#}"),
+                }
+            }
+            PhysicalKey::Code(KeyCode::Escape) => self.typing = None,
+            PhysicalKey::Code(KeyCode::Backspace) => {
+                typing.pop();
+            }
+            _ => {
+                let text = event.text.as_deref().unwrap_or_default();
+                typing.extend(text.chars().filter(|c| !c.is_control()));
+            }
+        }
+        self.show_typing();
+    }
 
-            compute_pass.dispatch_workgroups(
-                self.presentation_texture.size.width,
-                self.presentation_texture.size.height,
-                1,
-            );
+    fn show_typing(&self) {
+        if let Some(window) = &self.window {
+            match &self.typing {
+                Some(typing) => window.set_title(&format!("New world: {typing}_")),
+                None => window.set_title(TITLE),
+            }
         }
+    }
 
-        encoder.copy_texture_to_texture(
-            wgpu::TexelCopyTextureInfoBase {
-                texture: &self.compute_texture.texture,
-                mip_level: 0,
-                origin: Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            wgpu::TexelCopyTextureInfoBase {
+    /// The cell under a point on the window, if there is one. Every point is
+    /// over a cell of a HashLife universe or an unbounded world of tiles.
+    pub fn cell_at(&self, position: PhysicalPosition<f64>) -> Option<(i64, i64)> {
+        let (u, v) = self.camera.to_board(position, self.size);
+        let (width, height) = self.board_size();
+        if self.hashlife.is_some() || self.tiles.is_some() {
+            return Some(self.lattice.cell(u, v, width, height));
+        }
+        let (x, y) = self.lattice.cell_at(u, v, width, height)?;
+        return Some((x as i64, y as i64));
+    }
+
+    /// Size of the board in cells, which can be larger than its textures.
+    fn board_size(&self) -> (u32, u32) {
+        if let Some(packed) = &self.packed {
+            return (packed.width, packed.height);
+        }
+        if let Some(sections) = &self.sections {
+            return (sections.width, sections.height);
+        }
+        let size = self.presentation_texture.size;
+        return (size.width, size.height);
+    }
+
+    /// Sets the cell at `(x, y)` to `state`, or drops the rule's grains on it
+    /// if it is a sandpile. Only unbounded worlds have cells off the board.
+    pub fn paint(&mut self, x: i64, y: i64, state: u8) {
+        if let Some(hashlife) = &mut self.hashlife {
+            hashlife.set(x, y, state != 0);
+            return;
+        }
+        if let Some(tiles) = &mut self.tiles {
+            tiles.paint(&self.queue, x, y, state);
+            return;
+        }
+        let (x, y) = (x as u32, y as u32);
+        if let Some(packed) = &mut self.packed {
+            packed.paint(x, y, state != 0);
+            return;
+        }
+        if let Some(sections) = &self.sections {
+            sections.paint(&self.queue, x, y, state);
+            return;
+        }
+        if let (Some(grains), Rule::Sandpile(rule)) = (&mut self.grains, &self.rule) {
+            grains.drop(x, y, rule.drop);
+            return;
+        }
+        if let Some(field) = &self.field {
+            let value = f32::from(state.min(1));
+            field.paint(&self.queue, x, y, Field::cell(&self.rule, value));
+            return;
+        }
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
                 texture: &self.presentation_texture.texture,
                 mip_level: 0,
-                origin: Origin3d::ZERO,
+                origin: Origin3d { x, y, z: 0 },
                 aspect: wgpu::TextureAspect::All,
             },
+            &[state, 0, 0, 255],
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4),
+                rows_per_image: Some(1),
+            },
             Extent3d {
-                width: self.presentation_texture.size.width,
-                height: self.presentation_texture.size.height,
+                width: 1,
+                height: 1,
                 depth_or_array_layers: 1,
             },
         );
-
-        self.queue.submit(std::iter::once(encoder.finish()));
     }
 
-    pub fn render(&mut self) -> Result<(), SurfaceError> {
-        if !self.is_surface_configured {
-            return Ok(());
+    /// Paints the brush state along the line from the last painted cell, so
+    /// fast strokes don't leave gaps.
+    fn paint_stroke(&mut self, to: (i64, i64)) {
+        let from = self.last_painted.unwrap_or(to);
+        let steps = from.0.abs_diff(to.0).max(from.1.abs_diff(to.1)).max(1);
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let x = from.0 as f64 + (to.0 as f64 - from.0 as f64) * t;
+            let y = from.1 as f64 + (to.1 as f64 - from.1 as f64) * t;
+            self.paint(x.round() as i64, y.round() as i64, self.brush);
         }
-        let output = self.surface.get_current_texture()?;
-
-        let view = output
-            .texture
-            .create_view(&TextureViewDescriptor::default());
-        let mut encoder = self
-            .device
-            .create_command_encoder(&CommandEncoderDescriptor {
-                label: Some("Renderer"),
-            });
-
-        {
-            let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
-                label: Some("Render pass"),
-                color_attachments: &[Some(RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0,
-                            g: 0.0,
-                            b: 0.0,
-                            a: 0.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                    depth_slice: None,
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
-
-            match self.render_state {
-                RenderState::Default => {
-                    render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
-                    render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-                    render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-                    render_pass
-                        .set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-                    render_pass.set_pipeline(&self.render_pipeline);
-                    // render_pass.draw(0..self.num_verticies, 0..1);
-                    render_pass.draw_indexed(0..INDICES.len() as u32, 0, 0..1);
-                }
-                RenderState::ColourPass => {}
-                RenderState::ComplexObject => {}
-            }
+        self.last_painted = Some(to);
+        if let Some(packed) = &mut self.packed {
+            let mut encoder = self
+                .device
+                .create_command_encoder(&CommandEncoderDescriptor {
+                    label: Some("Packed paint"),
+                });
+            packed.apply_edits(&self.device, &mut encoder);
+            self.queue.submit(std::iter::once(encoder.finish()));
         }
-
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
-
-        return Ok(());
-    }
-
-    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+        // Dropped grains only land when the pile steps, so run it until it is
+        // stable again.
+        if self.grains.is_some() {
+            self.running = true;
         }
-        self.is_surface_configured = true;
     }
 
-    pub fn window(&self) -> Arc<Window> {
-        return self.window.clone();
+    /// Switches the tiling the board is drawn and edited with, the cells stay
+    /// where they are in the texture.
+    pub fn set_lattice(&mut self, lattice: Lattice) {
+        if lattice == self.lattice {
+            return;
+        }
+        self.render_pipeline = create_render_pipeline(
+            &self.device,
+            &self.render_pipeline_layout,
+            self.config.format,
+            lattice,
+        );
+        self.lattice = lattice;
     }
 
-    pub fn input(&mut self, window_event: &WindowEvent) -> bool {
-        match window_event {
+    /// Switches to `rule`, moving the board onto the lattice the rule is
+    /// defined on. Rules the cells can't be updated with are refused,
+    /// keeping the current one.
+    pub fn set_rule(&mut self, rule: Rule) -> anyhow::Result<()> {
+        self.update.check(&rule)?;
+        if self.hashlife.is_some() {
+            self.toggle_hashlife()?;
+        }
+        if let Some(tiles) = self.tiles.take() {
+            self.copy_tiles_to_board(&tiles);
+        }
+        // Packed boards stay packed for other rules that can run on them.
+        match (&self.packed, rule.life_like()) {
+            (Some(packed), Some(conditions)) => packed.set_conditions(&self.queue, conditions),
+            (Some(_), None) => self.toggle_packed()?,
+            (None, _) => {}
+        }
+        if self.sections.is_some() && !rule.can_split() {
+            self.crop_sections(&rule)?;
+        }
+        self.set_lattice(rule.lattice());
+        self.rule_bind_group = create_rule_bind_group(
+            &self.device,
+            &self.rule_bind_group_layout,
+            &rule,
+            &self.step_buffer,
+        );
+        self.volume = None;
+        self.field = None;
+        self.grains = None;
+        if rule.is_sandpile() {
+            let size = self.presentation_texture.size;
+            let grains = Grains::start(&rule, size.width, size.height);
+            self.grains = Some(self.create_grains(&rule, size.width, size.height, &grains));
+        } else if rule.is_continuous() {
+            let size = self.presentation_texture.size;
+            let values = Field::soup(&rule, size.width, size.height, seed());
+            self.field = Some(self.create_field(&rule, size.width, size.height, &values));
+        } else if rule.is_volume() {
+            self.volume = Some(Volume::new(
+                &self.device,
+                &self.queue,
+                self.config.format,
+                &self.palette_buffer,
+                &self.rule_bind_group_layout,
+                &rule,
+                seed(),
+            ));
+        } else {
+            self.compute_pipeline =
+                create_compute_pipeline(&self.device, &self.compute_pipeline_layout, &rule);
+        }
+        self.queue.write_buffer(
+            &self.palette_buffer,
+            0,
+            bytemuck::cast_slice(&rule.palette().to_buffer()),
+        );
+        self.rule = rule;
+        self.reversed = false;
+        self.generation = 0;
+        self.reset_ants();
+        // Diagrams start from a single live cell in the top row, not from
+        // whatever the old board had there.
+        if self.rule.is_one_dimensional() {
+            let (width, height) = self.board_size();
+            let mut world = World::new(width, height);
+            world.set(width / 2, 0, 1);
+            self.set_world(&world)?;
+        }
+        return Ok(());
+    }
+
+    /// Replaces a board split into sections with as much of it as fits in a
+    /// single texture, for `rule` which can't be run on sections.
+    fn crop_sections(&mut self, rule: &Rule) -> anyhow::Result<()> {
+        let world = self.read_world()?;
+        let limit = self.texture_limit;
+        let mut cropped = World::new(world.width.min(limit), world.height.min(limit));
+        cropped.paste(&world, 0, 0);
+        warn!(
+            "Cropping the {}x{} board to {}x{}, {rule} can only run on a single texture",
+            world.width, world.height, cropped.width, cropped.height
+        );
+        return self.set_world(&cropped);
+    }
+
+    /// Moves the board into a HashLife universe, or back onto the board with
+    /// the part of the universe over it.
+    pub fn toggle_hashlife(&mut self) -> anyhow::Result<()> {
+        if let Some(hashlife) = self.hashlife.take() {
+            let world = self.hashlife_board(&hashlife);
+            self.set_world(&world)?;
+            self.generation = hashlife.generation;
+            info!("Left HashLife at generation {}", hashlife.generation);
+            return Ok(());
+        }
+
+        let table = self.rule.moore_table().filter(|table| !table[0]);
+        let Some(table) = table.filter(|_| self.update.is_synchronous()) else {
+            anyhow::bail!("{} can't be run with HashLife", self.rule);
+        };
+        if let Some(tiles) = self.tiles.take() {
+            self.copy_tiles_to_board(&tiles);
+        }
+        if self.packed.is_some() {
+            self.toggle_packed()?;
+        }
+        if self.sections.is_some() {
+            anyhow::bail!("The board is too large to run with HashLife");
+        }
+        let mut hashlife = HashLife::new(table, &self.read_world()?);
+        hashlife.generation = self.generation;
+        info!("Running {} with HashLife, 2^{} generations a step", self.rule, hashlife.step);
+        self.hashlife = Some(hashlife);
+        return Ok(());
+    }
+
+    /// Moves the board into an unbounded world of tiles, or back onto the
+    /// board with the tiles over it.
+    pub fn toggle_tiles(&mut self) -> anyhow::Result<()> {
+        if let Some(tiles) = self.tiles.take() {
+            self.copy_tiles_to_board(&tiles);
+            info!("Back on the board at generation {}", self.generation);
+            return Ok(());
+        }
+
+        if !self.rule.can_tile() || !self.update.is_synchronous() {
+            anyhow::bail!("{} can't be run in an unbounded world", self.rule);
+        }
+        if self.hashlife.is_some() {
+            self.toggle_hashlife()?;
+        }
+        if self.packed.is_some() {
+            self.toggle_packed()?;
+        }
+        if self.sections.is_some() {
+            anyhow::bail!("The board is too large to split into tiles");
+        }
+        let tiles = Tiles::new(
+            &self.device,
+            &self.queue,
+            self.config.format,
+            &self.palette_buffer,
+            &self.texture_bind_group_layout,
+            &self.camera_bind_group_layout,
+            &self.read_world()?,
+        );
+        info!("Running {} in an unbounded world of {} tiles", self.rule, tiles.live());
+        self.tiles = Some(tiles);
+        return Ok(());
+    }
+
+    /// Packs the board a bit per cell, or unpacks it back onto the board.
+    pub fn toggle_packed(&mut self) -> anyhow::Result<()> {
+        let generation = self.generation;
+        if self.packed.is_some() {
+            let world = self.read_world()?;
+            self.load_world(&world, false)?;
+            self.generation = generation;
+            info!("Unpacked the board at generation {generation}");
+            return Ok(());
+        }
+
+        if self.rule.life_like().is_none() || !self.update.is_synchronous() {
+            anyhow::bail!("{} can't be run on a packed board", self.rule);
+        }
+        if self.hashlife.is_some() {
+            self.toggle_hashlife()?;
+        }
+        if let Some(tiles) = self.tiles.take() {
+            self.copy_tiles_to_board(&tiles);
+        }
+        let (width, height) = self.board_size();
+        if !self.can_pack(width, height) {
+            anyhow::bail!("The {width}x{height} board is too large to pack");
+        }
+        let world = self.read_world()?;
+        self.load_world(&world, true)?;
+        self.generation = generation;
+        return Ok(());
+    }
+
+    /// Whether a board of `width` by `height` cells fits in a storage buffer
+    /// packed a bit per cell.
+    fn can_pack(&self, width: u32, height: u32) -> bool {
+        let bytes = width.div_ceil(32) as u64 * height as u64 * 4;
+        return bytes <= self.device.limits().max_storage_buffer_binding_size as u64;
+    }
+
+    /// Replaces the board with the tiles over it.
+    fn copy_tiles_to_board(&self, tiles: &Tiles) {
+        let size = self.presentation_texture.size;
+        let empty = World::new(size.width, size.height).to_image().to_rgba8();
+        self.queue.write_texture(
+            self.presentation_texture.texture.as_image_copy(),
+            &empty,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size.width),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        );
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Tiles to board"),
+            });
+        tiles.copy_to_board(&mut encoder, &self.presentation_texture);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// The part of the HashLife universe over the board.
+    fn hashlife_board(&self, hashlife: &HashLife) -> World {
+        let size = self.presentation_texture.size;
+        let mut world = hashlife.rasterize(0.0, 0.0, (1.0, 1.0), size.width, size.height);
+        world.lattice = self.lattice;
+        return world;
+    }
+
+    /// Draws the part of the HashLife universe the camera is looking at onto
+    /// the board, which is then drawn over the whole window.
+    fn draw_hashlife(&self, hashlife: &HashLife) {
+        let size = self.presentation_texture.size;
+        let (left, top) = self.camera.to_board(PhysicalPosition::new(0.0, 0.0), self.size);
+        let (right, bottom) = self.camera.to_board(
+            PhysicalPosition::new(self.size.width as f64, self.size.height as f64),
+            self.size,
+        );
+        let world = hashlife.rasterize(
+            left as f64 * size.width as f64,
+            top as f64 * size.height as f64,
+            ((right - left) as f64, (bottom - top) as f64),
+            size.width,
+            size.height,
+        );
+        self.queue.write_texture(
+            self.presentation_texture.texture.as_image_copy(),
+            &world.to_image().to_rgba8(),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * size.width),
+                rows_per_image: Some(size.height),
+            },
+            size,
+        );
+
+        let window = Camera {
+            scale: 2.0,
+            x: 0.0,
+            y: 0.0,
+        };
+        self.queue
+            .write_buffer(&self.camera_uniform_buffer, 0, bytemuck::cast_slice(&[window]));
+    }
+
+    fn create_field(&self, rule: &Rule, width: u32, height: u32, values: &[[f32; 2]]) -> Field {
+        return Field::new(
+            &self.device,
+            &self.queue,
+            self.config.format,
+            &self.palette_buffer,
+            &self.camera_bind_group_layout,
+            &self.rule_bind_group_layout,
+            rule,
+            width,
+            height,
+            values,
+        );
+    }
+
+    fn create_grains(&self, rule: &Rule, width: u32, height: u32, grains: &[u32]) -> Grains {
+        return Grains::new(
+            &self.device,
+            &self.queue,
+            self.config.format,
+            &self.palette_buffer,
+            &self.camera_bind_group_layout,
+            &self.rule_bind_group_layout,
+            rule,
+            width,
+            height,
+            grains,
+        );
+    }
+
+    /// Starts the rule's agents over with a single ant in the middle of the
+    /// board, heading north.
+    fn reset_ants(&mut self) {
+        self.ant_count = 0;
+        if self.rule.has_agents() {
+            let (width, height) = self.board_size();
+            self.add_ant(width / 2, height / 2);
+        }
+    }
+
+    /// Puts another ant on the board at `(x, y)`, heading north.
+    pub fn add_ant(&mut self, x: u32, y: u32) {
+        if self.ant_count >= MAX_ANTS {
+            return;
+        }
+        // Only the new ant is written, the rest have moved on the GPU since
+        // they were placed.
+        let ant = Ant {
+            x,
+            y,
+            ..Default::default()
+        };
+        self.queue.write_buffer(
+            &self.ants_buffer,
+            (self.ant_count as usize * std::mem::size_of::<Ant>()) as u64,
+            bytemuck::cast_slice(&[ant]),
+        );
+        self.ant_count += 1;
+    }
+
+    /// Copies the board back from the GPU, blocking until it is available.
+    pub fn read_world(&self) -> anyhow::Result<World> {
+        if let Some(packed) = &self.packed {
+            let mut world = packed.read_world(&self.device, &self.queue)?;
+            world.lattice = self.lattice;
+            return Ok(world);
+        }
+        if let Some(sections) = &self.sections {
+            return sections.read_world(&self.device, &self.queue);
+        }
+        let size = self.presentation_texture.size;
+        let cells = self
+            .read_texture(&self.presentation_texture.texture, size)?
+            .chunks(4)
+            .map(|pixel| pixel[0])
+            .collect();
+
+        return Ok(World {
+            width: size.width,
+            height: size.height,
+            lattice: self.lattice,
+            cells,
+        });
+    }
+
+    /// Copies the texels of a texture with 4 bytes a texel back from the GPU,
+    /// row after row, blocking until they are available.
+    fn read_texture(&self, texture: &wgpu::Texture, size: Extent3d) -> anyhow::Result<Vec<u8>> {
+        let bytes_per_row = (4 * size.width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback buffer"),
+            size: (bytes_per_row * size.height) as u64,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Readback"),
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(size.height),
+                },
+            },
+            size,
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely())?;
+        receiver.recv()??;
+
+        let data = slice.get_mapped_range();
+        return Ok(data
+            .chunks(bytes_per_row as usize)
+            .flat_map(|row| &row[..4 * size.width as usize])
+            .copied()
+            .collect());
+    }
+
+    /// Switches how cells take on the states the rule gives them.
+    pub fn set_update(&mut self, update: Update) -> anyhow::Result<()> {
+        if !update.is_synchronous() {
+            let stepped_whole = [
+                (self.hashlife.is_some(), "HashLife updates"),
+                (self.tiles.is_some(), "The unbounded world updates"),
+                (self.packed.is_some(), "Packed boards update"),
+                (self.sections.is_some(), "Boards split into sections update"),
+            ];
+            if let Some((_, which)) = stepped_whole.iter().find(|(on, _)| *on) {
+                anyhow::bail!("{which} every cell each generation, not with {update}");
+            }
+        }
+        update.check(&self.rule)?;
+        info!("Updating cells with {update}");
+        self.update = update;
+        self.reversed &= update.is_synchronous();
+        return Ok(());
+    }
+
+    /// Loads a Golly rule file, an RLE pattern along with its rule, or an
+    /// image onto the board.
+    pub fn load_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        if path.extension().is_some_and(|extension| extension == "rule") {
+            return self.set_rule(Rule::Golly(GollyRule::load(path)?));
+        }
+
+        let bytes = std::fs::read(path)?;
+        if path.extension().is_some_and(|extension| extension == "rle") {
+            let pattern = Pattern::from_rle(std::str::from_utf8(&bytes)?)?;
+            // The pattern's rule and update have to go together before
+            // switching to either.
+            if let Some(rule) = &pattern.rule {
+                pattern.update.unwrap_or(self.update).check(rule)?;
+            }
+            if let Some(rule) = pattern.rule {
+                self.set_rule(rule)?;
+            }
+            // Boards only grow to fit patterns, so one split into sections
+            // stays split.
+            if let Some(update) = pattern.update {
+                self.set_update(update)?;
+            }
+
+            // Keep the current board size when the pattern fits, leaving room
+            // around it to grow otherwise.
+            let (width, height) = self.board_size();
+            let width = width.max(pattern.world.width + 64);
+            let height = height.max(pattern.world.height + 64);
+            let mut world = World::new(width, height);
+            world.lattice = self.lattice;
+            world.paste(
+                &pattern.world,
+                (width - pattern.world.width) / 2,
+                (height - pattern.world.height) / 2,
+            );
+            return self.set_world(&world);
+        }
+
+        // Images are just cells, so keep them on the current lattice.
+        let mut world = self.import.load(&bytes, self.rule.states())?;
+        world.lattice = self.lattice;
+        info!("Imported {} with {}", path.display(), self.import);
+        return self.set_world(&world);
+    }
+
+    /// Writes the board and rule to a timestamped RLE file in the working
+    /// directory.
+    pub fn save_pattern(&self) -> anyhow::Result<()> {
+        let pattern = Pattern {
+            world: self.board_world()?,
+            rule: Some(self.rule.clone()),
+            update: Some(self.update),
+        };
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let path = format!("pattern-{timestamp}.rle");
+        std::fs::write(&path, pattern.to_rle())?;
+        info!("Saved pattern to {path}");
+        return Ok(());
+    }
+
+    /// The cells on the board, including those stepped by HashLife or the
+    /// unbounded world. Rules run on a volume, field or pile of their own
+    /// only draw the board.
+    fn board_world(&self) -> anyhow::Result<World> {
+        if self.volume.is_some() || self.field.is_some() || self.grains.is_some() {
+            anyhow::bail!("{} has no board of cells to save", self.rule);
+        }
+        if let Some(tiles) = &self.tiles {
+            self.copy_tiles_to_board(tiles);
+        }
+        return match &self.hashlife {
+            Some(hashlife) => Ok(self.hashlife_board(hashlife)),
+            None => self.read_world(),
+        };
+    }
+
+    /// Writes the board to a timestamped PNG file in the working directory, a
+    /// pixel per cell in the colours of its states.
+    pub fn save_board(&self) -> anyhow::Result<()> {
+        if self.volume.is_some() || self.field.is_some() || self.grains.is_some() {
+            anyhow::bail!("{} has no board of cells to save, save the view instead", self.rule);
+        }
+        let world = self.board_world()?;
+        let palette = self.rule.palette();
+        let img = RgbaImage::from_fn(world.width, world.height, |x, y| {
+            return Rgba(palette.srgb(world.get(x, y)));
+        });
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let path = format!("board-{timestamp}.png");
+        img.save(&path)?;
+        info!("Saved board to {path}");
+        return Ok(());
+    }
+
+    /// Writes what the window shows, drawn at `width` by `height` pixels, to
+    /// a timestamped PNG file in the working directory.
+    pub fn save_view(&self, width: u32, height: u32) -> anyhow::Result<()> {
+        let format = self.config.format;
+        let bgra = match format.remove_srgb_suffix() {
+            wgpu::TextureFormat::Rgba8Unorm => false,
+            wgpu::TextureFormat::Bgra8Unorm => true,
+            _ => anyhow::bail!("Can't save views drawn in {format:?}"),
+        };
+        let limit = self.device.limits().max_texture_dimension_2d;
+        if width == 0 || height == 0 || width > limit || height > limit {
+            anyhow::bail!("Views are 1 to {limit} pixels a side, got {width}x{height}");
+        }
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("View texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        if let Some(volume) = &self.volume {
+            volume.update_camera(&self.queue, width as f32 / height as f32);
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("View"),
+            });
+        self.draw(&texture.create_view(&TextureViewDescriptor::default()), &mut encoder);
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let mut pixels = self.read_texture(&texture, size)?;
+        for pixel in pixels.chunks_mut(4) {
+            if bgra {
+                pixel.swap(0, 2);
+            }
+            pixel[3] = 255;
+        }
+        let img = RgbaImage::from_raw(width, height, pixels)
+            .ok_or_else(|| anyhow::anyhow!("The view should match its size"))?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let path = format!("view-{timestamp}.png");
+        img.save(&path)?;
+        info!("Saved {width}x{height} view to {path}");
+        return Ok(());
+    }
+
+    pub fn update(&mut self) {
+        if let Some(volume) = &self.volume {
+            let aspect = self.size.width as f32 / self.size.height.max(1) as f32;
+            volume.update_camera(&self.queue, aspect);
+        }
+        self.camera_controller.update_camera(&mut self.camera);
+        self.queue.write_buffer(
+            &self.camera_uniform_buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera]),
+        );
+        if let Some(hashlife) = &self.hashlife {
+            self.draw_hashlife(hashlife);
+        }
+        if let Some(tiles) = &mut self.tiles {
+            let size = self.presentation_texture.size;
+            let (left, top) = self.camera.to_board(PhysicalPosition::new(0.0, 0.0), self.size);
+            let (right, bottom) = self.camera.to_board(
+                PhysicalPosition::new(self.size.width as f64, self.size.height as f64),
+                self.size,
+            );
+            let board = (size.width as f32, size.height as f32);
+            tiles.show(
+                &self.queue,
+                (left * board.0, top * board.1),
+                (right * board.0, bottom * board.1),
+            );
+        }
+    }
+
+    pub fn run_compute(&mut self) {
+        if let Some(hashlife) = &mut self.hashlife {
+            hashlife.run();
+            return;
+        }
+        // Going backwards undoes the generation before this one, with the
+        // phase it was run in.
+        if self.reversed {
+            self.generation = self.generation.wrapping_sub(1);
+        }
+        let step = Step {
+            generation: self.generation as u32,
+            agents: self.ant_count,
+            phase: (self.generation % 2) as u32,
+            reverse: self.reversed as u32,
+        };
+        self.queue
+            .write_buffer(&self.step_buffer, 0, bytemuck::cast_slice(&[step]));
+        if !self.reversed {
+            self.generation = self.generation.wrapping_add(1);
+        }
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Compute"),
+            });
+
+        if let Some(packed) = &self.packed {
+            packed.step(&mut encoder);
+            self.queue.submit(std::iter::once(encoder.finish()));
+            return;
+        }
+        if let Some(sections) = &self.sections {
+            sections.step(
+                &mut encoder,
+                &self.compute_pipeline,
+                &[
+                    &self.camera_bind_group,
+                    &self.rule_bind_group,
+                    &self.agent_bind_group,
+                ],
+                self.rule.workgroup_size(),
+            );
+            self.queue.submit(std::iter::once(encoder.finish()));
+            return;
+        }
+        if let Some(tiles) = &mut self.tiles {
+            if let Err(e) = tiles.collect(&self.device) {
+                error!("Could not read the tiles back: {e:#}");
+            }
+            tiles.step(
+                &mut encoder,
+                &self.queue,
+                &self.compute_pipeline,
+                &[
+                    &self.camera_bind_group,
+                    &self.rule_bind_group,
+                    &self.agent_bind_group,
+                ],
+                self.rule.workgroup_size(),
+            );
+            self.queue.submit(std::iter::once(encoder.finish()));
+            return;
+        }
+        if let Some(volume) = &self.volume {
+            volume.step(&mut encoder, &self.rule_bind_group);
+            self.queue.submit(std::iter::once(encoder.finish()));
+            return;
+        }
+        if let Some(field) = &self.field {
+            field.step(
+                &mut encoder,
+                &self.camera_bind_group,
+                &self.rule_bind_group,
+                self.rule.substeps(),
+            );
+            self.queue.submit(std::iter::once(encoder.finish()));
+            return;
+        }
+        if let Some(grains) = &mut self.grains {
+            grains.step(
+                &self.queue,
+                &mut encoder,
+                &self.camera_bind_group,
+                &self.rule_bind_group,
+                self.rule.substeps(),
+            );
+            self.queue.submit(std::iter::once(encoder.finish()));
+
+            match grains.changed() {
+                Ok(Some(0)) if grains.building_identity => {
+                    let mut encoder = self
+                        .device
+                        .create_command_encoder(&CommandEncoderDescriptor {
+                            label: Some("Complement"),
+                        });
+                    grains.complement(&mut encoder, &self.camera_bind_group, &self.rule_bind_group);
+                    self.queue.submit(std::iter::once(encoder.finish()));
+                    grains.building_identity = false;
+                }
+                Ok(Some(0)) => {
+                    info!("Sandpile stable after {} generations", self.generation);
+                    self.running = false;
+                }
+                Ok(_) => {}
+                Err(e) => error!("Could not read the sandpile back: {e:#}"),
+            }
+            return;
+        }
+
+        let size = Extent3d {
+            width: self.presentation_texture.size.width,
+            height: self.presentation_texture.size.height,
+            depth_or_array_layers: 1,
+        };
+        if self.rule.has_agents() {
+            // Ants only write the cells they are on, so start from a copy of
+            // the board.
+            encoder.copy_texture_to_texture(
+                self.presentation_texture.texture.as_image_copy(),
+                self.compute_texture.texture.as_image_copy(),
+                size,
+            );
+        }
+        // `Update::check` only lets rules stepped cell by cell on the board
+        // have their generation applied by chance.
+        let stochastic = !self.update.is_synchronous();
+        if stochastic {
+            encoder.copy_texture_to_texture(
+                self.presentation_texture.texture.as_image_copy(),
+                self.previous_texture.texture.as_image_copy(),
+                size,
+            );
+        }
+
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Compute pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_bind_group(0, &self.texture_bind_group, &[]);
+            compute_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+            compute_pass.set_bind_group(2, &self.rule_bind_group, &[]);
+            compute_pass.set_bind_group(3, &self.agent_bind_group, &[]);
+            compute_pass.set_pipeline(&self.compute_pipeline);
+
+            let (workgroup_width, workgroup_height) = self.rule.workgroup_size();
+            if self.rule.has_agents() {
+                // A single workgroup moves every ant, in order where they
+                // share a cell.
+                compute_pass.dispatch_workgroups(1, 1, 1);
+            } else {
+                compute_pass.dispatch_workgroups(
+                    size.width.div_ceil(workgroup_width),
+                    size.height.div_ceil(workgroup_height),
+                    1,
+                );
+            }
+        }
+
+        if stochastic {
+            // The update pass writes every cell of the board itself.
+            self.queue.write_buffer(
+                &self.update_buffer,
+                0,
+                bytemuck::cast_slice(&[self.update.uniform(step.generation)]),
+            );
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Update pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_bind_group(0, &self.update_bind_group, &[]);
+            compute_pass.set_pipeline(&self.update_pipeline);
+            compute_pass.dispatch_workgroups(size.width.div_ceil(8), size.height.div_ceil(8), 1);
+        } else {
+            encoder.copy_texture_to_texture(
+                wgpu::TexelCopyTextureInfoBase {
+                    texture: &self.compute_texture.texture,
+                    mip_level: 0,
+                    origin: Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::TexelCopyTextureInfoBase {
+                    texture: &self.presentation_texture.texture,
+                    mip_level: 0,
+                    origin: Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                size,
+            );
+        }
+
+        let mut recorded = false;
+        if let Some(recorder) = &mut self.recorder {
+            if recorder.board == (size.width, size.height) {
+                let texture = &self.presentation_texture.texture;
+                recorded = recorder.capture(&self.device, &mut encoder, texture);
+            } else {
+                warn!("Stopped recording, the board changed size");
+                recorded = true;
+            }
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+        if recorded {
+            self.finish_recording();
+        }
+    }
+
+    /// Starts recording the board with the settings given on the command
+    /// line, capturing it as it is now and after every step of generations.
+    pub fn start_recording(&mut self) -> anyhow::Result<()> {
+        let stepped_elsewhere = self.hashlife.is_some()
+            || self.tiles.is_some()
+            || self.packed.is_some()
+            || self.sections.is_some()
+            || self.volume.is_some()
+            || self.field.is_some()
+            || self.grains.is_some();
+        if stepped_elsewhere {
+            anyhow::bail!("Only boards stepped a cell to a texel can be recorded");
+        }
+        let size = self.presentation_texture.size;
+        let mut recorder = Recorder::new(
+            &self.device,
+            self.recording,
+            &self.rule,
+            (size.width, size.height),
+        )?;
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Recording"),
+            });
+        let texture = &self.presentation_texture.texture;
+        let recorded = recorder.capture(&self.device, &mut encoder, texture);
+        self.queue.submit(std::iter::once(encoder.finish()));
+        if !recorded {
+            self.recorder = Some(recorder);
+        }
+        return Ok(());
+    }
+
+    pub fn is_recording(&self) -> bool {
+        return self.recorder.is_some();
+    }
+
+    /// Stops recording, writing out the frames captured so far.
+    pub fn stop_recording(&mut self) {
+        if self.finish_recording() {
+            info!("Stopped recording");
+        }
+    }
+
+    /// Stops capturing frames, leaving them to be written without waiting on
+    /// them. Returns whether there was a recording.
+    fn finish_recording(&mut self) -> bool {
+        let Some(recorder) = self.recorder.take() else {
+            return false;
+        };
+        self.encodings.retain(|encoding| !encoding.is_finished());
+        self.encodings.extend(recorder.finish());
+        return true;
+    }
+
+    /// Stops recording and waits for every recording to be written, for
+    /// when the program is about to exit.
+    pub fn wait_for_recordings(&mut self) {
+        self.finish_recording();
+        for encoding in self.encodings.drain(..) {
+            let _ = encoding.join();
+        }
+    }
+
+    pub fn render(&mut self) -> Result<(), SurfaceError> {
+        let (Some(surface), true) = (&self.surface, self.is_surface_configured) else {
+            return Ok(());
+        };
+        let output = surface.get_current_texture()?;
+
+        let view = output
+            .texture
+            .create_view(&TextureViewDescriptor::default());
+        let mut encoder = self
+            .device
+            .create_command_encoder(&CommandEncoderDescriptor {
+                label: Some("Renderer"),
+            });
+        self.draw(&view, &mut encoder);
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output.present();
+
+        return Ok(());
+    }
+
+    /// Draws the board, or whatever is stepped in its place, onto `view`.
+    fn draw(&self, view: &wgpu::TextureView, encoder: &mut wgpu::CommandEncoder) {
+        let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Render pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: 0.0,
+                        g: 0.0,
+                        b: 0.0,
+                        a: 0.0,
+                    }),
+                    store: wgpu::StoreOp::Store,
+                },
+                depth_slice: None,
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        match self.render_state {
+            RenderState::Default if self.volume.is_some() => {
+                self.volume.as_ref().unwrap().render(&mut render_pass);
+            }
+            RenderState::Default if self.field.is_some() => {
+                render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                let field = self.field.as_ref().unwrap();
+                field.render(&mut render_pass, INDICES.len() as u32);
+            }
+            RenderState::Default if self.tiles.is_some() => {
+                render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                self.tiles.as_ref().unwrap().render(&mut render_pass);
+            }
+            RenderState::Default if self.packed.is_some() => {
+                render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                let packed = self.packed.as_ref().unwrap();
+                packed.render(&mut render_pass, INDICES.len() as u32);
+            }
+            RenderState::Default if self.sections.is_some() => {
+                render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                self.sections.as_ref().unwrap().render(&mut render_pass);
+            }
+            RenderState::Default if self.grains.is_some() => {
+                render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                let grains = self.grains.as_ref().unwrap();
+                grains.render(&mut render_pass, INDICES.len() as u32);
+            }
+            RenderState::Default => {
+                render_pass.set_bind_group(0, &self.texture_bind_group, &[]);
+                render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+                render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                render_pass
+                    .set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                render_pass.set_pipeline(&self.render_pipeline);
+                // render_pass.draw(0..self.num_verticies, 0..1);
+                render_pass.draw_indexed(0..INDICES.len() as u32, 0, 0..1);
+
+                if self.ant_count > 0 {
+                    render_pass.set_bind_group(2, &self.agent_render_bind_group, &[]);
+                    render_pass.set_pipeline(&self.agent_pipeline);
+                    render_pass.draw(0..3, 0..self.ant_count);
+                }
+            }
+            RenderState::ColourPass => {}
+            RenderState::ComplexObject => {}
+        }
+    }
+
+    pub fn resize(&mut self, new_size: PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.size = new_size;
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            if let Some(surface) = &self.surface {
+                surface.configure(&self.device, &self.config);
+            }
+        }
+        self.is_surface_configured = true;
+    }
+
+    pub fn request_redraw(&self) {
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+    }
+
+    pub fn input(&mut self, window_event: &WindowEvent) -> bool {
+        match window_event {
             // TODO: move all this logic to the camera controller
             WindowEvent::CursorMoved { position, .. } => {
-                if self.is_mouse_pressed {
+                if self.is_painting {
+                    if let Some(cell) = self.cell_at(*position) {
+                        self.paint_stroke(cell);
+                    }
+                } else if let Some(volume) =
+                    self.volume.as_mut().filter(|_| self.is_mouse_pressed)
+                {
+                    volume.camera.rotate(
+                        (position.x - self.position.x) as f32,
+                        (position.y - self.position.y) as f32,
+                    );
+                } else if self.is_mouse_pressed {
                     let delta_x = (position.x - self.position.x) as f32;
                     let delta_y = (position.y - self.position.y) as f32;
 
@@ -498,50 +2079,51 @@ impl State {
                 if *button == MouseButton::Left {
                     self.is_mouse_pressed = state.is_pressed();
                 }
+                if *button == MouseButton::Right {
+                    self.is_painting = state.is_pressed();
+                    self.last_painted = None;
+                    if let Some(cell) = self.cell_at(self.position).filter(|_| self.is_painting) {
+                        self.paint_stroke(cell);
+                    }
+                }
+                if *button == MouseButton::Middle && state.is_pressed() && self.rule.has_agents() {
+                    if let Some((x, y)) = self.cell_at(self.position) {
+                        self.add_ant(x as u32, y as u32);
+                    }
+                }
                 return true;
             }
-            WindowEvent::MouseWheel { delta, .. } => match delta {
-                MouseScrollDelta::LineDelta(_, _) => todo!(),
-                MouseScrollDelta::PixelDelta(physical_position) => {
-                    self.camera.scale += (physical_position.y as f32) / 1000.0;
-                    return true;
+            WindowEvent::MouseWheel { delta, .. } => {
+                // A notch of a mouse wheel zooms as far as scrolling 100
+                // pixels on a touchpad.
+                let pixels = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y * 100.0,
+                    MouseScrollDelta::PixelDelta(physical_position) => physical_position.y as f32,
+                };
+                if let Some(volume) = &mut self.volume {
+                    volume.camera.zoom(pixels / 100.0);
+                } else {
+                    self.camera.scale += pixels / 1000.0;
                 }
-            },
+                return true;
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+                return false;
+            }
             WindowEvent::DroppedFile(path) => {
-                let bytes = std::fs::read(path).unwrap();
-
-                let new_presentation_texture =
-                    Texture::from_bytes(&self.device, &self.queue, &bytes, "Presentation Texture").unwrap();
-                let new_compute_texture =
-                    Texture::from_bytes(&self.device, &self.queue, &bytes, "Compute Texture").unwrap();
-
-                let texture_bind_group = self.device.create_bind_group(&BindGroupDescriptor {
-                    label: None,
-                    layout: &self.texture_bind_group_layout,
-                    entries: &[
-                        BindGroupEntry {
-                            binding: 0,
-                            resource: wgpu::BindingResource::TextureView(
-                                &new_presentation_texture.view,
-                            ),
-                        },
-                        BindGroupEntry {
-                            binding: 1,
-                            resource: wgpu::BindingResource::Sampler(&new_presentation_texture.sampler),
-                        },
-                        BindGroupEntry {
-                            binding: 2,
-                            resource: wgpu::BindingResource::TextureView(&new_compute_texture.view),
-                        },
-                    ],
-                });
-
-                self.texture_bind_group = texture_bind_group;
-                self.compute_texture = new_compute_texture;
-                self.presentation_texture = new_presentation_texture;
+                if let Err(e) = self.load_file(path) {
+                    error!("Could not load {}: {e:#}", path.display());
+                }
 
                 return true;
             }
+            WindowEvent::KeyboardInput { event, .. } if self.typing.is_some() => {
+                if event.state.is_pressed() {
+                    self.type_key(event);
+                }
+                return true;
+            }
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -559,10 +2141,121 @@ impl State {
                     self.run_compute();
                     return true;
                 }
-                (x, y) => self.camera_controller.handle_key(*x, y),
-                _ => {
-                    return false;
+                (KeyCode::Enter, true) => {
+                    self.running = !self.running;
+                    return true;
+                }
+                (KeyCode::KeyB, true) => {
+                    if self.rule.is_reversible() && self.update.is_synchronous() {
+                        self.reversed = !self.reversed;
+                        info!("Running {}", if self.reversed { "backwards" } else { "forwards" });
+                    } else {
+                        info!("{} can't be run backwards", self.rule);
+                    }
+                    return true;
                 }
+                (code, true) if digit(*code).is_some() => {
+                    let state = digit(*code).unwrap();
+                    if (state as u32) < self.rule.states() {
+                        self.brush = state;
+                        match self.rule.state_name(state) {
+                            Some(name) => info!("Painting {name}"),
+                            None => info!("Painting state {state}"),
+                        }
+                    }
+                    return true;
+                }
+                (KeyCode::KeyU, true) => {
+                    if let Err(e) = self.toggle_tiles() {
+                        error!("Could not switch the unbounded world: {e:#}");
+                    }
+                    return true;
+                }
+                (KeyCode::KeyN, true) if self.modifiers.shift_key() => {
+                    // Start from the current settings, with a fresh seed.
+                    let settings = self.world_settings.to_string();
+                    let settings = settings
+                        .rsplit_once(",seed=")
+                        .map_or(settings.as_str(), |(settings, _)| settings);
+                    self.typing = Some(format!("{settings},rule={}", self.rule));
+                    self.show_typing();
+                    return true;
+                }
+                (KeyCode::KeyN, true) => {
+                    let settings = NewWorld {
+                        seed: seed(),
+                        ..self.world_settings
+                    };
+                    if let Err(e) = self.create_world(settings) {
+                        error!("Could not create a world: {e:#}");
+                    }
+                    return true;
+                }
+                (KeyCode::KeyK, true) => {
+                    if let Err(e) = self.toggle_packed() {
+                        error!("Could not switch the packed board: {e:#}");
+                    }
+                    return true;
+                }
+                (KeyCode::KeyH, true) => {
+                    if let Err(e) = self.toggle_hashlife() {
+                        error!("Could not switch HashLife: {e:#}");
+                    }
+                    return true;
+                }
+                (KeyCode::BracketLeft | KeyCode::BracketRight, true)
+                    if self.hashlife.is_some() =>
+                {
+                    let hashlife = self.hashlife.as_mut().unwrap();
+                    hashlife.step = match code {
+                        KeyCode::BracketLeft => hashlife.step.saturating_sub(1),
+                        _ => (hashlife.step + 1).min(MAX_STEP),
+                    };
+                    info!(
+                        "HashLife at generation {} with {} cells, 2^{} generations a step",
+                        hashlife.generation,
+                        hashlife.population(),
+                        hashlife.step
+                    );
+                    return true;
+                }
+                (KeyCode::KeyP, true) => {
+                    if let Err(e) = self.save_pattern() {
+                        error!("Could not save pattern: {e:#}");
+                    }
+                    return true;
+                }
+                (KeyCode::KeyG, true) => {
+                    if self.recorder.is_some() {
+                        self.stop_recording();
+                    } else if let Err(e) = self.start_recording() {
+                        error!("Could not start recording: {e:#}");
+                    }
+                    return true;
+                }
+                (KeyCode::KeyI, true) => {
+                    if let Err(e) = self.save_board() {
+                        error!("Could not save board: {e:#}");
+                    }
+                    return true;
+                }
+                (KeyCode::KeyV, true) => {
+                    // Shift saves the view at four times the size.
+                    let scale = match self.modifiers.shift_key() {
+                        true => 4,
+                        false => 1,
+                    };
+                    let (width, height) = self
+                        .view_size
+                        .unwrap_or((self.size.width, self.size.height));
+                    let (width, height) =
+                        (width.saturating_mul(scale), height.saturating_mul(scale));
+                    if let Err(e) = self.save_view(width, height) {
+                        error!("Could not save view: {e:#}");
+                    }
+                    return true;
+                }
+                (x, y) => self.camera_controller.handle_key(*x, y),
             },
             _ => {
                 return false;
@@ -570,3 +2263,572 @@ impl State {
         }
     }
 }
+
+pub(crate) fn create_texture_bind_group(
+    device: &Device,
+    layout: &wgpu::BindGroupLayout,
+    presentation_texture: &Texture,
+    compute_texture: &Texture,
+    palette_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    return device.create_bind_group(&BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&presentation_texture.view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&presentation_texture.sampler),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&compute_texture.view),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: palette_buffer.as_entire_binding(),
+            },
+        ],
+    });
+}
+
+fn create_update_bind_group(
+    device: &Device,
+    layout: &wgpu::BindGroupLayout,
+    previous_texture: &Texture,
+    compute_texture: &Texture,
+    presentation_texture: &Texture,
+    update_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    return device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Update bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&previous_texture.view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&compute_texture.view),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&presentation_texture.view),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: update_buffer.as_entire_binding(),
+            },
+        ],
+    });
+}
+
+fn create_render_pipeline(
+    device: &Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    lattice: Lattice,
+) -> RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("automata/gol.wgsl").into()),
+    });
+
+    return device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: None,
+        layout: Some(layout),
+        vertex: VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            compilation_options: Default::default(),
+            buffers: &[Vertex::desc()],
+        },
+        fragment: Some(FragmentState {
+            module: &shader,
+            entry_point: Some(lattice.fragment_entry_point()),
+            compilation_options: Default::default(),
+            targets: &[Some(ColorTargetState {
+                format,
+                blend: Some(BlendState::REPLACE),
+                write_mask: ColorWrites::ALL,
+            })],
+        }),
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    });
+}
+
+fn create_rule_bind_group(
+    device: &Device,
+    layout: &wgpu::BindGroupLayout,
+    rule: &Rule,
+    step_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    let buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("Rule buffer"),
+        contents: bytemuck::cast_slice(&rule.params()),
+        usage: BufferUsages::STORAGE,
+    });
+    return device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Rule bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: step_buffer.as_entire_binding(),
+            },
+        ],
+    });
+}
+
+fn create_compute_pipeline(
+    device: &Device,
+    layout: &wgpu::PipelineLayout,
+    rule: &Rule,
+) -> wgpu::ComputePipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("Automata shader"),
+        source: wgpu::ShaderSource::Wgsl(rule.shader().into()),
+    });
+
+    return device.create_compute_pipeline(&ComputePipelineDescriptor {
+        label: Some("Automota"),
+        layout: Some(layout),
+        module: &shader,
+        entry_point: Some("cm_main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+}
+
+fn digit(code: KeyCode) -> Option<u8> {
+    return match code {
+        KeyCode::Digit0 => Some(0),
+        KeyCode::Digit1 => Some(1),
+        KeyCode::Digit2 => Some(2),
+        KeyCode::Digit3 => Some(3),
+        KeyCode::Digit4 => Some(4),
+        KeyCode::Digit5 => Some(5),
+        KeyCode::Digit6 => Some(6),
+        KeyCode::Digit7 => Some(7),
+        KeyCode::Digit8 => Some(8),
+        KeyCode::Digit9 => Some(9),
+        _ => None,
+    };
+}
+
+/// Parses the size views are saved at, written like `3840x2160`.
+pub fn parse_view_size(text: &str) -> anyhow::Result<(u32, u32)> {
+    let size = text.split_once('x').and_then(|(width, height)| {
+        Some((width.trim().parse().ok()?, height.trim().parse().ok()?))
+    });
+    match size {
+        Some((width, height)) if width > 0 && height > 0 => return Ok((width, height)),
+        _ => anyhow::bail!("expected a view size such as 1920x1080, got {text:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::MARGIN;
+
+    /// Runs `test` on a state without a window, one at a time as some
+    /// adapters can only be used from one thread at once.
+    fn headless(rule: &str, test: impl FnOnce(&mut State)) {
+        static GPU: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        let _gpu = GPU.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let rule = Rule::parse(rule).unwrap();
+        let mut state = pollster::block_on(State::new(
+            None,
+            rule,
+            Update::default(),
+            Import::default(),
+            Recording::default(),
+            None,
+        ));
+        test(&mut state);
+    }
+
+    fn alive(world: &World) -> usize {
+        return world.cells.iter().filter(|&&state| state != 0).count();
+    }
+
+    /// Jarzynski and Olano's pcg4d, as `update.wgsl` hashes each cell.
+    fn pcg4d(counters: [u32; 4]) -> [u32; 4] {
+        let mut v = counters.map(|c| c.wrapping_mul(1664525).wrapping_add(1013904223));
+        for round in 0..2 {
+            v[0] = v[0].wrapping_add(v[1].wrapping_mul(v[3]));
+            v[1] = v[1].wrapping_add(v[2].wrapping_mul(v[0]));
+            v[2] = v[2].wrapping_add(v[0].wrapping_mul(v[1]));
+            v[3] = v[3].wrapping_add(v[1].wrapping_mul(v[2]));
+            if round == 0 {
+                v = v.map(|c| c ^ c >> 16);
+            }
+        }
+        return v;
+    }
+
+    #[test]
+    fn update_pass_follows_its_seed() {
+        // Every cell is born or survives, so only the update decides which.
+        headless("B012345678/S012345678", |state| {
+            let update = Update::parse("birth=0.5,survival=0.75,async=0.9,seed=42", || 0).unwrap();
+            state.set_update(update).unwrap();
+            let settings = NewWorld::parse("width=40,height=24,seed=7", || 0).unwrap();
+            let mut world = settings.create(2);
+            state.set_world(&world).unwrap();
+
+            let unit = |bits: u32| (bits >> 8) as f32 / 16777216.0;
+            let seed = [update.seed as u32, (update.seed >> 32) as u32];
+            for generation in 0..3u32 {
+                state.run_compute();
+                for y in 0..world.height {
+                    for x in 0..world.width {
+                        let previous = world.get(x, y);
+                        let random = pcg4d([x, y, generation ^ seed[1], seed[0]]);
+                        // Births happen and survivals stay by chance, failed
+                        // survivals dying.
+                        let chance = match previous {
+                            0 => update.birth,
+                            _ => update.survival,
+                        };
+                        let state = match unit(random[0]) < update.fraction {
+                            true => (unit(random[1]) < chance) as u8,
+                            false => previous,
+                        };
+                        world.set(x, y, state);
+                    }
+                }
+                let cells = state.read_world().unwrap().cells;
+                assert_eq!(cells, world.cells, "generation {generation}");
+            }
+            // Some cells took each way.
+            let live = alive(&world);
+            assert!(live > 0 && live < world.cells.len());
+        });
+    }
+
+    #[test]
+    fn refuses_block_rules_with_chance_updates() {
+        headless("B3/S23", |state| {
+            let update = Update::parse("birth=0.5,seed=1", || 1).unwrap();
+            state.set_update(update).unwrap();
+            let sand = Rule::parse("sand").unwrap();
+            assert!(state.set_rule(sand.clone()).is_err());
+            assert_eq!(state.rule, Rule::Life);
+
+            let path = std::env::temp_dir().join(format!("sand-{}.rle", std::process::id()));
+            std::fs::write(&path, format!("x = 2, y = 1, rule = {sand}\n2o!")).unwrap();
+            let loaded = state.load_file(&path);
+            std::fs::remove_file(&path).unwrap();
+            assert!(loaded.is_err());
+            assert_eq!(state.rule, Rule::Life);
+            assert_eq!(state.update, update);
+        });
+    }
+
+    #[test]
+    fn refuses_chance_updates_where_every_cell_is_stepped() {
+        headless("B3/S23", |state| {
+            let update = Update::parse("async=0.5,seed=1", || 1).unwrap();
+            state.toggle_hashlife().unwrap();
+            assert!(state.set_update(update).is_err());
+            state.toggle_hashlife().unwrap();
+            state.toggle_tiles().unwrap();
+            assert!(state.set_update(update).is_err());
+            state.toggle_tiles().unwrap();
+            state.toggle_packed().unwrap();
+            assert!(state.set_update(update).is_err());
+            state.toggle_packed().unwrap();
+            state.set_update(update).unwrap();
+            assert_eq!(state.update, update);
+        });
+    }
+
+    #[test]
+    fn tiles_are_freed_while_painting() {
+        headless("B3/S23", |state| {
+            let mut world = World::new(64, 64);
+            for x in 30..33 {
+                world.set(x, 32, 1);
+            }
+            state.set_world(&world).unwrap();
+            state.toggle_tiles().unwrap();
+            // Painting between steps leaves every readback older than the
+            // last paint, which only keeps the painted tile.
+            for _ in 0..20 {
+                state.paint(0, 0, 0);
+                state.run_compute();
+                state.device.poll(wgpu::PollType::wait_indefinitely()).unwrap();
+                // The blinker's tile and the tiles around it.
+                assert!(state.tiles.as_ref().unwrap().live() <= 9);
+            }
+            state.toggle_tiles().unwrap();
+            assert_eq!(alive(&state.read_world().unwrap()), 3);
+        });
+    }
+
+    #[test]
+    fn tiles_step_like_the_board() {
+        headless("B3/S23", |state| {
+            // Gliders flying south east and north west across the corners
+            // where four tiles meet.
+            let mut world = World::new(192, 192);
+            for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+                world.set(58 + x, 58 + y, 1);
+                world.set(132 - x, 132 - y, 1);
+            }
+            state.set_world(&world).unwrap();
+            state.toggle_tiles().unwrap();
+            let conditions = state.rule.life_like().unwrap();
+            for _ in 0..48 {
+                state.run_compute();
+                state.device.poll(wgpu::PollType::wait_indefinitely()).unwrap();
+                world = life_like_generation(conditions, &world);
+            }
+            state.toggle_tiles().unwrap();
+            assert_eq!(alive(&world), 10);
+            assert_eq!(state.read_world().unwrap().cells, world.cells);
+        });
+    }
+
+    #[test]
+    fn sections_step_like_the_board() {
+        // Life written as a Larger than Life rule, which isn't packed.
+        headless("R1,C0,M0,S2..3,B3..3,NM", |state| {
+            // Gliders flying south east and north west across the corners
+            // where four sections meet.
+            let mut world = World::new(96, 96);
+            for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+                world.set(28 + x, 28 + y, 1);
+                world.set(68 - x, 68 - y, 1);
+            }
+            let limit = state.texture_limit;
+            let mut boards = Vec::new();
+            for texture_limit in [2 * MARGIN + 32, limit] {
+                state.texture_limit = texture_limit;
+                state.set_world(&world).unwrap();
+                let sections = state.sections.as_ref().map(Sections::count);
+                assert_eq!(sections, (texture_limit < limit).then_some(9));
+                for _ in 0..24 {
+                    state.run_compute();
+                }
+                boards.push(state.read_world().unwrap());
+            }
+            assert_eq!(alive(&boards[1]), 10);
+            assert_eq!(boards[0].cells, boards[1].cells);
+        });
+    }
+
+    #[test]
+    fn only_tiles_rules_that_keep_empty_space_empty() {
+        for rule in ["B03/S23", "R2,C0,M0,S1..2,B0..3,NM", "critters"] {
+            headless(rule, |state| assert!(state.toggle_tiles().is_err(), "{rule}"));
+        }
+    }
+
+    #[test]
+    fn hashlife_keeps_its_generations_and_cells_off_the_board() {
+        headless("B3/S23", |state| {
+            state.set_world(&World::new(16, 16)).unwrap();
+            state.toggle_hashlife().unwrap();
+            state.paint(-40, -3, 1);
+            assert_eq!(state.hashlife.as_ref().unwrap().population(), 1);
+            let generation = (1 << 40) + 1;
+            state.hashlife.as_mut().unwrap().generation = generation;
+            state.toggle_hashlife().unwrap();
+            assert_eq!(state.generation, generation);
+        });
+    }
+
+    #[test]
+    fn sandpiles_stop_once_stable() {
+        headless("sandpile:steps=4,drop=64", |state| {
+            state.set_world(&World::new(16, 16)).unwrap();
+            // Generations run until the pile stops itself.
+            let run = |state: &mut State| {
+                state.running = true;
+                let mut generations = 0;
+                while state.running {
+                    assert!(generations < 1000, "never stable");
+                    state.run_compute();
+                    state.device.poll(wgpu::PollType::wait_indefinitely()).unwrap();
+                    generations += 1;
+                }
+                return generations;
+            };
+            assert!(run(state) <= 2);
+            // 64 grains take more topples than a generation runs.
+            state.paint(8, 8, 1);
+            assert!(run(state) > 4);
+            // A step of the stable pile is still being read back when the
+            // next grains land, and doesn't count.
+            state.run_compute();
+            state.paint(8, 8, 1);
+            assert!(run(state) > 4);
+        });
+    }
+
+    #[test]
+    fn stays_packed_or_tiled_when_hashlife_is_refused() {
+        headless("B03/S23", |state| {
+            state.toggle_packed().unwrap();
+            assert!(state.toggle_hashlife().is_err());
+            assert!(state.packed.is_some());
+        });
+        headless("R2,C0,M0,S1..2,B3..3,NM", |state| {
+            state.toggle_tiles().unwrap();
+            assert!(state.toggle_hashlife().is_err());
+            assert!(state.tiles.is_some());
+        });
+    }
+
+    #[test]
+    fn only_saves_patterns_of_boards_of_cells() {
+        for rule in ["4/4/5/M", "lenia", "smoothlife", "gray-scott", "sandpile"] {
+            headless(rule, |state| assert!(state.save_pattern().is_err(), "{rule}"));
+        }
+    }
+
+    /// `world` after a generation of the rule with the birth and survival
+    /// conditions of `Rule::life_like`, with the cells past its edges dead.
+    fn life_like_generation((birth, survival): (u32, u32), world: &World) -> World {
+        let mut next = World::new(world.width, world.height);
+        for y in 0..world.height {
+            for x in 0..world.width {
+                let mut count = 0;
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                        let inside = (0..world.width as i64).contains(&nx)
+                            && (0..world.height as i64).contains(&ny);
+                        if (dx, dy) != (0, 0) && inside && world.get(nx as u32, ny as u32) != 0 {
+                            count += 1;
+                        }
+                    }
+                }
+                let conditions = match world.get(x, y) {
+                    0 => birth,
+                    _ => survival,
+                };
+                next.set(x, y, (conditions >> count & 1) as u8);
+            }
+        }
+        return next;
+    }
+
+    #[test]
+    fn packed_boards_run_like_the_rule() {
+        for rule in ["B3/S23", "B36/S125", "B2/S", "B1357/S1357"] {
+            headless(rule, |state| {
+                // Rows that end partway through a word, with the soup
+                // reaching every edge.
+                let settings = NewWorld::parse("width=70,height=40,seed=3", || 0).unwrap();
+                let mut world = settings.create(2);
+                state.set_world(&world).unwrap();
+                state.toggle_packed().unwrap();
+                let conditions = state.rule.life_like().unwrap();
+                for generation in 1..=6 {
+                    state.run_compute();
+                    world = life_like_generation(conditions, &world);
+                    let packed = state.packed.as_ref().unwrap();
+                    let cells = packed.read_world(&state.device, &state.queue).unwrap();
+                    assert_eq!(cells.cells, world.cells, "{rule} at {generation}");
+                }
+            });
+        }
+    }
+
+    #[test]
+    fn boards_too_large_for_a_texture_are_packed() {
+        headless("B3/S23", |state| {
+            let limit = state.device.limits().max_texture_dimension_2d;
+            let mut world = World::new(limit + 40, 3);
+            for x in [0, limit, limit + 1, limit + 2, limit + 39] {
+                world.set(x, 1, 1);
+            }
+            state.set_world(&world).unwrap();
+            assert!(state.packed.is_some() && state.sections.is_none());
+            assert_eq!(state.presentation_texture.size.width, 1);
+            state.run_compute();
+            world = life_like_generation(state.rule.life_like().unwrap(), &world);
+            assert_eq!(state.read_world().unwrap().cells, world.cells);
+        });
+    }
+
+    #[test]
+    fn packed_boards_stay_packed() {
+        headless("B3/S23", |state| {
+            state.set_world(&World::new(64, 64)).unwrap();
+            state.toggle_packed().unwrap();
+            let mut world = World::new(40, 20);
+            for x in 10..13 {
+                world.set(x, 10, 1);
+            }
+            state.set_world(&world).unwrap();
+            assert_eq!(state.presentation_texture.size.width, 1);
+            assert_eq!(state.board_size(), (40, 20));
+
+            // Replicator copies the blinker rather than turning it.
+            state.set_rule(Rule::parse("B1357/S1357").unwrap()).unwrap();
+            state.run_compute();
+            world = life_like_generation(state.rule.life_like().unwrap(), &world);
+            assert_eq!(state.read_world().unwrap().cells, world.cells);
+
+            state.set_rule(Rule::parse("B2/S34H").unwrap()).unwrap();
+            assert!(state.packed.is_none());
+            assert_eq!(state.read_world().unwrap().cells, world.cells);
+        });
+    }
+
+    #[test]
+    fn one_dimensional_rules_start_from_a_single_cell() {
+        headless("B3/S23", |state| {
+            let mut world = World::new(16, 8);
+            world.cells.fill(1);
+            state.set_world(&world).unwrap();
+            state.set_rule(Rule::parse("W30").unwrap()).unwrap();
+            let world = state.read_world().unwrap();
+            assert_eq!((world.width, world.height), (16, 8));
+            assert_eq!(alive(&world), 1);
+            assert_eq!(world.get(8, 0), 1);
+            state.run_compute();
+            let world = state.read_world().unwrap();
+            // Rule 30 grows a cell either side of the one above.
+            assert_eq!(alive(&world), 4);
+        });
+    }
+
+    #[test]
+    fn parses_view_sizes() {
+        assert_eq!(parse_view_size("3840x2160").unwrap(), (3840, 2160));
+        assert_eq!(parse_view_size(" 64 x 48 ").unwrap(), (64, 48));
+        assert!(parse_view_size("0x10").is_err());
+        assert!(parse_view_size("1920").is_err());
+        assert!(parse_view_size("ax2").is_err());
+    }
+}
@@ -0,0 +1,424 @@
+use std::sync::mpsc;
+
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BlendState, BufferUsages, ColorTargetState, ColorWrites, CommandEncoder, ComputePassDescriptor,
+    Device, FragmentState, MultisampleState, Origin3d, PipelineLayoutDescriptor, PrimitiveState,
+    Queue, RenderPass, RenderPipelineDescriptor, ShaderStages, TextureViewDimension, VertexState,
+};
+
+use crate::{
+    renderer::create_texture_bind_group,
+    texture::Texture,
+    tiles::{copy_region, tile_extent, write_region, MARGIN},
+    world::World,
+};
+
+/// Where a section is drawn, laid out as `Section` in `sections.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct SectionUniform {
+    origin: [f32; 2],
+    size: [f32; 2],
+    board: [f32; 2],
+    _padding: [f32; 2],
+}
+
+/// A part of the board, kept in a texture of its own with a margin around
+/// it for the cells of the sections next to it.
+struct Section {
+    /// First cell of the section on the board.
+    origin: (u32, u32),
+    /// Cells of the section on the board, fewer than the section's size at
+    /// the right and bottom edges.
+    size: (u32, u32),
+    texture: Texture,
+    compute_bind_group: wgpu::BindGroup,
+    render_bind_group: wgpu::BindGroup,
+}
+
+/// A board too large for a single texture, split into a grid of sections
+/// that each fit in one. Before every generation each section copies the
+/// cells around it from its neighbours into its margin, then the sections
+/// are stepped one at a time with the board's compute pipeline. Sections
+/// are drawn on the board quad with the board's camera.
+pub struct Sections {
+    pub width: u32,
+    pub height: u32,
+    /// Cells along each side of every section, not counting its margin.
+    section_size: (u32, u32),
+    columns: u32,
+    sections: Vec<Section>,
+    /// The rule's generation of a section, copied back into it.
+    output: Texture,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl Sections {
+    /// The cells of `world` split into sections no larger than `limit` cells
+    /// along each side, margin included. Sections are bound with
+    /// `texture_bind_group_layout` for the board's compute pipeline, and the
+    /// board's camera at `@group(1)` when drawing.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        format: wgpu::TextureFormat,
+        palette_buffer: &wgpu::Buffer,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        world: &World,
+        limit: u32,
+    ) -> Sections {
+        let most = limit - 2 * MARGIN;
+        let columns = world.width.div_ceil(most);
+        let rows = world.height.div_ceil(most);
+        let section_size = (world.width.div_ceil(columns), world.height.div_ceil(rows));
+        let padded = (section_size.0 + 2 * MARGIN, section_size.1 + 2 * MARGIN);
+        let output = Texture::from_image(
+            device,
+            queue,
+            &World::new(padded.0, padded.1).to_image(),
+            Some("Section output texture"),
+        )
+        .unwrap();
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Section bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let mut sections = Vec::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let origin = (column * section_size.0, row * section_size.1);
+                let size = (
+                    section_size.0.min(world.width - origin.0),
+                    section_size.1.min(world.height - origin.1),
+                );
+                let mut cells = World::new(padded.0, padded.1);
+                for y in 0..size.1 {
+                    for x in 0..size.0 {
+                        let state = world.get(origin.0 + x, origin.1 + y);
+                        cells.set(MARGIN + x, MARGIN + y, state);
+                    }
+                }
+                let texture =
+                    Texture::from_image(device, queue, &cells.to_image(), Some("Section texture"))
+                        .unwrap();
+
+                let compute_bind_group = create_texture_bind_group(
+                    device,
+                    texture_bind_group_layout,
+                    &texture,
+                    &output,
+                    palette_buffer,
+                );
+                let uniform = SectionUniform {
+                    origin: [origin.0 as f32, origin.1 as f32],
+                    size: [size.0 as f32, size.1 as f32],
+                    board: [world.width as f32, world.height as f32],
+                    _padding: [0.0; 2],
+                };
+                let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+                    label: Some("Section buffer"),
+                    contents: bytemuck::cast_slice(&[uniform]),
+                    usage: BufferUsages::UNIFORM,
+                });
+                let render_bind_group = device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("Section bind group"),
+                    layout: &bind_group_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&texture.view),
+                        },
+                        BindGroupEntry {
+                            binding: 3,
+                            resource: palette_buffer.as_entire_binding(),
+                        },
+                        BindGroupEntry {
+                            binding: 4,
+                            resource: uniform_buffer.as_entire_binding(),
+                        },
+                    ],
+                });
+
+                sections.push(Section {
+                    origin,
+                    size,
+                    texture,
+                    compute_bind_group,
+                    render_bind_group,
+                });
+            }
+        }
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sections shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("automata/sections.wgsl").into()),
+        });
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Sections render pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout, camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Sections pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_section"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_section"),
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        return Sections {
+            width: world.width,
+            height: world.height,
+            section_size,
+            columns,
+            sections,
+            output,
+            render_pipeline,
+        };
+    }
+
+    /// Number of sections the board is split into.
+    pub fn count(&self) -> usize {
+        return self.sections.len();
+    }
+
+    /// Records a generation of the whole board into `encoder`, with the
+    /// board's compute pipeline and its bind groups for `@group(1)` onwards.
+    pub fn step(
+        &self,
+        encoder: &mut CommandEncoder,
+        pipeline: &wgpu::ComputePipeline,
+        bind_groups: &[&wgpu::BindGroup],
+        workgroup_size: (u32, u32),
+    ) {
+        // Every margin is filled before any section changes, so they all
+        // see the generation before.
+        let rows = self.sections.len() as u32 / self.columns;
+        for (index, section) in self.sections.iter().enumerate() {
+            let (column, row) = (index as u32 % self.columns, index as u32 / self.columns);
+            for dy in -1..=1i32 {
+                for dx in -1..=1i32 {
+                    let (x, y) = (column as i32 + dx, row as i32 + dy);
+                    if (dx, dy) == (0, 0)
+                        || x < 0
+                        || y < 0
+                        || x as u32 >= self.columns
+                        || y as u32 >= rows
+                    {
+                        continue;
+                    }
+                    let neighbour = &self.sections[(y as u32 * self.columns + x as u32) as usize];
+                    self.copy_margin(encoder, neighbour, section);
+                }
+            }
+        }
+
+        let padded = self.output.size;
+        for section in &self.sections {
+            {
+                let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("Section compute pass"),
+                    timestamp_writes: None,
+                });
+                compute_pass.set_bind_group(0, &section.compute_bind_group, &[]);
+                for (index, bind_group) in bind_groups.iter().enumerate() {
+                    compute_pass.set_bind_group(index as u32 + 1, *bind_group, &[]);
+                }
+                compute_pass.set_pipeline(pipeline);
+                let (workgroup_width, workgroup_height) = workgroup_size;
+                compute_pass.dispatch_workgroups(
+                    padded.width.div_ceil(workgroup_width),
+                    padded.height.div_ceil(workgroup_height),
+                    1,
+                );
+            }
+
+            // Cells past the edge of the board are left dead.
+            let interior = Origin3d {
+                x: MARGIN,
+                y: MARGIN,
+                z: 0,
+            };
+            copy_region(
+                encoder,
+                &self.output,
+                interior,
+                &section.texture,
+                interior,
+                tile_extent(section.size.0, section.size.1),
+            );
+        }
+    }
+
+    /// Records copying the cells of `from` that lie in the margin of `to`
+    /// into `encoder`.
+    fn copy_margin(&self, encoder: &mut CommandEncoder, from: &Section, to: &Section) {
+        let span = |from_origin: u32, from_size: u32, to_origin: u32, to_size: u32| {
+            let start = from_origin.max(to_origin.saturating_sub(MARGIN));
+            let end = (from_origin + from_size).min(to_origin + to_size + MARGIN);
+            return (start, end.saturating_sub(start));
+        };
+        let (left, width) = span(from.origin.0, from.size.0, to.origin.0, self.section_size.0);
+        let (top, height) = span(from.origin.1, from.size.1, to.origin.1, self.section_size.1);
+        if width == 0 || height == 0 {
+            return;
+        }
+        copy_region(
+            encoder,
+            &from.texture,
+            Origin3d {
+                x: MARGIN + left - from.origin.0,
+                y: MARGIN + top - from.origin.1,
+                z: 0,
+            },
+            &to.texture,
+            Origin3d {
+                x: MARGIN + left - to.origin.0,
+                y: MARGIN + top - to.origin.1,
+                z: 0,
+            },
+            tile_extent(width, height),
+        );
+    }
+
+    /// Sets the cell at `(x, y)` to `state`.
+    pub fn paint(&self, queue: &Queue, x: u32, y: u32, state: u8) {
+        if x >= self.width || y >= self.height {
+            return;
+        }
+        let (column, row) = (x / self.section_size.0, y / self.section_size.1);
+        let section = &self.sections[(row * self.columns + column) as usize];
+        let cell = Origin3d {
+            x: MARGIN + x - section.origin.0,
+            y: MARGIN + y - section.origin.1,
+            z: 0,
+        };
+        write_region(queue, &section.texture, cell, &[state, 0, 0, 255], 1);
+    }
+
+    /// Copies the board back from the GPU a section at a time, blocking
+    /// until each is available.
+    pub fn read_world(&self, device: &Device, queue: &Queue) -> anyhow::Result<World> {
+        let mut world = World::new(self.width, self.height);
+        for section in &self.sections {
+            let (width, height) = section.size;
+            let bytes_per_row = (4 * width).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Section readback buffer"),
+                size: (bytes_per_row * height) as u64,
+                usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Section readback"),
+            });
+            encoder.copy_texture_to_buffer(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &section.texture.texture,
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: MARGIN,
+                        y: MARGIN,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                wgpu::TexelCopyBufferInfo {
+                    buffer: &buffer,
+                    layout: wgpu::TexelCopyBufferLayout {
+                        offset: 0,
+                        bytes_per_row: Some(bytes_per_row),
+                        rows_per_image: Some(height),
+                    },
+                },
+                tile_extent(width, height),
+            );
+            queue.submit(std::iter::once(encoder.finish()));
+
+            let (sender, receiver) = mpsc::channel();
+            let slice = buffer.slice(..);
+            slice.map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+            device.poll(wgpu::PollType::wait_indefinitely())?;
+            receiver.recv()??;
+
+            let data = slice.get_mapped_range();
+            for (y, row) in data.chunks(bytes_per_row as usize).enumerate() {
+                for (x, texel) in row[..4 * width as usize].chunks(4).enumerate() {
+                    world.set(
+                        section.origin.0 + x as u32,
+                        section.origin.1 + y as u32,
+                        texel[0],
+                    );
+                }
+            }
+        }
+        return Ok(world);
+    }
+
+    /// Draws every section, with the board's camera bind group already set
+    /// on `render_pass`.
+    pub fn render(&self, render_pass: &mut RenderPass) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        for section in &self.sections {
+            render_pass.set_bind_group(0, &section.render_bind_group, &[]);
+            render_pass.draw(0..6, 0..1);
+        }
+    }
+}
@@ -0,0 +1,756 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::mpsc,
+};
+
+use log::warn;
+use wgpu::{
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BlendState, BufferUsages, ColorTargetState, ColorWrites, CommandEncoder, ComputePassDescriptor,
+    ComputePipelineDescriptor, Device, Extent3d, FragmentState, MultisampleState, Origin3d,
+    PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPass, RenderPipelineDescriptor,
+    ShaderStages, TextureViewDimension, VertexState,
+};
+
+use crate::{automata::ltl, renderer::create_texture_bind_group, texture::Texture, world::World};
+
+/// Cells along each side of a tile.
+pub const TILE_SIZE: u32 = 64;
+
+/// Cells around a tile copied from its neighbours before it is stepped,
+/// enough for the widest neighbourhood a rule can have. Match `MARGIN` in
+/// `tiles.wgsl`.
+pub(crate) const MARGIN: u32 = ltl::MAX_RADIUS;
+
+/// Tiles along each side of the atlases, which limits how many can be live
+/// at once.
+const ATLAS_TILES: u32 = 32;
+
+const SLOTS: u32 = ATLAS_TILES * ATLAS_TILES;
+
+/// Cells along each side of a tile with its margin, as kept in the scratch
+/// atlases.
+const SCRATCH_SIZE: u32 = TILE_SIZE + 2 * MARGIN;
+
+/// A tile being drawn, laid out as `Visible` in `tiles.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Visible {
+    tile: [i32; 2],
+    slot: u32,
+    _padding: u32,
+}
+
+/// A step whose live tiles are being read back.
+struct Reading {
+    /// The tiles stepped and the slots they were at.
+    tiles: Vec<((i32, i32), u32)>,
+    /// [`Tiles::steps`] once the step was taken.
+    step: u64,
+    /// Where the mapping reports back once it is done.
+    receiver: mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+/// An unbounded world, made of square tiles that only exist while they can
+/// have live cells on them. Each generation gathers the tiles that can be
+/// live and those next to them into a scratch atlas along with their
+/// margins, steps all of them in one dispatch of the board's compute
+/// pipeline, and frees the tiles too far from any live cells to have any.
+/// Tiles have the same coordinates as the board's cells, and are drawn with
+/// the board's camera.
+pub struct Tiles {
+    /// Size of the board the tiles were taken from, which sets the size
+    /// cells are drawn at.
+    board: (u32, u32),
+    /// The slot in the atlases each tile is kept at, by tile coordinates.
+    slots: HashMap<(i32, i32), u32>,
+    free: Vec<u32>,
+    atlas: Texture,
+    /// Binds the scratch atlases, holding every tile stepped with its margin
+    /// at its slot, and the rule's generation of them.
+    scratch_bind_group: wgpu::BindGroup,
+    visible_buffer: wgpu::Buffer,
+    visible_count: u32,
+    stepped_buffer: wgpu::Buffer,
+    neighbours_buffer: wgpu::Buffer,
+    /// Whether each slot has a live cell after the last step.
+    alive: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    reading: Option<Reading>,
+    /// Steps taken so far.
+    steps: u64,
+    /// Tiles last known to have live cells on them, with the step they were
+    /// known to at, from the readbacks and painting.
+    live: HashMap<(i32, i32), u64>,
+    bind_group: wgpu::BindGroup,
+    /// Gather tiles from the atlas into the scratch atlas, and scatter their
+    /// next generation back.
+    gather_bind_group: wgpu::BindGroup,
+    scatter_bind_group: wgpu::BindGroup,
+    gather_pipeline: wgpu::ComputePipeline,
+    scatter_pipeline: wgpu::ComputePipeline,
+    alive_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    /// Whether tiles couldn't be added because the atlas was full, so it is
+    /// only logged once.
+    full: bool,
+}
+
+impl Tiles {
+    /// Tiles holding the live cells of `world`. The scratch tile is bound
+    /// with `texture_bind_group_layout` for the board's compute pipeline, and
+    /// the board's camera at `@group(1)` when drawing.
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        format: wgpu::TextureFormat,
+        palette_buffer: &wgpu::Buffer,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        world: &World,
+    ) -> Tiles {
+        let create_texture = |size, label| {
+            Texture::from_image(
+                device,
+                queue,
+                &World::new(size, size).to_image(),
+                Some(label),
+            )
+            .unwrap()
+        };
+        let atlas = create_texture(ATLAS_TILES * TILE_SIZE, "Tile atlas");
+        let scratch = create_texture(ATLAS_TILES * SCRATCH_SIZE, "Scratch tile atlas");
+        let scratch_output =
+            create_texture(ATLAS_TILES * SCRATCH_SIZE, "Scratch tile atlas output");
+        let scratch_bind_group = create_texture_bind_group(
+            device,
+            texture_bind_group_layout,
+            &scratch,
+            &scratch_output,
+            palette_buffer,
+        );
+
+        let slot_buffer = |label, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: SLOTS as u64 * 4,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        let stepped_buffer = slot_buffer(
+            "Stepped tiles buffer",
+            BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        );
+        let alive = slot_buffer(
+            "Live tiles buffer",
+            BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+        );
+        let readback = slot_buffer(
+            "Live tiles readback buffer",
+            BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+        );
+        let neighbours_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Tile neighbours buffer"),
+            size: SLOTS as u64 * 9 * 4,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let visible_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Visible tiles buffer"),
+            size: (SLOTS as usize * std::mem::size_of::<Visible>()) as u64,
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let view_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Tile view buffer"),
+            size: 16,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let board = [world.width as f32, world.height as f32, 0.0, 0.0];
+        queue.write_buffer(&view_buffer, 0, bytemuck::cast_slice(&board));
+
+        let buffer_entry = |binding, visibility, ty| BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let read_only = wgpu::BufferBindingType::Storage { read_only: true };
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Tiles bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT | ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                buffer_entry(3, ShaderStages::FRAGMENT, read_only),
+                buffer_entry(4, ShaderStages::VERTEX, read_only),
+                buffer_entry(5, ShaderStages::VERTEX, wgpu::BufferBindingType::Uniform),
+                buffer_entry(6, ShaderStages::COMPUTE, read_only),
+                buffer_entry(
+                    7,
+                    ShaderStages::COMPUTE,
+                    wgpu::BufferBindingType::Storage { read_only: false },
+                ),
+            ],
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Tiles bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas.view),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: palette_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: visible_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: view_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 6,
+                    resource: stepped_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 7,
+                    resource: alive.as_entire_binding(),
+                },
+            ],
+        });
+
+        let step_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Tile step bind group layout"),
+            entries: &[
+                buffer_entry(6, ShaderStages::COMPUTE, read_only),
+                BindGroupLayoutEntry {
+                    binding: 8,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 9,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                buffer_entry(10, ShaderStages::COMPUTE, read_only),
+            ],
+        });
+        let create_step_bind_group = |label, from: &Texture, to: &Texture| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some(label),
+                layout: &step_bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 6,
+                        resource: stepped_buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 8,
+                        resource: wgpu::BindingResource::TextureView(&from.view),
+                    },
+                    BindGroupEntry {
+                        binding: 9,
+                        resource: wgpu::BindingResource::TextureView(&to.view),
+                    },
+                    BindGroupEntry {
+                        binding: 10,
+                        resource: neighbours_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        };
+        let gather_bind_group = create_step_bind_group("Tile gather bind group", &atlas, &scratch);
+        let scatter_bind_group =
+            create_step_bind_group("Tile scatter bind group", &scratch_output, &atlas);
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Tiles shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("automata/tiles.wgsl").into()),
+        });
+        let step_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Tile step pipeline layout"),
+            bind_group_layouts: &[&step_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_step_pipeline = |label, entry_point| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&step_pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let gather_pipeline = create_step_pipeline("Tile gather", "cm_gather");
+        let scatter_pipeline = create_step_pipeline("Tile scatter", "cm_scatter");
+        let alive_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Live tiles pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let alive_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Live tiles"),
+            layout: Some(&alive_pipeline_layout),
+            module: &shader,
+            entry_point: Some("cm_alive"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Tiles render pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout, camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Tiles pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &shader,
+                entry_point: Some("vs_tiles"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &shader,
+                entry_point: Some("fs_tiles"),
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let mut tiles = Tiles {
+            board: (world.width, world.height),
+            slots: HashMap::new(),
+            free: (0..SLOTS).rev().collect(),
+            atlas,
+            scratch_bind_group,
+            visible_buffer,
+            visible_count: 0,
+            stepped_buffer,
+            neighbours_buffer,
+            alive,
+            readback,
+            reading: None,
+            steps: 0,
+            live: HashMap::new(),
+            bind_group,
+            gather_bind_group,
+            scatter_bind_group,
+            gather_pipeline,
+            scatter_pipeline,
+            alive_pipeline,
+            render_pipeline,
+            full: false,
+        };
+
+        // Only the tiles with live cells on them are kept.
+        let size = TILE_SIZE as i32;
+        for tile_y in 0..world.height.div_ceil(TILE_SIZE) as i32 {
+            for tile_x in 0..world.width.div_ceil(TILE_SIZE) as i32 {
+                let cells: Vec<u8> = (0..TILE_SIZE as i32)
+                    .flat_map(|y| (0..size).map(move |x| (tile_x * size + x, tile_y * size + y)))
+                    .flat_map(|(x, y)| {
+                        let inside = (x as u32) < world.width && (y as u32) < world.height;
+                        let state = if inside {
+                            world.get(x as u32, y as u32)
+                        } else {
+                            0
+                        };
+                        [state, 0, 0, 255]
+                    })
+                    .collect();
+                if cells.chunks(4).all(|texel| texel[0] == 0) {
+                    continue;
+                }
+                let Some(slot) = tiles.allocate((tile_x, tile_y)) else {
+                    continue;
+                };
+                tiles.live.insert((tile_x, tile_y), 0);
+                write_region(queue, &tiles.atlas, slot_origin(slot), &cells, TILE_SIZE);
+            }
+        }
+        return tiles;
+    }
+
+    /// Number of tiles in use.
+    pub fn live(&self) -> usize {
+        return self.slots.len();
+    }
+
+    /// Records a generation of every tile that can be live and the tiles
+    /// next to them into `encoder`, with the board's compute pipeline and
+    /// its bind groups for `@group(1)` onwards. [`Tiles::collect`] picks up
+    /// which of them were left empty once it has been read back.
+    pub fn step(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        queue: &Queue,
+        pipeline: &wgpu::ComputePipeline,
+        bind_groups: &[&wgpu::BindGroup],
+        workgroup_size: (u32, u32),
+    ) {
+        // Cells move at most a tile a step, so a tile known to be live some
+        // steps ago can have reached that many tiles away since. Past the
+        // size of the atlas every tile is in use anyway.
+        let mut stepped = HashSet::new();
+        for (&(x, y), &known) in &self.live {
+            let reach = (self.steps - known).min(ATLAS_TILES as u64) as i32 + 1;
+            for dy in -reach..=reach {
+                for dx in -reach..=reach {
+                    stepped.insert((x + dx, y + dy));
+                }
+            }
+        }
+        // Every other tile is empty, and stays empty this step.
+        let empty: Vec<(i32, i32)> = self
+            .slots
+            .keys()
+            .filter(|tile| !stepped.contains(tile))
+            .copied()
+            .collect();
+        for tile in empty {
+            let slot = self.slots.remove(&tile).unwrap();
+            self.free.push(slot);
+        }
+
+        // New tiles start out empty, so they are gathered as if they weren't
+        // there.
+        let existing = self.slots.clone();
+        let mut tiles = Vec::new();
+        let mut slots = Vec::new();
+        let mut neighbours = Vec::new();
+        for tile in stepped {
+            let slot = match self.slots.get(&tile) {
+                Some(&slot) => slot,
+                None => match self.allocate(tile) {
+                    Some(slot) => slot,
+                    None => continue,
+                },
+            };
+            let (x, y) = tile;
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let neighbour = existing.get(&(x + dx, y + dy));
+                    neighbours.push(neighbour.map_or(-1, |&slot| slot as i32));
+                }
+            }
+            tiles.push((tile, slot));
+            slots.push(slot);
+        }
+        self.steps += 1;
+        if slots.is_empty() {
+            return;
+        }
+        queue.write_buffer(&self.stepped_buffer, 0, bytemuck::cast_slice(&slots));
+        queue.write_buffer(
+            &self.neighbours_buffer,
+            0,
+            bytemuck::cast_slice(&neighbours),
+        );
+
+        let count = slots.len() as u32;
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Tile gather pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_bind_group(0, &self.gather_bind_group, &[]);
+            compute_pass.set_pipeline(&self.gather_pipeline);
+            compute_pass.dispatch_workgroups(SCRATCH_SIZE / 8, SCRATCH_SIZE / 8, count);
+        }
+        {
+            // Cells only see as far as the margin, so tiles next to each
+            // other in the scratch atlas don't reach into one another.
+            let rows = slots.iter().max().unwrap() / ATLAS_TILES + 1;
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Tile compute pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_bind_group(0, &self.scratch_bind_group, &[]);
+            for (index, bind_group) in bind_groups.iter().enumerate() {
+                compute_pass.set_bind_group(index as u32 + 1, *bind_group, &[]);
+            }
+            compute_pass.set_pipeline(pipeline);
+            let (workgroup_width, workgroup_height) = workgroup_size;
+            compute_pass.dispatch_workgroups(
+                (ATLAS_TILES * SCRATCH_SIZE).div_ceil(workgroup_width),
+                (rows * SCRATCH_SIZE).div_ceil(workgroup_height),
+                1,
+            );
+        }
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Tile scatter pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_bind_group(0, &self.scatter_bind_group, &[]);
+            compute_pass.set_pipeline(&self.scatter_pipeline);
+            compute_pass.dispatch_workgroups(TILE_SIZE / 8, TILE_SIZE / 8, count);
+        }
+
+        encoder.clear_buffer(&self.alive, 0, None);
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Live tiles pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            compute_pass.set_pipeline(&self.alive_pipeline);
+            compute_pass.dispatch_workgroups(TILE_SIZE / 8, TILE_SIZE / 8, count);
+        }
+
+        // Only one step is read back at a time.
+        if self.reading.is_none() {
+            encoder.copy_buffer_to_buffer(&self.alive, 0, &self.readback, 0, SLOTS as u64 * 4);
+            let (sender, receiver) = mpsc::channel();
+            encoder.map_buffer_on_submit(&self.readback, wgpu::MapMode::Read, .., move |result| {
+                let _ = sender.send(result);
+            });
+            self.reading = Some(Reading {
+                tiles,
+                step: self.steps,
+                receiver,
+            });
+        }
+    }
+
+    /// Notes which tiles the step being read back left live or empty, if it
+    /// has arrived, without waiting for it. Tiles painted since keep the
+    /// step they were painted at.
+    pub fn collect(&mut self, device: &Device) -> anyhow::Result<()> {
+        let Some(reading) = &self.reading else {
+            return Ok(());
+        };
+        device.poll(wgpu::PollType::Poll)?;
+        let result = match reading.receiver.try_recv() {
+            Result::Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return Ok(()),
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.reading = None;
+                anyhow::bail!("Live tiles were never read back");
+            }
+        };
+        let reading = self.reading.take().unwrap();
+        result?;
+
+        {
+            let data = self.readback.slice(..).get_mapped_range();
+            let alive: &[u32] = bytemuck::cast_slice(&data);
+            for (tile, slot) in reading.tiles {
+                let newer = self
+                    .live
+                    .get(&tile)
+                    .is_some_and(|&known| known >= reading.step);
+                if newer {
+                    continue;
+                }
+                if alive[slot as usize] != 0 {
+                    self.live.insert(tile, reading.step);
+                } else {
+                    self.live.remove(&tile);
+                }
+            }
+        }
+        self.readback.unmap();
+        return Ok(());
+    }
+
+    /// Sets the cell at `(x, y)` to `state`, adding its tile if it isn't
+    /// there yet.
+    pub fn paint(&mut self, queue: &Queue, x: i64, y: i64, state: u8) {
+        let size = TILE_SIZE as i64;
+        let tile = (x.div_euclid(size) as i32, y.div_euclid(size) as i32);
+        let slot = match self.slots.get(&tile) {
+            Some(&slot) => slot,
+            None => {
+                let Some(slot) = self.allocate(tile) else {
+                    return;
+                };
+                let empty = [0, 0, 0, 255].repeat((TILE_SIZE * TILE_SIZE) as usize);
+                write_region(queue, &self.atlas, slot_origin(slot), &empty, TILE_SIZE);
+                slot
+            }
+        };
+        self.live.insert(tile, self.steps);
+        let origin = slot_origin(slot);
+        let cell = Origin3d {
+            x: origin.x + x.rem_euclid(size) as u32,
+            y: origin.y + y.rem_euclid(size) as u32,
+            z: 0,
+        };
+        write_region(queue, &self.atlas, cell, &[state, 0, 0, 255], 1);
+    }
+
+    /// Records copying the tiles over the board onto `board` into
+    /// `encoder`, leaving the rest of it as it is.
+    pub fn copy_to_board(&self, encoder: &mut CommandEncoder, board: &Texture) {
+        let (width, height) = self.board;
+        for (&(x, y), &slot) in &self.slots {
+            let (left, top) = (x * TILE_SIZE as i32, y * TILE_SIZE as i32);
+            if left < 0 || top < 0 || left as u32 >= width || top as u32 >= height {
+                continue;
+            }
+            let (left, top) = (left as u32, top as u32);
+            copy_region(
+                encoder,
+                &self.atlas,
+                slot_origin(slot),
+                board,
+                Origin3d {
+                    x: left,
+                    y: top,
+                    z: 0,
+                },
+                tile_extent(TILE_SIZE.min(width - left), TILE_SIZE.min(height - top)),
+            );
+        }
+    }
+
+    /// Picks out the tiles in view between the cells at `from` and `to`
+    /// for [`Tiles::render`].
+    pub fn show(&mut self, queue: &Queue, from: (f32, f32), to: (f32, f32)) {
+        let size = TILE_SIZE as f32;
+        let (left, top) = (
+            (from.0 / size).floor() as i32,
+            (from.1 / size).floor() as i32,
+        );
+        let (right, bottom) = ((to.0 / size).floor() as i32, (to.1 / size).floor() as i32);
+        let visible: Vec<Visible> = self
+            .slots
+            .iter()
+            .filter(|(&(x, y), _)| (left..=right).contains(&x) && (top..=bottom).contains(&y))
+            .map(|(&(x, y), &slot)| Visible {
+                tile: [x, y],
+                slot,
+                _padding: 0,
+            })
+            .collect();
+        queue.write_buffer(&self.visible_buffer, 0, bytemuck::cast_slice(&visible));
+        self.visible_count = visible.len() as u32;
+    }
+
+    /// Draws the tiles in view, with the board's camera bind group already
+    /// set on `render_pass`.
+    pub fn render(&self, render_pass: &mut RenderPass) {
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.draw(0..6, 0..self.visible_count);
+    }
+
+    fn allocate(&mut self, tile: (i32, i32)) -> Option<u32> {
+        let Some(slot) = self.free.pop() else {
+            if !self.full {
+                warn!("Every one of the {SLOTS} tiles is in use, the world can't grow further");
+                self.full = true;
+            }
+            return None;
+        };
+        self.slots.insert(tile, slot);
+        return Some(slot);
+    }
+}
+
+fn slot_origin(slot: u32) -> Origin3d {
+    return Origin3d {
+        x: slot % ATLAS_TILES * TILE_SIZE,
+        y: slot / ATLAS_TILES * TILE_SIZE,
+        z: 0,
+    };
+}
+
+pub(crate) fn tile_extent(width: u32, height: u32) -> Extent3d {
+    return Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+}
+
+pub(crate) fn copy_region(
+    encoder: &mut CommandEncoder,
+    from: &Texture,
+    from_origin: Origin3d,
+    to: &Texture,
+    to_origin: Origin3d,
+    size: Extent3d,
+) {
+    encoder.copy_texture_to_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &from.texture,
+            mip_level: 0,
+            origin: from_origin,
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::TexelCopyTextureInfo {
+            texture: &to.texture,
+            mip_level: 0,
+            origin: to_origin,
+            aspect: wgpu::TextureAspect::All,
+        },
+        size,
+    );
+}
+
+/// Writes a square of `size` cells of `texels` to `texture` at `origin`.
+pub(crate) fn write_region(
+    queue: &Queue,
+    texture: &Texture,
+    origin: Origin3d,
+    texels: &[u8],
+    size: u32,
+) {
+    queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &texture.texture,
+            mip_level: 0,
+            origin,
+            aspect: wgpu::TextureAspect::All,
+        },
+        texels,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * size),
+            rows_per_image: Some(size),
+        },
+        tile_extent(size, size),
+    );
+}
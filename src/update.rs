@@ -0,0 +1,226 @@
+use std::fmt;
+
+use anyhow::*;
+
+use crate::automata::Rule;
+
+/// How cells on the board take on the state the rule gives them. By default
+/// every cell follows the rule every generation, stochastic updates make
+/// births and survivals happen only by chance and asynchronous updates
+/// leave a random part of the board as it was. Written like
+/// `birth=0.9,survival=0.95,async=0.5,seed=42`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Update {
+    /// Chance a birth the rule makes happens.
+    pub birth: f32,
+    /// Chance a cell the rule keeps in its state stays in it, otherwise it
+    /// dies.
+    pub survival: f32,
+    /// Fraction of the cells updated each generation.
+    pub fraction: f32,
+    /// Seed of the random numbers deciding each cell, so a run can be
+    /// repeated.
+    pub seed: u64,
+}
+
+/// Layout of the `Update` uniform in `update.wgsl`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct UpdateUniform {
+    pub birth: f32,
+    pub survival: f32,
+    pub fraction: f32,
+    pub generation: u32,
+    pub seed: [u32; 2],
+    pub _padding: [u32; 2],
+}
+
+impl Default for Update {
+    fn default() -> Self {
+        return Update {
+            birth: 1.0,
+            survival: 1.0,
+            fraction: 1.0,
+            seed: 0,
+        };
+    }
+}
+
+impl Update {
+    /// Parses the settings, taking the seed from `seed` when none is given.
+    pub fn parse(text: &str, seed: impl FnOnce() -> u64) -> Result<Self> {
+        let mut update = Update::default();
+        let mut given_seed = None;
+        for pair in text
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected key=value, got {pair}"))?;
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "birth" => update.birth = parse_chance(value)?,
+                "survival" => update.survival = parse_chance(value)?,
+                "async" => update.fraction = parse_chance(value)?,
+                "seed" => given_seed = Some(value.parse()?),
+                _ => bail!("unknown update setting {key}"),
+            }
+        }
+        update.seed = given_seed.unwrap_or_else(seed);
+        return Ok(update);
+    }
+
+    /// Whether every cell follows the rule every generation, so there is
+    /// nothing for the update pass to do.
+    pub fn is_synchronous(&self) -> bool {
+        return self.birth == 1.0 && self.survival == 1.0 && self.fraction == 1.0;
+    }
+
+    /// Fails for rules the update pass can't run on. It only follows cells
+    /// stepped one at a time on the board, so it can't run on rules with a
+    /// volume, field or pile of their own, or on ants. Leaving some cells of
+    /// a block as they were would make or lose cells, and a spacetime
+    /// diagram's earlier rows would be taken for survivals.
+    pub fn check(&self, rule: &Rule) -> Result<()> {
+        if self.is_synchronous() {
+            return Ok(());
+        }
+        let reason = if rule.is_volume() {
+            "runs on a volume"
+        } else if rule.is_continuous() {
+            "has continuous cells"
+        } else if rule.is_sandpile() {
+            "counts grains"
+        } else if rule.has_agents() {
+            "moves ants"
+        } else if rule.is_one_dimensional() {
+            "draws a spacetime diagram"
+        } else if rule.updates_blocks() {
+            "moves whole blocks of cells"
+        } else {
+            return Ok(());
+        };
+        bail!("{rule} {reason}, so it can't update with {self}");
+    }
+
+    pub fn uniform(&self, generation: u32) -> UpdateUniform {
+        return UpdateUniform {
+            birth: self.birth,
+            survival: self.survival,
+            fraction: self.fraction,
+            generation,
+            seed: [self.seed as u32, (self.seed >> 32) as u32],
+            _padding: [0; 2],
+        };
+    }
+}
+
+impl fmt::Display for Update {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(
+            f,
+            "birth={},survival={},async={},seed={}",
+            self.birth, self.survival, self.fraction, self.seed
+        );
+    }
+}
+
+fn parse_chance(value: &str) -> Result<f32> {
+    let chance: f32 = value
+        .parse()
+        .with_context(|| format!("expected a chance, got {value:?}"))?;
+    if !(0.0..=1.0).contains(&chance) {
+        bail!("chances are between 0 and 1, got {chance}");
+    }
+    return Ok(chance);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logged_updates_repeat_the_run() {
+        // The seed is only drawn when none is given, and is written out with
+        // the chances so the run can be made again.
+        let update = Update::parse("birth=0.9,async=0.5", || 42).unwrap();
+        assert_eq!(update.to_string(), "birth=0.9,survival=1,async=0.5,seed=42");
+        let repeated = Update::parse(&update.to_string(), || unreachable!()).unwrap();
+        assert_eq!(repeated, update);
+    }
+
+    #[test]
+    fn only_chances_below_one_need_the_update_pass() {
+        assert!(Update::parse("seed=3", || 1).unwrap().is_synchronous());
+        assert!(Update::parse("birth=1,survival=1,async=1", || 1)
+            .unwrap()
+            .is_synchronous());
+        for text in ["birth=0.99", "survival=0", "async=0.5"] {
+            assert!(
+                !Update::parse(text, || 1).unwrap().is_synchronous(),
+                "{text}"
+            );
+        }
+        assert!(Update::parse("birth=1.5", || 1).is_err());
+        assert!(Update::parse("async=-0.1", || 1).is_err());
+    }
+
+    #[test]
+    fn splits_the_seed_for_the_shader() {
+        let update = Update::parse("birth=0.5,seed=4294967298", || 1).unwrap();
+        let uniform = update.uniform(9);
+        assert_eq!(uniform.seed, [2, 1]);
+        assert_eq!(uniform.generation, 9);
+        assert_eq!(
+            (uniform.birth, uniform.survival, uniform.fraction),
+            (0.5, 1.0, 1.0)
+        );
+    }
+
+    fn only_updates_synchronously(rule: &str) {
+        let rule = Rule::parse(rule).unwrap();
+        for text in ["birth=0.5", "survival=0.5", "async=0.5"] {
+            let update = Update::parse(text, || 1).unwrap();
+            assert!(update.check(&rule).is_err(), "{rule} with {text}");
+        }
+        assert!(Update::default().check(&rule).is_ok(), "{rule}");
+    }
+
+    #[test]
+    fn block_rules_only_update_synchronously() {
+        only_updates_synchronously("sand");
+        only_updates_synchronously("critters");
+        let update = Update::parse("async=0.5", || 1).unwrap();
+        assert!(update.check(&Rule::Life).is_ok());
+        assert!(update.check(&Rule::WireWorld).is_ok());
+    }
+
+    #[test]
+    fn volume_rules_only_update_synchronously() {
+        only_updates_synchronously("4/4/5/M");
+    }
+
+    #[test]
+    fn continuous_rules_only_update_synchronously() {
+        only_updates_synchronously("lenia");
+        only_updates_synchronously("smoothlife");
+        only_updates_synchronously("grayscott");
+    }
+
+    #[test]
+    fn sandpiles_only_update_synchronously() {
+        only_updates_synchronously("sandpile");
+    }
+
+    #[test]
+    fn ants_only_update_synchronously() {
+        only_updates_synchronously("RL");
+    }
+
+    #[test]
+    fn one_dimensional_rules_only_update_synchronously() {
+        only_updates_synchronously("W110");
+    }
+}
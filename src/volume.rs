@@ -0,0 +1,303 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor, BindGroupLayoutEntry,
+    BlendState, BufferUsages, ColorTargetState, ColorWrites, CommandEncoder, ComputePassDescriptor,
+    ComputePipelineDescriptor, Device, Extent3d, FragmentState, MultisampleState,
+    PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPass, RenderPipelineDescriptor,
+    ShaderStages, TextureUsages, TextureViewDimension, VertexState,
+};
+
+use crate::{
+    automata::Rule,
+    camera::OrbitCamera,
+    random::Random,
+};
+
+/// Number of voxels along each side of the volume.
+pub const VOLUME_SIZE: u32 = 64;
+
+/// The depth of the `@workgroup_size` of 3D rules, the width and height come
+/// from `Rule::workgroup_size`.
+const WORKGROUP_DEPTH: u32 = 4;
+
+/// The board for 3D rules, a cube of voxels stepped like the 2D board and
+/// drawn by ray marching through it.
+pub struct Volume {
+    size: Extent3d,
+    /// Read by the compute and render passes.
+    texture: wgpu::Texture,
+    /// Written by the compute pass, then copied back to `texture`.
+    output: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    compute_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    workgroup_size: (u32, u32),
+
+    pub camera: OrbitCamera,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+}
+
+impl Volume {
+    /// A volume running `rule`, starting from a random soup in the middle
+    /// third of the cube. The rule's parameters are bound by the caller at
+    /// `@group(2)`, with the layout `rule_bind_group_layout`.
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        format: wgpu::TextureFormat,
+        palette_buffer: &wgpu::Buffer,
+        rule_bind_group_layout: &wgpu::BindGroupLayout,
+        rule: &Rule,
+        seed: u64,
+    ) -> Volume {
+        let size = Extent3d {
+            width: VOLUME_SIZE,
+            height: VOLUME_SIZE,
+            depth_or_array_layers: VOLUME_SIZE,
+        };
+        let texture = create_volume_texture(device, size, "Volume texture");
+        let output = create_volume_texture(device, size, "Volume output texture");
+
+        let mut random = Random::new(seed);
+        let soup = VOLUME_SIZE / 3..2 * VOLUME_SIZE / 3;
+        let mut voxels = vec![0u8; (4 * VOLUME_SIZE * VOLUME_SIZE * VOLUME_SIZE) as usize];
+        for z in soup.clone() {
+            for y in soup.clone() {
+                for x in soup.clone() {
+                    let index = (((z * VOLUME_SIZE + y) * VOLUME_SIZE + x) * 4) as usize;
+                    voxels[index] = u8::from(random.next_f32() < 0.5);
+                    voxels[index + 3] = 255;
+                }
+            }
+        }
+        queue.write_texture(
+            texture.as_image_copy(),
+            &voxels,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * VOLUME_SIZE),
+                rows_per_image: Some(VOLUME_SIZE),
+            },
+            size,
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Volume bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT | ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D3,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba8Unorm,
+                        view_dimension: TextureViewDimension::D3,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: true },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Volume bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &texture.create_view(&Default::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(
+                        &output.create_view(&Default::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: palette_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let camera = OrbitCamera::default();
+        let camera_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Orbit camera buffer"),
+            contents: bytemuck::cast_slice(&[camera.uniform(1.0)]),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Orbit camera bind group layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let camera_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Orbit camera bind group"),
+            layout: &camera_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // Same group numbering as the board, so 3D rules find their
+        // parameters at `@group(2)` too.
+        let compute_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Volume compute pipeline layout"),
+            bind_group_layouts: &[
+                &bind_group_layout,
+                &camera_bind_group_layout,
+                rule_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let compute_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Volume automata shader"),
+            source: wgpu::ShaderSource::Wgsl(rule.shader().into()),
+        });
+        let compute_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Volume automata"),
+            layout: Some(&compute_pipeline_layout),
+            module: &compute_shader,
+            entry_point: Some("cm_main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Volume render pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout, &camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let render_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Volume shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("automata/volume.wgsl").into()),
+        });
+        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Volume pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: VertexState {
+                module: &render_shader,
+                entry_point: Some("vs_volume"),
+                compilation_options: Default::default(),
+                buffers: &[],
+            },
+            fragment: Some(FragmentState {
+                module: &render_shader,
+                entry_point: Some("fs_volume"),
+                compilation_options: Default::default(),
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::REPLACE),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        return Volume {
+            size,
+            texture,
+            output,
+            bind_group,
+            compute_pipeline,
+            render_pipeline,
+            workgroup_size: rule.workgroup_size(),
+            camera,
+            camera_buffer,
+            camera_bind_group,
+        };
+    }
+
+    /// Records a generation of the rule into `encoder`.
+    pub fn step(&self, encoder: &mut CommandEncoder, rule_bind_group: &wgpu::BindGroup) {
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Volume compute pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_bind_group(0, &self.bind_group, &[]);
+            compute_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+            compute_pass.set_bind_group(2, rule_bind_group, &[]);
+            compute_pass.set_pipeline(&self.compute_pipeline);
+
+            let (workgroup_width, workgroup_height) = self.workgroup_size;
+            compute_pass.dispatch_workgroups(
+                self.size.width.div_ceil(workgroup_width),
+                self.size.height.div_ceil(workgroup_height),
+                self.size.depth_or_array_layers.div_ceil(WORKGROUP_DEPTH),
+            );
+        }
+
+        encoder.copy_texture_to_texture(
+            self.output.as_image_copy(),
+            self.texture.as_image_copy(),
+            self.size,
+        );
+    }
+
+    pub fn update_camera(&self, queue: &Queue, aspect: f32) {
+        queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera.uniform(aspect)]),
+        );
+    }
+
+    pub fn render(&self, render_pass: &mut RenderPass) {
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_volume_texture(device: &Device, size: Extent3d, label: &str) -> wgpu::Texture {
+    return device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D3,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: TextureUsages::TEXTURE_BINDING
+            | TextureUsages::STORAGE_BINDING
+            | TextureUsages::COPY_DST
+            | TextureUsages::COPY_SRC,
+        view_formats: &[],
+    });
+}
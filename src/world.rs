@@ -0,0 +1,74 @@
+use image::{DynamicImage, RgbaImage};
+
+use crate::lattice::Lattice;
+
+/// Longest side of a board. Boards larger than a texture are split into
+/// sections, but still have to fit in memory.
+pub const MAX_SIDE: u32 = 1 << 16;
+
+/// The cell states of a board, one byte per cell in row-major order.
+///
+/// On the GPU each state lives in the red channel of the `Rgba8Unorm` board
+/// texture, so a state `s` is stored as `s / 255`.
+#[derive(Clone, Debug)]
+pub struct World {
+    pub width: u32,
+    pub height: u32,
+    /// How the rows and columns of cells tile the plane.
+    pub lattice: Lattice,
+    pub cells: Vec<u8>,
+}
+
+impl World {
+    /// An empty board on the square lattice.
+    pub fn new(width: u32, height: u32) -> Self {
+        return World {
+            width,
+            height,
+            lattice: Lattice::Square,
+            cells: vec![0; Self::area(width, height)],
+        };
+    }
+
+    /// Number of cells on a board, panicking if they can't be counted.
+    fn area(width: u32, height: u32) -> usize {
+        return (width as usize)
+            .checked_mul(height as usize)
+            .expect("board should fit in memory");
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        return y as usize * self.width as usize + x as usize;
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u8 {
+        return self.cells[self.index(x, y)];
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, state: u8) {
+        let index = self.index(x, y);
+        self.cells[index] = state;
+    }
+
+    /// Copies `pattern` onto the board with its top left corner at `(x, y)`,
+    /// dropping any cells that fall outside.
+    pub fn paste(&mut self, pattern: &World, x: u32, y: u32) {
+        for py in 0..pattern.height.min(self.height.saturating_sub(y)) {
+            for px in 0..pattern.width.min(self.width.saturating_sub(x)) {
+                self.set(x + px, y + py, pattern.get(px, py));
+            }
+        }
+    }
+
+    /// The board encoded the way the compute shaders expect it.
+    pub fn to_image(&self) -> DynamicImage {
+        let rgba = self
+            .cells
+            .iter()
+            .flat_map(|&state| [state, 0, 0, 255])
+            .collect();
+        let img = RgbaImage::from_raw(self.width, self.height, rgba)
+            .expect("cells should match the board dimensions");
+        return DynamicImage::ImageRgba8(img);
+    }
+}